@@ -0,0 +1,61 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Measures the throughput of `Pcre::par_matches()` with different numbers of threads, and
+//! of matching the subjects one after another with `Pcre::exec()`.
+//!
+//! Run with `cargo bench --features rayon`.
+
+#[macro_use]
+extern crate criterion;
+extern crate pcre;
+extern crate rayon;
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use pcre::{Pcre};
+use rayon::{ThreadPoolBuilder};
+use std::thread;
+
+const RECORDS: usize = 100_000;
+
+/// Returns log-like records, about a tenth of which match the pattern below.
+fn records() -> Vec<String> {
+    (0..RECORDS).map(|i| {
+        let level = if i % 10 == 0 { "ERROR" } else { "INFO" };
+        format!("2015-06-{:02}T12:{:02}:{:02}Z {} request id={} user=user{}@example.com took {}ms",
+                i % 28 + 1, i / 60 % 60, i % 60, level, i, i % 977, i * 31 % 1000)
+    }).collect()
+}
+
+fn bench_par_matches(c: &mut Criterion) {
+    let records = records();
+    let subjects: Vec<&str> = records.iter().map(|record| &record[..]).collect();
+    let re = Pcre::builder(r"ERROR request id=(\d+) user=(\S+)@(\S+) took (\d+)ms").jit(true).build().unwrap();
+
+    let mut group = c.benchmark_group("par_matches");
+    group.throughput(Throughput::Elements(RECORDS as u64));
+    group.sample_size(20);
+
+    group.bench_function("exec", |b| b.iter(|| {
+        subjects.iter().filter(|subject| re.exec(subject).is_some()).count()
+    }));
+
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut threads = 1;
+    while threads <= cores {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::new("threads", threads), &threads, |b, _| b.iter(|| {
+            pool.install(|| re.par_matches(&subjects)).iter().filter(|m| m.is_some()).count()
+        }));
+        threads = if threads < cores && threads * 2 > cores { cores } else { threads * 2 };
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_par_matches);
+criterion_main!(benches);
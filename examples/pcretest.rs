@@ -0,0 +1,505 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// A test driver in the style of the pcre project's `pcretest` program, using rust-pcre
+// bindings.
+//
+// The input is a sequence of blocks, each a pattern such as `/(a+)b/ig` followed by
+// subject lines and ended by a blank line. A pattern may span several lines. Besides the
+// compile flags of `pcre::flags::parse_flags()`, these modifiers are supported:
+//
+//  * `g` finds all matches,
+//  * `I` prints information about the compiled pattern,
+//  * `K` prints the MARK of each match,
+//  * `S` studies the pattern, and `S+` compiles it with the JIT compiler.
+//
+// Subject lines are trimmed and may contain the escapes `\n`, `\t`, `\r`, `\e`, `\a`, `\f`,
+// `\v`, `\\`, `\xhh`, `\x{hhh}` and octal `\ddd`, along with `\A` (anchored), `\B`
+// (not BOL), `\Z` (not EOL), `\N` (not empty), `\P` (partial match) and `\P\P` (hard
+// partial match).
+//
+// With `--check EXPECTED`, the output is compared with the file EXPECTED instead of being
+// printed, and the program exits with status 1 at the first difference. For example:
+//
+//     cargo run --example pcretest -- -q --check examples/testdata/testoutput1 examples/testdata/testinput1
+
+extern crate enum_set;
+extern crate getopts;
+extern crate pcre;
+
+use enum_set::{EnumSet};
+use getopts::{Options};
+use pcre::{CompileOption, ExecOption, Match, PartialResult, Pcre, StudyOption, pcre_version};
+use pcre::CompileOption::*;
+use pcre::flags;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::process;
+use std::string::{String};
+use std::vec::{Vec};
+
+/// The names of the compile options printed by the `I` modifier, in pcretest's order.
+const OPTION_NAMES: &[(CompileOption, &str)] = &[
+    (Anchored, "anchored"),
+    (Caseless, "caseless"),
+    (Extended, "extended"),
+    (Multiline, "multiline"),
+    (FirstLine, "firstline"),
+    (DotAll, "dotall"),
+    (BsrAnyCRLF, "bsr_anycrlf"),
+    (BsrUnicode, "bsr_unicode"),
+    (DollarEndOnly, "dollar_endonly"),
+    (Extra, "extra"),
+    (Ungreedy, "ungreedy"),
+    (NoAutoCapture, "no_auto_capture"),
+    (Ucp, "ucp"),
+    (DupNames, "dupnames"),
+    (JavaScriptCompat, "javascript_compat")
+];
+
+/// A compiled pattern and its modifiers.
+struct Test {
+
+    re: Pcre,
+
+    global: bool,
+
+    show_mark: bool
+
+}
+
+/// Reads blocks from `input` and writes the results to `out`.
+struct Tester<R: BufRead, W: Write> {
+
+    input: R,
+
+    out: W,
+
+    /// Whether to prompt for input rather than echo it.
+    interactive: bool
+
+}
+
+fn print_usage(program: &str, opts: &Options) {
+    let brief = format!("Usage: {} [options] [input [output]]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn print_version_info() {
+    println!("rust-pcre 0.2.3 compiled against libpcre {}", pcre_version());
+}
+
+/// Formats `text` for output, escaping characters which are not printable ASCII.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if (' '..='~').contains(&c) {
+            escaped.push(c);
+        } else {
+            escaped.push_str(&format!("\\x{{{:x}}}", c as u32));
+        }
+    }
+    escaped
+}
+
+/// Like `escape()`, but for text which can split characters, such as the groups of a `\C`,
+/// whose invalid bytes are written as `\x{hh}` like `pcretest` does.
+fn escape_bytes(text: &[u8]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for chunk in text.utf8_chunks() {
+        escaped.push_str(&escape(chunk.valid()));
+        for &b in chunk.invalid() {
+            escaped.push_str(&format!("\\x{{{:x}}}", b));
+        }
+    }
+    escaped
+}
+
+/// Parses the digits at the start of `chars` in base `radix`, consuming at most `max`.
+fn parse_digits(chars: &mut ::std::iter::Peekable<::std::str::Chars>, radix: u32, max: usize, value: u32) -> u32 {
+    let mut value = value;
+    for _ in 0..max {
+        match chars.peek().and_then(|c| c.to_digit(radix)) {
+            None => break,
+            Some(digit) => {
+                value = value * radix + digit;
+                chars.next();
+            }
+        }
+    }
+    value
+}
+
+/// Decodes the escapes of a subject line, returning the subject and its exec options.
+fn parse_subject(line: &str) -> Result<(String, EnumSet<ExecOption>), String> {
+    let mut subject = String::new();
+    let mut options = EnumSet::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            subject.push(c);
+            continue;
+        }
+        let code = match chars.next() {
+            None => break,
+            Some('a') => 0x07,
+            Some('e') => 0x1b,
+            Some('f') => 0x0c,
+            Some('n') => 0x0a,
+            Some('r') => 0x0d,
+            Some('t') => 0x09,
+            Some('v') => 0x0b,
+            Some(c @ '0'..='7') => parse_digits(&mut chars, 8, 2, c.to_digit(8).unwrap()),
+            Some('x') => if chars.peek() == Some(&'{') {
+                chars.next();
+                let code = parse_digits(&mut chars, 16, 8, 0);
+                if chars.next() != Some('}') {
+                    return Err("missing } after \\x{".to_string());
+                }
+                code
+            } else {
+                parse_digits(&mut chars, 16, 2, 0)
+            },
+            Some(c) => {
+                match c {
+                    'A' => options.insert(ExecOption::ExecAnchored),
+                    'B' => options.insert(ExecOption::ExecNotBol),
+                    'Z' => options.insert(ExecOption::ExecNotEol),
+                    'N' => options.insert(ExecOption::ExecNotEmpty),
+                    'P' => if options.remove(&ExecOption::ExecPartialSoft) {
+                        options.insert(ExecOption::ExecPartialHard)
+                    } else {
+                        options.insert(ExecOption::ExecPartialSoft)
+                    },
+                    c => {
+                        subject.push(c);
+                        true
+                    }
+                };
+                continue;
+            }
+        };
+        match ::std::char::from_u32(code) {
+            None => return Err(format!("invalid character code {:x}", code)),
+            Some(c) => subject.push(c)
+        }
+    }
+    Ok((subject, options))
+}
+
+impl<R: BufRead, W: Write> Tester<R, W> {
+    /// Reads a line, prompting with `prompt` or echoing the line.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        if self.interactive {
+            write!(self.out, "{}", prompt)?;
+            self.out.flush()?;
+        }
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.ends_with('\n') {
+            line.push('\n');
+        }
+        if !self.interactive {
+            self.out.write_all(line.as_bytes())?;
+        }
+        Ok(Some(line))
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        while let Some(line) = self.read_line("  re> ")? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let test = self.read_pattern(line)?;
+            while let Some(line) = self.read_line("data> ")? {
+                let line = line.trim();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(ref test) = test {
+                    self.run_subject(test, line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the rest of a pattern which starts on `line` and compiles it. Errors are
+    /// printed, and the subject lines of a pattern which did not compile are skipped.
+    fn read_pattern(&mut self, line: String) -> io::Result<Option<Test>> {
+        let line = line.trim_start();
+        let delimiter = line.chars().next().unwrap();
+        if delimiter.is_alphanumeric() || delimiter == '\\' {
+            writeln!(self.out, "** Delimiter must not be alphanumeric or \\")?;
+            return Ok(None);
+        }
+
+        let mut pattern = String::new();
+        let mut rest = line[delimiter.len_utf8()..].to_string();
+        let modifiers = loop {
+            let mut chars = rest.char_indices();
+            let mut end = None;
+            while let Some((i, c)) = chars.next() {
+                if c == '\\' {
+                    chars.next();
+                } else if c == delimiter {
+                    end = Some(i);
+                    break;
+                }
+            }
+            if let Some(end) = end {
+                pattern.push_str(&rest[..end]);
+                break rest[(end + delimiter.len_utf8())..].trim().to_string();
+            }
+            pattern.push_str(&rest);
+            rest = match self.read_line("    > ")? {
+                None => {
+                    writeln!(self.out, "** Unexpected EOF")?;
+                    return Ok(None);
+                },
+                Some(line) => line
+            };
+        };
+
+        let mut global = false;
+        let mut info = false;
+        let mut show_mark = false;
+        let mut study = EnumSet::new();
+        let mut study_wanted = false;
+        let mut compile_flags = String::new();
+        let mut chars = modifiers.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                'g' => global = true,
+                'I' => info = true,
+                'K' => show_mark = true,
+                'S' => {
+                    study_wanted = true;
+                    if chars.peek() == Some(&'+') {
+                        chars.next();
+                        study.insert(StudyOption::StudyJitCompile);
+                        study.insert(StudyOption::StudyJitPartialSoftCompile);
+                        study.insert(StudyOption::StudyJitPartialHardCompile);
+                    }
+                },
+                '<' => {
+                    compile_flags.push(c);
+                    for c in chars.by_ref() {
+                        compile_flags.push(c);
+                        if c == '>' {
+                            break;
+                        }
+                    }
+                },
+                c if c.is_whitespace() => {},
+                c => compile_flags.push(c)
+            }
+        }
+        let options = match flags::parse_flags(&compile_flags) {
+            Err(err) => {
+                writeln!(self.out, "** {}", err)?;
+                return Ok(None);
+            },
+            Ok(options) => options
+        };
+
+        let mut re = match Pcre::compile_with_options(&pattern, &options) {
+            Err(err) => {
+                writeln!(self.out, "Failed: {} at offset {}", err.message().unwrap_or_default(), err.offset())?;
+                return Ok(None);
+            },
+            Ok(re) => re
+        };
+        if study_wanted {
+            re.study_with_options(&study);
+        }
+        if info {
+            self.print_info(&re)?;
+        }
+        Ok(Some(Test { re, global, show_mark }))
+    }
+
+    fn print_info(&mut self, re: &Pcre) -> io::Result<()> {
+        writeln!(self.out, "Capturing subpattern count = {}", re.capture_count())?;
+        let name_table = re.name_table();
+        if !name_table.is_empty() {
+            writeln!(self.out, "Named capturing subpatterns:")?;
+            let width = name_table.keys().map(|name| name.len()).max().unwrap_or(0);
+            for (name, ns) in name_table.iter() {
+                for n in ns.iter() {
+                    writeln!(self.out, "  {:width$} {:>3}", name, n, width = width)?;
+                }
+            }
+        }
+        let options = re.compile_options();
+        let names: Vec<&str> = OPTION_NAMES.iter()
+            .filter(|&&(option, _)| options.contains(&option))
+            .map(|&(_, name)| name)
+            .collect();
+        if names.is_empty() {
+            writeln!(self.out, "No options")
+        } else {
+            writeln!(self.out, "Options: {}", names.join(" "))
+        }
+    }
+
+    fn print_match(&mut self, test: &Test, m: &Match) -> io::Result<()> {
+        for i in 0..m.string_count() {
+            if m.group_start(i) == usize::MAX {
+                writeln!(self.out, "{:2}: <unset>", i)?;
+            } else {
+                writeln!(self.out, "{:2}: {}", i, escape_bytes(m.group_bytes(i)))?;
+            }
+        }
+        if test.show_mark {
+            if let Some(mark) = m.mark() {
+                writeln!(self.out, "MK: {}", escape(mark))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_subject(&mut self, test: &Test, line: &str) -> io::Result<()> {
+        let (subject, options) = match parse_subject(line) {
+            Err(err) => return writeln!(self.out, "** {}", err),
+            Ok(subject) => subject
+        };
+
+        let partial = options.contains(&ExecOption::ExecPartialSoft) || options.contains(&ExecOption::ExecPartialHard);
+        if partial {
+            return match test.re.exec_partial(&subject, 0, &options) {
+                PartialResult::Match(m) => self.print_match(test, &m),
+                PartialResult::Partial { start, .. } => writeln!(self.out, "Partial match: {}", escape(&subject[start..])),
+                PartialResult::NoMatch => writeln!(self.out, "No match")
+            };
+        }
+
+        let mut matched = false;
+        if test.global {
+            for m in test.re.matches_with_options(&subject, &options) {
+                matched = true;
+                self.print_match(test, &m)?;
+            }
+        } else if let Some(m) = test.re.exec_from_with_options(&subject, 0, &options) {
+            matched = true;
+            self.print_match(test, &m)?;
+        }
+        if !matched {
+            writeln!(self.out, "No match")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares `actual` with `expected`, printing the first difference with a line of context.
+fn check(actual: &str, expected: &str, expected_path: &str) -> bool {
+    let actual: Vec<&str> = actual.lines().collect();
+    let expected: Vec<&str> = expected.lines().collect();
+    let n = match (0..actual.len().max(expected.len())).find(|&i| actual.get(i) != expected.get(i)) {
+        None => return true,
+        Some(n) => n
+    };
+    eprintln!("{}:{}: output differs", expected_path, n + 1);
+    if n > 0 {
+        eprintln!("  {}", expected[n - 1]);
+    }
+    eprintln!("- {}", expected.get(n).unwrap_or(&"<end of file>"));
+    eprintln!("+ {}", actual.get(n).unwrap_or(&"<end of file>"));
+    false
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optopt("", "check", "compare the output with EXPECTED instead of printing it", "EXPECTED");
+    opts.optflag("q", "", "do not print the version line");
+    opts.optflag("h", "help", "print usage and exit");
+    opts.optflag("", "version", "print version information and exit");
+    let opt_matches = match opts.parse(&args[1..]) {
+        Ok(m)  => m,
+        Err(f) => {
+            eprintln!("Error: {}", f);
+            process::exit(2);
+        }
+    };
+
+    if opt_matches.opt_present("h") {
+        print_usage(&program, &opts);
+        return;
+    }
+
+    if opt_matches.opt_present("version") {
+        print_version_info();
+        return;
+    }
+
+    if opt_matches.free.len() > 2 {
+        eprintln!("Error: Too many command line arguments");
+        process::exit(2);
+    }
+
+    let input: Box<dyn BufRead> = match opt_matches.free.first() {
+        None => Box::new(BufReader::new(io::stdin())),
+        Some(path) => match File::open(path) {
+            Err(err) => {
+                eprintln!("Error: {}: {}", path, err);
+                process::exit(2);
+            },
+            Ok(file) => Box::new(BufReader::new(file))
+        }
+    };
+    let interactive = opt_matches.free.is_empty() && io::stdin().is_terminal();
+    let header = if opt_matches.opt_present("q") {
+        String::new()
+    } else {
+        format!("PCRE version {}\n\n", pcre_version())
+    };
+
+    if let Some(expected_path) = opt_matches.opt_str("check") {
+        let expected = match fs::read_to_string(&expected_path) {
+            Err(err) => {
+                eprintln!("Error: {}: {}", expected_path, err);
+                process::exit(2);
+            },
+            Ok(expected) => expected
+        };
+        let mut tester = Tester { input, out: header.into_bytes(), interactive: false };
+        if let Err(err) = tester.run() {
+            eprintln!("Error: {}", err);
+            process::exit(2);
+        }
+        if !check(&String::from_utf8(tester.out).unwrap(), &expected, &expected_path) {
+            process::exit(1);
+        }
+        return;
+    }
+
+    let out: Box<dyn Write> = match opt_matches.free.get(1) {
+        None => Box::new(io::stdout()),
+        Some(path) => match File::create(path) {
+            Err(err) => {
+                eprintln!("Error: {}: {}", path, err);
+                process::exit(2);
+            },
+            Ok(file) => Box::new(io::BufWriter::new(file))
+        }
+    };
+    let mut tester = Tester { input, out, interactive };
+    let result = tester.out.write_all(header.as_bytes())
+        .and_then(|_| tester.run())
+        .and_then(|_| tester.out.flush());
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        process::exit(2);
+    }
+}
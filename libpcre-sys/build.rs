@@ -18,10 +18,13 @@ use std::path::{Path};
 use std::process::{Command};
 use tar::{Archive};
 
-const BUNDLED_PCRE_VERSION: &'static str = "8.39";
+const BUNDLED_PCRE_VERSION: &str = "8.39";
 
 fn main() {
-    if pkg_config::Config::new().atleast_version("8.20").find("libpcre").is_ok() {
+    // Setting LIBPCRE_SYS_BUNDLED builds the bundled copy even if libpcre is installed,
+    // for example to run the conformance tests, whose expected output is for this version.
+    println!("cargo:rerun-if-env-changed=LIBPCRE_SYS_BUNDLED");
+    if env::var_os("LIBPCRE_SYS_BUNDLED").is_none() && pkg_config::Config::new().atleast_version("8.20").find("libpcre").is_ok() {
         return;
     }
 
@@ -35,7 +38,7 @@ fn main() {
     let decompressor = BzDecoder::new(pcre_tbz2_f);
 
     let mut archive = Archive::new(decompressor);
-    if !archive.unpack(&out_dir).is_ok() {
+    if archive.unpack(&out_dir).is_err() {
         panic!("failed to extract the tarball");
     }
 
@@ -8,7 +8,7 @@
 
 extern crate libc;
 
-use libc::{c_char, c_int, c_uchar, c_ulong, c_void};
+use libc::{c_char, c_int, c_uchar, c_ulong, c_void, size_t};
 use std::option::{Option};
 use std::ptr;
 
@@ -20,23 +20,44 @@ pub type exec_options = c_int;
 pub type fullinfo_field = c_int;
 #[allow(non_camel_case_types)]
 pub type study_options = c_int;
+#[allow(non_camel_case_types)]
+pub type config_field = c_int;
 
 pub const PCRE_UTF8: compile_options = 0x00000800;
 
+// The newline conventions are both compile and exec options
+pub const PCRE_NEWLINE_CR: c_int = 0x00100000;
+pub const PCRE_NEWLINE_LF: c_int = 0x00200000;
+pub const PCRE_NEWLINE_CRLF: c_int = 0x00300000;
+pub const PCRE_NEWLINE_ANY: c_int = 0x00400000;
+pub const PCRE_NEWLINE_ANYCRLF: c_int = 0x00500000;
+pub const PCRE_NEWLINE_MASK: c_int = 0x00700000;
+
 // PCRE_NO_UTF8_CHECK is both a compile and exec option
 pub const PCRE_NO_UTF8_CHECK: c_int = 0x00002000;
 
 pub const PCRE_ERROR_NOMATCH: c_int = -1;
 pub const PCRE_ERROR_NULL: c_int = -2;
-
+pub const PCRE_ERROR_MATCHLIMIT: c_int = -8;
+pub const PCRE_ERROR_CALLOUT: c_int = -9;
+pub const PCRE_ERROR_PARTIAL: c_int = -12;
+pub const PCRE_ERROR_RECURSIONLIMIT: c_int = -21;
+pub const PCRE_ERROR_JIT_STACKLIMIT: c_int = -27;
+
+pub const PCRE_INFO_OPTIONS: fullinfo_field = 0;
+pub const PCRE_INFO_SIZE: fullinfo_field = 1;
 pub const PCRE_INFO_CAPTURECOUNT: fullinfo_field = 2;
 pub const PCRE_INFO_NAMEENTRYSIZE: fullinfo_field = 7;
 pub const PCRE_INFO_NAMECOUNT: fullinfo_field = 8;
 pub const PCRE_INFO_NAMETABLE: fullinfo_field = 9;
+pub const PCRE_INFO_JIT: fullinfo_field = 16;
+pub const PCRE_INFO_MAXLOOKBEHIND: fullinfo_field = 18;
+
+pub const PCRE_CONFIG_NEWLINE: config_field = 1;
 
 //const PCRE_EXTRA_STUDY_DATA: c_ulong = 0x0001;
 const PCRE_EXTRA_MATCH_LIMIT: c_ulong = 0x0002;
-//const PCRE_EXTRA_CALLOUT_DATA: c_ulong = 0x0004;
+const PCRE_EXTRA_CALLOUT_DATA: c_ulong = 0x0004;
 //const PCRE_EXTRA_TABLES: c_ulong = 0x0008;
 const PCRE_EXTRA_MATCH_LIMIT_RECURSION: c_ulong = 0x0010;
 const PCRE_EXTRA_MARK: c_ulong = 0x0020;
@@ -46,6 +67,10 @@ const PCRE_EXTRA_MARK: c_ulong = 0x0020;
 pub enum pcre {}
 
 #[allow(non_camel_case_types)]
+pub enum pcre_jit_stack {}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone)]
 #[repr(C)]
 pub struct pcre_extra {
     flags: c_ulong,
@@ -58,6 +83,39 @@ pub struct pcre_extra {
     executable_jit: *mut c_void
 }
 
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub struct pcre_callout_block {
+    pub version: c_int,
+    pub callout_number: c_int,
+    pub offset_vector: *mut c_int,
+    pub subject: *const c_char,
+    pub subject_length: c_int,
+    pub start_match: c_int,
+    pub current_position: c_int,
+    pub capture_top: c_int,
+    pub capture_last: c_int,
+    pub callout_data: *mut c_void,
+    pub pattern_position: c_int,
+    pub next_item_length: c_int,
+    pub mark: *const c_uchar
+}
+
+impl Default for pcre_extra {
+    fn default() -> pcre_extra {
+        pcre_extra {
+            flags: 0,
+            study_data: ptr::null_mut(),
+            match_limit_: 0,
+            callout_data: ptr::null_mut(),
+            tables: ptr::null(),
+            match_limit_recursion_: 0,
+            mark: ptr::null_mut(),
+            executable_jit: ptr::null_mut()
+        }
+    }
+}
+
 impl pcre_extra {
     /// Returns the match limit, if previously set by [set_match_limit()](#method.set_match_limit).
     ///
@@ -90,10 +148,13 @@ impl pcre_extra {
     /// Sets the recursion depth limit to `limit` instead of using PCRE's default.
     pub fn set_match_limit_recursion(&mut self, limit: u32) {
         self.flags |= PCRE_EXTRA_MATCH_LIMIT_RECURSION;
-        self.match_limit_ = limit as c_ulong;
+        self.match_limit_recursion_ = limit as c_ulong;
     }
 
     /// Sets the mark field.
+    ///
+    /// # Safety
+    /// `mark` must remain valid for as long as this extra block is passed to `pcre_exec()`.
     pub unsafe fn set_mark(&mut self, mark: &mut *mut c_uchar) {
         self.flags |= PCRE_EXTRA_MARK;
         self.mark = mark as *mut *mut c_uchar;
@@ -104,16 +165,42 @@ impl pcre_extra {
         self.flags &= !PCRE_EXTRA_MARK;
         self.mark = ptr::null_mut();
     }
+
+    /// Sets the callout data field. PCRE passes `data` to the callout function in the
+    /// `callout_data` field of the callout block.
+    ///
+    /// # Safety
+    /// `data` must remain valid for as long as this extra block is passed to `pcre_exec()`.
+    pub unsafe fn set_callout_data(&mut self, data: *mut c_void) {
+        self.flags |= PCRE_EXTRA_CALLOUT_DATA;
+        self.callout_data = data;
+    }
+
+    /// Unsets the callout data field.
+    pub fn unset_callout_data(&mut self) {
+        self.flags &= !PCRE_EXTRA_CALLOUT_DATA;
+        self.callout_data = ptr::null_mut();
+    }
 }
 
 #[link(name = "pcre")]
-extern {
+extern "C" {
+    pub static pcre_malloc: extern "C" fn(size: size_t) -> *mut c_void;
     pub static pcre_free: extern "C" fn(ptr: *mut c_void);
+    // Note: libpcre's pcre_callout is a process-wide function pointer.
+    pub static mut pcre_callout: Option<extern "C" fn(block: *mut pcre_callout_block) -> c_int>;
 
+    pub fn pcre_config(what: config_field, where_: *mut c_void) -> c_int;
     pub fn pcre_compile(pattern: *const c_char, options: compile_options, errptr: *mut *const c_char, erroffset: *mut c_int, tableptr: *const c_uchar) -> *mut pcre;
     pub fn pcre_exec(code: *const pcre, extra: *const pcre_extra, subject: *const c_char, length: c_int, startoffset: c_int, options: exec_options, ovector: *mut c_int, ovecsize: c_int) -> c_int;
     pub fn pcre_free_study(extra: *mut pcre_extra);
+    pub fn pcre_maketables() -> *const c_uchar;
+    pub fn pcre_pattern_to_host_byte_order(code: *mut pcre, extra: *mut pcre_extra, tables: *const c_uchar) -> c_int;
     pub fn pcre_fullinfo(code: *const pcre, extra: *const pcre_extra, what: fullinfo_field, where_: *mut c_void) -> c_int;
+    pub fn pcre_assign_jit_stack(extra: *mut pcre_extra, callback: Option<extern "C" fn(data: *mut c_void) -> *mut pcre_jit_stack>, data: *mut c_void);
+    pub fn pcre_jit_exec(code: *const pcre, extra: *const pcre_extra, subject: *const c_char, length: c_int, startoffset: c_int, options: exec_options, ovector: *mut c_int, ovecsize: c_int, jstack: *mut pcre_jit_stack) -> c_int;
+    pub fn pcre_jit_stack_alloc(startsize: c_int, maxsize: c_int) -> *mut pcre_jit_stack;
+    pub fn pcre_jit_stack_free(stack: *mut pcre_jit_stack);
     // Note: libpcre's pcre_refcount() function is not thread-safe.
     pub fn pcre_refcount(code: *mut pcre, adjust: c_int) -> c_int;
     pub fn pcre_study(code: *const pcre, options: study_options, errptr: *mut *const c_char) -> *mut pcre_extra;
@@ -0,0 +1,257 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The `pcre!` macro, which checks regular expressions when the program is built. Use it
+//! through the `pcre` crate's `macros` feature.
+
+extern crate libc;
+extern crate libpcre_sys;
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use libc::{c_char, c_int, c_uchar, c_void};
+use libpcre_sys::{PCRE_INFO_NAMECOUNT, PCRE_INFO_NAMEENTRYSIZE, PCRE_INFO_NAMETABLE, PCRE_NO_UTF8_CHECK, PCRE_UTF8};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{quote, quote_spanned};
+use std::collections::{BTreeMap};
+use std::ffi::{CStr, CString};
+use std::ptr;
+use syn::{Attribute, LitStr, Token, Visibility};
+use syn::parse::{Parse, ParseStream};
+
+/// One `static NAME: Captures = "pattern";` item.
+struct PcreItem {
+
+    attrs: Vec<Attribute>,
+
+    vis: Visibility,
+
+    name: Ident,
+
+    captures: Ident,
+
+    pattern: LitStr
+
+}
+
+struct PcreItems {
+
+    items: Vec<PcreItem>
+
+}
+
+impl Parse for PcreItem {
+    fn parse(input: ParseStream) -> syn::Result<PcreItem> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![static]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let captures = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let pattern = input.parse()?;
+        input.parse::<Token![;]>()?;
+        Ok(PcreItem { attrs, vis, name, captures, pattern })
+    }
+}
+
+impl Parse for PcreItems {
+    fn parse(input: ParseStream) -> syn::Result<PcreItems> {
+        let mut items = Vec::new();
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+        Ok(PcreItems { items })
+    }
+}
+
+/// Compiles `pattern` with the same options as `Pcre::compile()`, returning the name table,
+/// or the error message and offset.
+fn compile(pattern: &str) -> Result<BTreeMap<String, Vec<usize>>, (String, usize)> {
+    let pattern_cstring = match CString::new(pattern) {
+        Err(err) => return Err(("pattern contains a NUL character".to_string(), err.nul_position())),
+        Ok(pattern_cstring) => pattern_cstring
+    };
+    unsafe {
+        let mut err: *const c_char = ptr::null();
+        let mut erroffset: c_int = 0;
+        let code = libpcre_sys::pcre_compile(pattern_cstring.as_ptr(), PCRE_UTF8 | PCRE_NO_UTF8_CHECK, &mut err, &mut erroffset, ptr::null::<c_uchar>());
+        if code.is_null() {
+            let message = CStr::from_ptr(err).to_string_lossy().into_owned();
+            return Err((message, erroffset as usize));
+        }
+
+        let mut name_count: c_int = 0;
+        let mut name_entry_size: c_int = 0;
+        let mut tabptr: *const c_uchar = ptr::null();
+        libpcre_sys::pcre_fullinfo(code, ptr::null(), PCRE_INFO_NAMECOUNT, &mut name_count as *mut c_int as *mut c_void);
+        libpcre_sys::pcre_fullinfo(code, ptr::null(), PCRE_INFO_NAMEENTRYSIZE, &mut name_entry_size as *mut c_int as *mut c_void);
+        libpcre_sys::pcre_fullinfo(code, ptr::null(), PCRE_INFO_NAMETABLE, &mut tabptr as *mut *const c_uchar as *mut c_void);
+
+        let mut name_table: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for _ in 0..name_count {
+            let n = ((*tabptr as usize) << 8) | (*tabptr.offset(1) as usize);
+            let name = CStr::from_ptr(tabptr.offset(2) as *const c_char).to_string_lossy().into_owned();
+            name_table.entry(name).or_default().push(n);
+            tabptr = tabptr.offset(name_entry_size as isize);
+        }
+
+        (libpcre_sys::pcre_free)(code as *mut c_void);
+        Ok(name_table)
+    }
+}
+
+/// Returns the identifier for the accessor of the group named `name`, which is a raw
+/// identifier if `name` is a keyword.
+fn accessor(name: &str) -> Option<Ident> {
+    if syn::parse_str::<Ident>(name).is_ok() {
+        Some(Ident::new(name, Span::call_site()))
+    } else if syn::parse_str::<Ident>(&format!("r#{}", name)).is_ok() {
+        Some(Ident::new_raw(name, Span::call_site()))
+    } else {
+        None
+    }
+}
+
+/// Returns a `compile_error!` invocation which points at `pattern`.
+///
+/// Errors are not reported with `syn::Error::to_compile_error()` because it refers to
+/// `::core`, which is not in scope in 2015 edition crates.
+fn compile_error(pattern: &LitStr, message: &str) -> TokenStream {
+    quote_spanned! { pattern.span() => compile_error!(#message); }
+}
+
+fn expand(item: PcreItem) -> TokenStream {
+    let PcreItem { attrs, vis, name, captures, pattern } = item;
+    let name_table = match compile(&pattern.value()) {
+        Err((message, offset)) => {
+            let message = format!("invalid pattern at offset {}: {}", offset, message);
+            return compile_error(&pattern, &message);
+        },
+        Ok(name_table) => name_table
+    };
+
+    let mut accessors = Vec::new();
+    for (group_name, ns) in name_table.iter() {
+        let method = match accessor(group_name) {
+            None => {
+                let message = format!("the group name `{}` cannot be used as a method name", group_name);
+                return compile_error(&pattern, &message);
+            },
+            Some(method) => method
+        };
+        let doc = format!("Returns the substring for the group named `{}`, if it participated in the match.", group_name);
+        accessors.push(quote! {
+            #[doc = #doc]
+            pub fn #method(&self) -> ::std::option::Option<&'a str> {
+                let m = &self.m;
+                [#(#ns),*].iter().cloned()
+                    .find(|&n| n < m.string_count() && m.group_start(n) != ::std::usize::MAX)
+                    .map(|n| m.group(n))
+            }
+        });
+    }
+
+    let captures_doc = format!("A match of [`{}`], with an accessor for each named group.", name);
+    quote! {
+        #(#attrs)*
+        #vis static #name: ::pcre::LazyPcre<#captures<'static>> = ::pcre::LazyPcre::new(#pattern);
+
+        #[doc = #captures_doc]
+        #vis struct #captures<'a> {
+            m: ::pcre::Match<'a>
+        }
+
+        impl<'x> ::pcre::CaptureGroups for #captures<'x> {
+            type Captures<'a> = #captures<'a>;
+
+            fn from_match(m: ::pcre::Match<'_>) -> #captures<'_> {
+                #captures { m }
+            }
+        }
+
+        impl<'a> ::std::ops::Deref for #captures<'a> {
+            type Target = ::pcre::Match<'a>;
+
+            fn deref(&self) -> &::pcre::Match<'a> {
+                &self.m
+            }
+        }
+
+        impl<'a> #captures<'a> {
+            #(#accessors)*
+        }
+    }
+}
+
+/// Declares statics holding regular expressions which are checked when the program is
+/// built, each with a captures type that has an accessor per named group.
+///
+/// Each item has the form `static NAME: Captures = "pattern";`, optionally with attributes
+/// and a visibility. The pattern is compiled with the linked libpcre, and an invalid
+/// pattern is a compile error. `NAME` is a [`LazyPcre`], so it is compiled again the first
+/// time that it is used at run time, and `Captures` is a new type which derefs to a
+/// `Match`. Compile options can be set with inline option settings such as `(?i)`.
+///
+/// # Example
+/// ```
+/// extern crate pcre;
+/// use pcre::pcre;
+///
+/// pcre! {
+///     /// An ISO 8601 date.
+///     static DATE: Date = r"(?<year>\d{4})-(?<month>\d{2})(?:-(?<day>\d{2}))?";
+/// }
+///
+/// let date = DATE.captures("due 2015-06").unwrap();
+/// assert_eq!(date.year(), Some("2015"));
+/// assert_eq!(date.month(), Some("06"));
+/// assert_eq!(date.day(), None);
+/// assert_eq!(date.group(0), "2015-06");
+/// ```
+///
+/// An invalid pattern does not compile:
+/// ```compile_fail
+/// extern crate pcre;
+/// use pcre::pcre;
+///
+/// pcre! {
+///     static BAD: Bad = "(abc";
+/// }
+/// ```
+///
+/// Neither does a misspelled group name:
+/// ```compile_fail
+/// extern crate pcre;
+/// use pcre::pcre;
+///
+/// pcre! {
+///     static DATE: Date = r"(?<year>\d{4})";
+/// }
+///
+/// DATE.captures("2015").unwrap().yaer();
+/// ```
+///
+/// [`LazyPcre`]: ../pcre/struct.LazyPcre.html
+#[proc_macro]
+pub fn pcre(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let items: PcreItems = match syn::parse(input) {
+        Err(err) => {
+            let message = err.to_string();
+            return quote_spanned!(err.span() => compile_error!(#message);).into();
+        },
+        Ok(items) => items
+    };
+    let mut output = TokenStream::new();
+    for item in items.items {
+        output.extend(expand(item));
+    }
+    output.into()
+}
@@ -0,0 +1,50 @@
+extern crate pcre;
+
+use pcre::pcre;
+use std::thread;
+
+pcre! {
+    static DATE: Date = r"(?<year>\d{4})-(?<month>\d{2})(?:-(?<day>\d{2}))?";
+
+    /// Several groups with the same name, and a group named after a keyword.
+    pub(crate) static DUP: Dup = r"(?J)(?:(?<n>a+)|(?<n>b+))(?<type>c)?";
+}
+
+#[test]
+fn test_pcre_macro_captures() {
+    let date = DATE.captures("on 2015-06-30.").unwrap();
+    assert_eq!(date.year(), Some("2015"));
+    assert_eq!(date.month(), Some("06"));
+    assert_eq!(date.day(), Some("30"));
+    assert_eq!(date.group(0), "2015-06-30");
+
+    let date = DATE.captures("2015-06").unwrap();
+    assert_eq!(date.day(), None);
+
+    assert!(DATE.captures("no date").is_none());
+    assert_eq!(DATE.capture_count(), 3);
+}
+
+#[test]
+fn test_pcre_macro_iter() {
+    let years: Vec<&str> = DATE.captures_iter("2014-01, 2015-02").map(|date| date.year().unwrap()).collect();
+    assert_eq!(years, vec!["2014", "2015"]);
+}
+
+#[test]
+fn test_pcre_macro_dup_names() {
+    assert_eq!(DUP.captures("aa").unwrap().n(), Some("aa"));
+    let dup = DUP.captures("bbc").unwrap();
+    assert_eq!(dup.n(), Some("bb"));
+    assert_eq!(dup.r#type(), Some("c"));
+}
+
+#[test]
+fn test_pcre_macro_threads() {
+    let threads: Vec<_> = (0..4).map(|i| thread::spawn(move || {
+        let subject = format!("201{}-01", i);
+        DATE.captures(&subject).unwrap().year().unwrap().to_string()
+    })).collect();
+    let years: Vec<String> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert_eq!(years, vec!["2010", "2011", "2012", "2013"]);
+}
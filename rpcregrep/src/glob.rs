@@ -0,0 +1,77 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Shell-style globs for `--include`, `--exclude` and `--exclude-dir`, translated into
+//! regular expressions.
+
+use pcre::{Pcre};
+use std::string::{String};
+
+/// A glob which is matched against file names.
+pub struct Glob {
+
+    re: Pcre
+
+}
+
+/// Appends `c` to `pattern`, escaped if it is not alphanumeric.
+fn push_literal(pattern: &mut String, c: char) {
+    if !c.is_alphanumeric() {
+        pattern.push('\\');
+    }
+    pattern.push(c);
+}
+
+impl Glob {
+    /// Parses `glob`, in which `*` matches any characters, `?` matches one character, and
+    /// `[...]` (or `[!...]`) matches one character in (or not in) a set. A `\` escapes the
+    /// next character.
+    pub fn new(glob: &str) -> Result<Glob, String> {
+        let mut pattern = String::from("^");
+        let mut chars = glob.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => pattern.push_str(".*"),
+                '?' => pattern.push('.'),
+                '\\' => match chars.next() {
+                    None => return Err(format!("invalid glob {:?}: trailing backslash", glob)),
+                    Some(c) => push_literal(&mut pattern, c)
+                },
+                '[' => {
+                    pattern.push('[');
+                    if let Some('!') | Some('^') = chars.clone().next() {
+                        pattern.push('^');
+                        chars.next();
+                    }
+                    // A `]` right after the opening bracket is part of the set.
+                    let mut first = true;
+                    loop {
+                        match chars.next() {
+                            None => return Err(format!("invalid glob {:?}: unclosed `[`", glob)),
+                            Some(']') if !first => break,
+                            Some('-') if !first => pattern.push('-'),
+                            Some(c) => push_literal(&mut pattern, c)
+                        }
+                        first = false;
+                    }
+                    pattern.push(']');
+                },
+                c => push_literal(&mut pattern, c)
+            }
+        }
+        pattern.push('$');
+
+        let re = Pcre::builder(&pattern).dotall(true).build().map_err(|err| format!("invalid glob {:?}: {}", glob, err))?;
+        Ok(Glob { re })
+    }
+
+    /// Returns `true` if `name` matches the glob.
+    pub fn is_match(&self, name: &str) -> bool {
+        self.re.is_match(name)
+    }
+}
@@ -0,0 +1,550 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `rpcregrep`, a `pcregrep`-like search tool.
+//!
+//! The exit status is 0 if a line was selected, 1 if no line was selected, and 2 if an
+//! error occurred. Errors in individual files, and lines on which matching exceeds the
+//! `--match-limit` or the `--recursion-limit`, are reported and the search continues.
+
+extern crate getopts;
+extern crate pcre;
+extern crate serde_json;
+
+mod glob;
+mod printer;
+
+use getopts::{Matches, Options};
+use glob::{Glob};
+use pcre::{ExecError, Newline, Pcre, pcre_version};
+use pcre::inspect::{has_numbered_reference};
+use pcre::search::{Searcher, Summary};
+use printer::{Printer};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::{Path};
+use std::process;
+use std::string::{String};
+use std::vec::{Vec};
+
+/// What is printed for each file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Matching lines and context lines.
+    Lines,
+
+    /// The matched parts of matching lines (`-o`).
+    OnlyMatching,
+
+    /// The number of matching lines (`-c`).
+    Count,
+
+    /// The names of files with a matching line (`-l`).
+    FilesWithMatches
+}
+
+struct Config {
+
+    re: Pcre,
+
+    searcher: Searcher,
+
+    mode: Mode,
+
+    with_filename: bool,
+
+    recursive: bool,
+
+    no_messages: bool,
+
+    /// Whether the output is flushed after each line.
+    line_buffered: bool,
+
+    include: Vec<Glob>,
+
+    exclude: Vec<Glob>,
+
+    exclude_dir: Vec<Glob>
+
+}
+
+/// The state of a run over all the inputs.
+struct Grep<W: Write> {
+
+    config: Config,
+
+    printer: Printer<W>,
+
+    /// Whether a line was selected in any input.
+    matched: bool,
+
+    /// Whether an error was reported.
+    failed: bool
+
+}
+
+/// The reason that the search of an input stopped.
+enum SearchError {
+    /// The input could not be read. The search continues with the next input.
+    Input(io::Error),
+
+    /// The output could not be written, for example because stdout was closed. The
+    /// search stops.
+    Output(io::Error)
+}
+
+const STDIN_NAME: &str = "(standard input)";
+
+fn print_usage(program: &str, opts: &Options) {
+    let brief = format!("Usage: {} [options] pattern [file...]\n       {} [options] -e pattern... [file...]", program, program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("rpcregrep: {}", message);
+    eprintln!("Try `rpcregrep --help` for more information.");
+    process::exit(2);
+}
+
+fn parse_number<T: std::str::FromStr>(opt_matches: &Matches, name: &str) -> Option<T> {
+    opt_matches.opt_str(name).map(|value| match value.parse() {
+        Err(_) => usage_error(&format!("invalid number {:?} for --{}", value, name)),
+        Ok(n) => n
+    })
+}
+
+fn parse_globs(opt_matches: &Matches, name: &str) -> Vec<Glob> {
+    opt_matches.opt_strs(name).iter().map(|glob| match Glob::new(glob) {
+        Err(message) => usage_error(&message),
+        Ok(glob) => glob
+    }).collect()
+}
+
+/// Builds the combined regular expression from the patterns and options.
+///
+/// Several patterns are combined into an alternation, in which the capture groups of all
+/// of the patterns are numbered together, so a pattern which refers to a group by number
+/// is rejected.
+fn build_regex(patterns: &[String], opt_matches: &Matches) -> Pcre {
+    if patterns.len() > 1 {
+        if let Some(pattern) = patterns.iter().find(|pattern| has_numbered_reference(pattern, &Default::default())) {
+            usage_error(&format!("pattern {:?} refers to a group by number, which is not supported with several patterns; \
+                                  use a named group or a relative reference such as \\g{{-1}}", pattern));
+        }
+    }
+    let (before, after) = if opt_matches.opt_present("x") {
+        ("^(?:", ")$")
+    } else if opt_matches.opt_present("w") {
+        (r"\b(?:", r")\b")
+    } else {
+        ("(?:", ")")
+    };
+    let pattern = patterns.iter().map(|pattern| format!("{}{}{}", before, pattern, after)).collect::<Vec<_>>().join("|");
+
+    let mut builder = Pcre::builder(&pattern);
+    builder.caseless(opt_matches.opt_present("i")).jit(!opt_matches.opt_present("no-jit"));
+    if let Some(limit) = parse_number(opt_matches, "match-limit") {
+        builder.match_limit(limit);
+    }
+    if let Some(limit) = parse_number(opt_matches, "recursion-limit") {
+        builder.match_limit_recursion(limit);
+    }
+    if let Some(newline) = opt_matches.opt_str("newline") {
+        builder.newline(match &newline.to_lowercase()[..] {
+            "cr" => Newline::Cr,
+            "lf" => Newline::Lf,
+            "crlf" => Newline::Crlf,
+            "any" => Newline::Any,
+            "anycrlf" => Newline::AnyCrlf,
+            _ => usage_error(&format!("invalid newline convention {:?}", newline))
+        });
+    }
+
+    match builder.build() {
+        Err(err) => {
+            // Report the error against the pattern that the user wrote.
+            let message = if patterns.len() == 1 { format!("{}", err) } else { format!("in the combined pattern {:?}: {}", pattern, err) };
+            eprintln!("rpcregrep: invalid pattern: {}", message);
+            process::exit(2);
+        },
+        Ok(re) => re
+    }
+}
+
+impl<W: Write> Grep<W> {
+    fn error(&mut self, path: &str, err: &io::Error) {
+        self.failed = true;
+        if !self.config.no_messages {
+            eprintln!("rpcregrep: {}: {}", path, err);
+        }
+    }
+
+    /// Searches the file or directory at `path`. `explicit` is set for paths given on the
+    /// command line.
+    fn search_path(&mut self, path: &Path, explicit: bool) -> io::Result<()> {
+        let display = path.to_string_lossy().into_owned();
+        let metadata = match fs::metadata(path) {
+            Err(err) => {
+                self.error(&display, &err);
+                return Ok(());
+            },
+            Ok(metadata) => metadata
+        };
+        let name = path.file_name().map_or(display.clone(), |name| name.to_string_lossy().into_owned());
+
+        if metadata.is_dir() {
+            if !self.config.recursive {
+                if !self.config.no_messages {
+                    eprintln!("rpcregrep: {}: Is a directory", display);
+                }
+                return Ok(());
+            }
+            if !explicit && self.config.exclude_dir.iter().any(|glob| glob.is_match(&name)) {
+                return Ok(());
+            }
+            let mut entries: Vec<_> = match fs::read_dir(path).and_then(|entries| entries.collect::<io::Result<Vec<_>>>()) {
+                Err(err) => {
+                    self.error(&display, &err);
+                    return Ok(());
+                },
+                Ok(entries) => entries.into_iter().map(|entry| entry.path()).collect()
+            };
+            entries.sort();
+            for entry in entries {
+                self.search_path(&entry, false)?;
+            }
+            return Ok(());
+        }
+
+        let config = &self.config;
+        if (!config.include.is_empty() && !config.include.iter().any(|glob| glob.is_match(&name)))
+            || config.exclude.iter().any(|glob| glob.is_match(&name)) {
+            return Ok(());
+        }
+        match File::open(path) {
+            Err(err) => {
+                self.error(&display, &err);
+                Ok(())
+            },
+            Ok(file) => self.search_file(&display, file)
+        }
+    }
+
+    /// Searches an open file, which is read again from the offset of its binary data, if
+    /// any.
+    fn search_file<R: Read + Seek>(&mut self, display: &str, mut file: R) -> io::Result<()> {
+        let result = self.search_reader(display, BufReader::new(&mut file));
+        self.finish_search(display, result, |re, offset, on_error| {
+            file.seek(SeekFrom::Start(offset))?;
+            Searcher::new().count_with_errors(re, BufReader::new(&mut file), on_error)
+        })
+    }
+
+    /// Handles the result of `search_reader()`. If binary data was found, `rest` counts the
+    /// matching lines from the offset of the line where it was found, passing the lines on
+    /// which matching failed to the callback that it is given.
+    fn finish_search<F>(&mut self, display: &str, result: Result<Option<(u64, u64)>, SearchError>, rest: F) -> io::Result<()>
+        where F: FnOnce(&Pcre, u64, &mut dyn FnMut(u64, ExecError) -> bool) -> io::Result<Summary> {
+        match result {
+            Err(SearchError::Output(err)) => Err(err),
+            Err(SearchError::Input(err)) => {
+                self.error(display, &err);
+                Ok(())
+            },
+            Ok(None) => Ok(()),
+            Ok(Some((count, offset))) => {
+                // The lines of binary data are not printed, so neither are their numbers.
+                let mut exceeded = None;
+                let rest = rest(&self.config.re, offset, &mut |_, err| {
+                    exceeded = Some(err);
+                    true
+                });
+                if let Some(err) = exceeded {
+                    self.failed = true;
+                    eprintln!("rpcregrep: {}: {} in binary data", display, err);
+                }
+                match rest {
+                    Err(err) => {
+                        self.error(display, &err);
+                        Ok(())
+                    },
+                    Ok(rest) => self.finish_binary(display, count, rest.matched_lines)
+                }
+            }
+        }
+    }
+
+    /// Searches `reader`, printing its results unless binary data is found. In that case,
+    /// returns the number of matching lines before the binary data, and its offset.
+    fn search_reader<R: BufRead>(&mut self, display: &str, reader: R) -> Result<Option<(u64, u64)>, SearchError> {
+        let path = if self.config.with_filename { Some(display) } else { None };
+        let mut searcher = self.config.searcher.clone();
+        let mode = self.config.mode;
+        if mode == Mode::FilesWithMatches {
+            searcher.max_count(Some(1));
+        }
+        // A line on which matching exceeds a limit is reported, and the search goes on, as
+        // pcregrep does.
+        let failed = &mut self.failed;
+        let on_error = |number, err| {
+            *failed = true;
+            eprintln!("rpcregrep: {}: line {}: {}", display, number, err);
+            true
+        };
+        let summary = if mode == Mode::Count || mode == Mode::FilesWithMatches {
+            searcher.count_with_errors(&self.config.re, reader, on_error).map_err(SearchError::Input)?
+        } else {
+            let printer = &mut self.printer;
+            let mut output_error = None;
+            printer.begin_file();
+            let line_buffered = self.config.line_buffered;
+            let summary = searcher.search_with_errors(&self.config.re, reader, |line| match printer.line(path, line).and_then(|()| {
+                if line_buffered { printer.flush() } else { Ok(()) }
+            }) {
+                Err(err) => {
+                    output_error = Some(err);
+                    false
+                },
+                Ok(()) => true
+            }, on_error);
+            if let Some(err) = output_error {
+                return Err(SearchError::Output(err));
+            }
+            summary.map_err(SearchError::Input)?
+        };
+
+        self.matched |= summary.matched_lines > 0;
+        if let Some(offset) = summary.binary_offset {
+            return Ok(Some((summary.matched_lines, offset)));
+        }
+        let result = match mode {
+            Mode::Count => self.printer.count(path, summary.matched_lines),
+            Mode::FilesWithMatches if summary.matched_lines > 0 => self.printer.file(display),
+            _ => Ok(())
+        };
+        result.map_err(SearchError::Output)?;
+        Ok(None)
+    }
+
+    /// Prints the results for a file with binary data, given the number of matching lines
+    /// before the binary data and after it.
+    fn finish_binary(&mut self, display: &str, before: u64, after: u64) -> io::Result<()> {
+        let path = if self.config.with_filename { Some(display) } else { None };
+        let count = if self.config.mode == Mode::FilesWithMatches { before.max(after).min(1) } else { before + after };
+        self.matched |= count > 0;
+        match self.config.mode {
+            Mode::Count => self.printer.count(path, count),
+            Mode::FilesWithMatches if count > 0 => self.printer.file(display),
+            Mode::Lines | Mode::OnlyMatching if after > 0 => self.printer.binary(display),
+            _ => Ok(())
+        }
+    }
+
+    /// Searches standard input line by line as it is read, so that the results for input
+    /// such as the output of `tail -f` are printed as they are found.
+    fn search_stdin(&mut self) -> io::Result<()> {
+        let stdin = io::stdin();
+        let mut reader = LastLine { reader: stdin.lock(), line: Vec::new() };
+        let result = self.search_reader(STDIN_NAME, &mut reader);
+        // The search stops after reading the line with binary data, so the rest is
+        // counted from a copy of that line.
+        self.finish_search(STDIN_NAME, result, |re, _, on_error| {
+            let LastLine { reader, line } = reader;
+            Searcher::new().count_with_errors(re, io::Cursor::new(line).chain(reader), on_error)
+        })
+    }
+}
+
+/// A reader which keeps a copy of the last line that was read from it.
+struct LastLine<R> {
+
+    reader: R,
+
+    line: Vec<u8>
+
+}
+
+impl<R: BufRead> Read for LastLine<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = {
+            let available = self.fill_buf()?;
+            let n = available.len().min(buf.len());
+            buf[..n].copy_from_slice(&available[..n]);
+            n
+        };
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for LastLine<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if amt == 0 {
+            return;
+        }
+        if self.line.last() == Some(&b'\n') {
+            self.line.clear();
+        }
+        // The consumed bytes are still in the buffer, so this does not read.
+        if let Ok(buf) = self.reader.fill_buf() {
+            self.line.extend_from_slice(&buf[..amt]);
+        }
+        self.reader.consume(amt);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optmulti("e", "regexp", "search for PATTERN; can be given several times", "PATTERN");
+    opts.optflag("i", "ignore-case", "ignore case distinctions");
+    opts.optflag("w", "word-regexp", "match only whole words");
+    opts.optflag("x", "line-regexp", "match only whole lines");
+    opts.optflag("v", "invert-match", "select non-matching lines");
+    opts.optflag("c", "count", "print only a count of matching lines per file");
+    opts.optflag("l", "files-with-matches", "print only the names of files with matches");
+    opts.optflag("o", "only-matching", "print only the matched parts of lines");
+    opts.optflag("n", "line-number", "print line numbers");
+    opts.optflag("H", "with-filename", "print the file name for each match");
+    opts.optflag("h", "no-filename", "do not print file names");
+    opts.optopt("A", "after-context", "print NUM lines of context after matches", "NUM");
+    opts.optopt("B", "before-context", "print NUM lines of context before matches", "NUM");
+    opts.optopt("C", "context", "print NUM lines of context around matches", "NUM");
+    opts.optopt("m", "max-count", "stop after NUM matching lines per file", "NUM");
+    opts.optflag("r", "recursive", "search directories recursively");
+    opts.optmulti("", "include", "search only files whose name matches GLOB", "GLOB");
+    opts.optmulti("", "exclude", "skip files whose name matches GLOB", "GLOB");
+    opts.optmulti("", "exclude-dir", "skip directories whose name matches GLOB", "GLOB");
+    opts.optflag("s", "no-messages", "suppress error messages about files");
+    opts.optflagopt("", "color", "highlight matches: never (the default), always or auto", "WHEN");
+    opts.optflag("", "json", "print results as JSON lines");
+    opts.optflag("", "line-buffered", "flush the output after each line (the default on a terminal)");
+    opts.optflag("", "no-jit", "do not JIT-compile the pattern");
+    opts.optopt("", "match-limit", "limit the internal match function calls per match", "NUM");
+    opts.optopt("", "recursion-limit", "limit the recursion depth of matching", "NUM");
+    opts.optopt("N", "newline", "newline convention: cr, lf, crlf, any or anycrlf", "TYPE");
+    opts.optflag("", "help", "print usage and exit");
+    opts.optflag("V", "version", "print version information and exit");
+    let opt_matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => usage_error(&f.to_string())
+    };
+
+    if opt_matches.opt_present("help") {
+        print_usage(&program, &opts);
+        return;
+    }
+    if opt_matches.opt_present("version") {
+        println!("rpcregrep {} using libpcre {}", env!("CARGO_PKG_VERSION"), pcre_version());
+        return;
+    }
+
+    let mut free = opt_matches.free.clone();
+    let patterns = if opt_matches.opt_present("e") {
+        opt_matches.opt_strs("e")
+    } else if free.is_empty() {
+        usage_error("no pattern");
+    } else {
+        vec![free.remove(0)]
+    };
+    let re = build_regex(&patterns, &opt_matches);
+
+    let mut searcher = Searcher::new();
+    searcher.invert(opt_matches.opt_present("v")).binary_detection(true).max_count(parse_number(&opt_matches, "m"));
+    if let Some(n) = parse_number(&opt_matches, "C") {
+        searcher.context(n);
+    }
+    if let Some(n) = parse_number(&opt_matches, "A") {
+        searcher.after_context(n);
+    }
+    if let Some(n) = parse_number(&opt_matches, "B") {
+        searcher.before_context(n);
+    }
+    let has_context = ["A", "B", "C"].iter().any(|name| parse_number(&opt_matches, name).unwrap_or(0) > 0);
+
+    let mode = if opt_matches.opt_present("l") {
+        Mode::FilesWithMatches
+    } else if opt_matches.opt_present("c") {
+        Mode::Count
+    } else if opt_matches.opt_present("o") {
+        if opt_matches.opt_present("v") {
+            // No part of a non-matching line matches.
+            searcher.max_count(Some(0));
+        }
+        searcher.context(0);
+        Mode::OnlyMatching
+    } else {
+        Mode::Lines
+    };
+
+    let recursive = opt_matches.opt_present("r");
+    if free.is_empty() {
+        free.push(if recursive { ".".to_string() } else { "-".to_string() });
+    }
+    let with_filename = if opt_matches.opt_present("h") {
+        false
+    } else {
+        opt_matches.opt_present("H") || recursive || free.len() > 1
+    };
+
+    let stdout = io::stdout();
+    let color = match opt_matches.opt_str("color") {
+        None => opt_matches.opt_present("color") && stdout.is_terminal(),
+        Some(when) => match &when[..] {
+            "auto" => stdout.is_terminal(),
+            "always" => true,
+            "never" => false,
+            _ => usage_error(&format!("invalid argument {:?} for --color", when))
+        }
+    };
+    let printer = Printer::new(io::BufWriter::new(stdout.lock()), color, opt_matches.opt_present("json"),
+                               opt_matches.opt_present("n"), mode == Mode::OnlyMatching, has_context);
+
+    let config = Config {
+        re,
+        searcher,
+        mode,
+        with_filename,
+        recursive,
+        no_messages: opt_matches.opt_present("s"),
+        line_buffered: opt_matches.opt_present("line-buffered") || stdout.is_terminal(),
+        include: parse_globs(&opt_matches, "include"),
+        exclude: parse_globs(&opt_matches, "exclude"),
+        exclude_dir: parse_globs(&opt_matches, "exclude-dir")
+    };
+    let mut grep = Grep {
+        config,
+        printer,
+        matched: false,
+        failed: false
+    };
+
+    let mut result = Ok(());
+    for path in free.iter() {
+        result = if path == "-" { grep.search_stdin() } else { grep.search_path(Path::new(path), true) };
+        if result.is_err() {
+            break;
+        }
+    }
+    let result = result.and_then(|()| grep.printer.flush());
+    if let Err(err) = result {
+        if err.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("rpcregrep: {}", err);
+            process::exit(2);
+        }
+    }
+
+    process::exit(if grep.failed { 2 } else if grep.matched { 0 } else { 1 });
+}
@@ -0,0 +1,165 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Writes search results as text, optionally colored, or as JSON lines.
+
+use pcre::search::{Line};
+use serde_json::{json, Value};
+use std::io::{self, Write};
+
+const COLOR_PATH: &str = "\x1b[35m";
+const COLOR_NUMBER: &str = "\x1b[32m";
+const COLOR_SEPARATOR: &str = "\x1b[36m";
+const COLOR_MATCH: &str = "\x1b[1;31m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// How results are printed.
+pub struct Printer<W: Write> {
+
+    out: W,
+
+    color: bool,
+
+    json: bool,
+
+    line_numbers: bool,
+
+    only_matching: bool,
+
+    /// Whether groups of lines are separated with `--`.
+    separators: bool,
+
+    /// Whether a line has been printed, so that the next group needs a separator.
+    printed_line: bool,
+
+    /// The number of the last line printed from the current file.
+    last_number: Option<u64>
+
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(out: W, color: bool, json: bool, line_numbers: bool, only_matching: bool, separators: bool) -> Printer<W> {
+        Printer {
+            out,
+            color,
+            json,
+            line_numbers,
+            only_matching,
+            separators: separators && !only_matching && !json,
+            printed_line: false,
+            last_number: None
+        }
+    }
+
+    /// Starts printing the lines of a new file.
+    pub fn begin_file(&mut self) {
+        self.last_number = None;
+    }
+
+    fn colored(&mut self, color: &str, text: &str) -> io::Result<()> {
+        if self.color {
+            write!(self.out, "{}{}{}", color, text, COLOR_RESET)
+        } else {
+            self.out.write_all(text.as_bytes())
+        }
+    }
+
+    fn json(&mut self, value: Value) -> io::Result<()> {
+        writeln!(self.out, "{}", value)
+    }
+
+    /// Writes the file name and line number before a line, each followed by `separator`.
+    fn prefix(&mut self, path: Option<&str>, number: u64, separator: &str) -> io::Result<()> {
+        if let Some(path) = path {
+            self.colored(COLOR_PATH, path)?;
+            self.colored(COLOR_SEPARATOR, separator)?;
+        }
+        if self.line_numbers {
+            self.colored(COLOR_NUMBER, &number.to_string())?;
+            self.colored(COLOR_SEPARATOR, separator)?;
+        }
+        Ok(())
+    }
+
+    /// Prints a matching line or context line from the file `path`.
+    pub fn line(&mut self, path: Option<&str>, line: &Line) -> io::Result<()> {
+        if self.json {
+            let submatches: Vec<Value> = line.spans.iter()
+                .map(|span| json!({ "start": span.start, "end": span.end, "text": &line.text[span.clone()] }))
+                .collect();
+            return self.json(json!({
+                "type": if line.is_match() { "match" } else { "context" },
+                "path": path,
+                "line_number": line.number,
+                "offset": line.offset,
+                "text": line.text,
+                "submatches": submatches
+            }));
+        }
+
+        if self.only_matching {
+            for span in line.spans.iter().filter(|span| !span.is_empty()) {
+                self.prefix(path, line.number, ":")?;
+                self.colored(COLOR_MATCH, &line.text[span.clone()])?;
+                self.out.write_all(b"\n")?;
+            }
+            return Ok(());
+        }
+
+        let adjacent = self.last_number == Some(line.number - 1);
+        if self.separators && self.printed_line && !adjacent {
+            self.colored(COLOR_SEPARATOR, "--")?;
+            self.out.write_all(b"\n")?;
+        }
+        self.printed_line = true;
+        self.last_number = Some(line.number);
+
+        self.prefix(path, line.number, if line.is_match() { ":" } else { "-" })?;
+        let mut end = 0;
+        for span in line.spans.iter() {
+            self.out.write_all(&line.text.as_bytes()[end..span.start])?;
+            self.colored(COLOR_MATCH, &line.text[span.clone()])?;
+            end = span.end;
+        }
+        self.out.write_all(&line.text.as_bytes()[end..])?;
+        self.out.write_all(b"\n")
+    }
+
+    /// Prints the number of matching lines in the file `path`.
+    pub fn count(&mut self, path: Option<&str>, count: u64) -> io::Result<()> {
+        if self.json {
+            return self.json(json!({ "type": "count", "path": path, "count": count }));
+        }
+        if let Some(path) = path {
+            self.colored(COLOR_PATH, path)?;
+            self.colored(COLOR_SEPARATOR, ":")?;
+        }
+        writeln!(self.out, "{}", count)
+    }
+
+    /// Prints the name of a file which matches.
+    pub fn file(&mut self, path: &str) -> io::Result<()> {
+        if self.json {
+            return self.json(json!({ "type": "file", "path": path }));
+        }
+        self.colored(COLOR_PATH, path)?;
+        self.out.write_all(b"\n")
+    }
+
+    /// Reports that the binary file `path` matches.
+    pub fn binary(&mut self, path: &str) -> io::Result<()> {
+        if self.json {
+            return self.json(json!({ "type": "binary", "path": path }));
+        }
+        writeln!(self.out, "Binary file {} matches", path)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
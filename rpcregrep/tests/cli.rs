@@ -0,0 +1,134 @@
+use std::path::{Path};
+use std::process::{Command, Stdio};
+use std::io::{BufRead, BufReader, Write};
+
+/// Runs `rpcregrep` with `args` in the fixtures directory, returning its exit status and
+/// output.
+fn run(args: &[&str]) -> (i32, String) {
+    run_with_input(args, None)
+}
+
+fn run_with_input(args: &[&str], input: Option<&str>) -> (i32, String) {
+    let (status, output, _) = run_with_errors(args, input);
+    (status, output)
+}
+
+/// Runs `rpcregrep` as `run_with_input()` does, also returning its error output.
+fn run_with_errors(args: &[&str], input: Option<&str>) -> (i32, String, String) {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rpcregrep"))
+        .args(args)
+        .current_dir(fixtures)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.unwrap_or("").as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn test_basic_options() {
+    assert_eq!(run(&["world", "a.txt"]), (0, "hello world\nworld peace\n".to_string()));
+    assert_eq!(run(&["-i", "-n", "^hello", "a.txt"]), (0, "1:hello world\n2:Hello again\n".to_string()));
+    assert_eq!(run(&["-v", "o", "a.txt"]), (1, String::new()));
+    assert_eq!(run(&["-v", "world", "a.txt"]), (0, "Hello again\nnothing here\n".to_string()));
+    assert_eq!(run(&["-x", "world", "a.txt"]), (1, String::new()));
+    assert_eq!(run(&["-x", "world.*", "a.txt"]), (0, "world peace\n".to_string()));
+    assert_eq!(run(&["-w", "wor", "a.txt"]), (1, String::new()));
+    assert_eq!(run(&["-o", r"\w+o\b", "a.txt"]), (0, "hello\nHello\n".to_string()));
+    assert_eq!(run(&["-c", "o", "a.txt", "b.log"]), (0, "a.txt:4\nb.log:2\n".to_string()));
+    assert_eq!(run(&["-l", "(?i)error", "a.txt", "b.log"]), (0, "b.log\n".to_string()));
+    assert_eq!(run(&["-e", "peace", "-e", "^error", "a.txt", "b.log"]), (0, "a.txt:world peace\nb.log:error: disk full\n".to_string()));
+    assert_eq!(run_with_input(&["-n", "b"], Some("a\nb\n")), (0, "2:b\n".to_string()));
+}
+
+#[test]
+fn test_several_patterns() {
+    assert_eq!(run(&["-e", r"(w)o", "-e", r"(l)\g{-1}", "a.txt"]), (0, "hello world\nHello again\nworld peace\n".to_string()));
+    // Group numbers would refer to the groups of the other patterns.
+    assert_eq!(run(&["-e", "x", "-e", r"(l)\1", "a.txt"]), (2, String::new()));
+    assert_eq!(run(&["-e", r"(l)\1", "a.txt"]), (0, "hello world\nHello again\n".to_string()));
+}
+
+#[test]
+fn test_stdin() {
+    // Lines are printed as they are read, before the input ends.
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rpcregrep"))
+        .args(["--line-buffered", "b"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    for &(input, output) in [("a\nb1\n", "b1\n"), ("c\nb2\n", "b2\n")].iter() {
+        stdin.write_all(input.as_bytes()).unwrap();
+        line.clear();
+        stdout.read_line(&mut line).unwrap();
+        assert_eq!(line, output);
+    }
+    drop(stdin);
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+
+    // The line with binary data is counted.
+    assert_eq!(run_with_input(&["-c", "x"], Some("x\n\0x\nx\n")), (0, "3\n".to_string()));
+}
+
+#[test]
+fn test_context() {
+    assert_eq!(run(&["-n", "-A", "1", "again", "a.txt"]), (0, "2:Hello again\n3-nothing here\n".to_string()));
+    assert_eq!(run(&["-A", "1", "-e", "^hello", "-e", "peace", "a.txt"]),
+               (0, "hello world\nHello again\n--\nworld peace\n".to_string()));
+    assert_eq!(run(&["-C", "1", "nothing", "a.txt"]), (0, "Hello again\nnothing here\nworld peace\n".to_string()));
+}
+
+#[test]
+fn test_recursive() {
+    assert_eq!(run(&["-r", "hello"]).1, "./a.txt:hello world\n./data.bin:text hello\nBinary file ./data.bin matches\n./sub/c.txt:hello from sub\n./sub/skip/d.txt:hello hidden\n");
+    assert_eq!(run(&["-r", "--include=*.txt", "--exclude-dir=skip", "-l", "hello", "."]).1, "./a.txt\n./sub/c.txt\n");
+    assert_eq!(run(&["-r", "--exclude=[ab].*", "--exclude=*.bin", "-c", "hello", "sub"]).1, "sub/c.txt:1\nsub/skip/d.txt:1\n");
+    assert_eq!(run(&["-c", "hello", "data.bin"]), (0, "2\n".to_string()));
+
+    // Directories are skipped without -r.
+    assert_eq!(run(&["hello", "sub"]), (1, String::new()));
+    assert_eq!(run(&["hello", "missing.txt"]).0, 2);
+}
+
+#[test]
+fn test_output_formats() {
+    assert_eq!(run(&["--color=always", "-n", "peace", "a.txt"]).1, "\x1b[32m4\x1b[0m\x1b[36m:\x1b[0mworld \x1b[1;31mpeace\x1b[0m\n");
+
+    let (status, output) = run(&["--json", "-H", "o w", "a.txt"]);
+    assert_eq!(status, 0);
+    assert_eq!(output, concat!(r#"{"line_number":1,"offset":0,"path":"a.txt","submatches":[{"end":7,"start":4,"text":"o w"}],"#,
+                               r#""text":"hello world","type":"match"}"#, "\n"));
+    assert_eq!(run(&["--json", "-c", "o", "b.log"]).1, "{\"count\":2,\"path\":null,\"type\":\"count\"}\n");
+}
+
+#[test]
+fn test_pcre_options() {
+    assert_eq!(run(&["--no-jit", "--match-limit=100000", "(a|b)*c", "b.log"]), (1, String::new()));
+    assert_eq!(run_with_input(&["--newline=cr", "(?m)^y"], Some("x\ry\n")), (0, "x\ry\n".to_string()));
+    assert_eq!(run_with_input(&["(?m)^y"], Some("x\ry\n")), (1, String::new()));
+    assert_eq!(run(&["--newline=bogus", "x", "a.txt"]).0, 2);
+    assert_eq!(run(&["(x", "a.txt"]).0, 2);
+}
+
+#[test]
+fn test_match_limit_exceeded() {
+    // The line on which the match limit is exceeded is reported, and the search goes on.
+    let input = Some("ok 1
+aaaaaaaaaaaaaaaaaaaaaaaaab
+ok 2
+");
+    for &args in [&["--match-limit=1000", r"^(a+)+$|ok"][..], &["-c", "--match-limit=1000", r"^(a+)+$|ok"][..]].iter() {
+        let (status, output, errors) = run_with_errors(args, input);
+        assert_eq!(status, 2);
+        assert_eq!(output, if args[0] == "-c" { "2\n" } else { "ok 1\nok 2\n" });
+        assert_eq!(errors, "rpcregrep: (standard input): line 2: match limit exceeded\n");
+    }
+}
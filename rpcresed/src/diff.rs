@@ -0,0 +1,147 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Unified diffs of the changes made to an input, for `--dry-run`.
+
+use std::cmp;
+use std::string::{String};
+use std::vec::{Vec};
+
+/// The largest table of common subsequence lengths which is computed. If the changed
+/// region of a file is larger, it is shown as all of its old lines replaced by all of its
+/// new lines.
+const MAX_CELLS: usize = 16 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Delete,
+    Insert
+}
+
+/// Returns the edits which turn `old` into `new`, keeping a longest common subsequence of
+/// lines.
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new.iter()).take_while(|&(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|&(a, b)| a == b).count();
+    let a = &old[prefix..(old.len() - suffix)];
+    let b = &new[prefix..(new.len() - suffix)];
+
+    let mut edits = vec![Edit::Keep; prefix];
+    if a.len().saturating_mul(b.len()) > MAX_CELLS {
+        edits.extend(a.iter().map(|_| Edit::Delete));
+        edits.extend(b.iter().map(|_| Edit::Insert));
+    } else {
+        // lengths[i * width + j] is the length of the longest common subsequence of
+        // a[i..] and b[j..].
+        let width = b.len() + 1;
+        let mut lengths = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lengths[i * width + j] = if a[i] == b[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    cmp::max(lengths[(i + 1) * width + j], lengths[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if a[i] == b[j] {
+                edits.push(Edit::Keep);
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+                edits.push(Edit::Delete);
+                i += 1;
+            } else {
+                edits.push(Edit::Insert);
+                j += 1;
+            }
+        }
+        edits.extend((i..a.len()).map(|_| Edit::Delete));
+        edits.extend((j..b.len()).map(|_| Edit::Insert));
+    }
+    edits.extend((0..suffix).map(|_| Edit::Keep));
+    edits
+}
+
+/// Formats the start line and line count of a hunk header, where `start` is the number of
+/// lines before the hunk.
+fn range(start: usize, count: usize) -> String {
+    match count {
+        // An empty range is given by the line before it.
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count)
+    }
+}
+
+/// Appends a line of a hunk to `diff`.
+fn push_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line);
+    if !line.ends_with('\n') {
+        diff.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// Returns a unified diff from `old` to `new`, labelled with `old_name` and `new_name`,
+/// with `context` unchanged lines around each change. The diff is empty if the texts are
+/// the same.
+pub fn unified(old_name: &str, new_name: &str, old: &str, new: &str, context: usize) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = edits(&old_lines, &new_lines);
+
+    // The positions in the old and new lines before each edit.
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for edit in edits.iter() {
+        positions.push((i, j));
+        match *edit {
+            Edit::Keep => {
+                i += 1;
+                j += 1;
+            },
+            Edit::Delete => i += 1,
+            Edit::Insert => j += 1
+        }
+    }
+    positions.push((i, j));
+
+    let changes: Vec<usize> = (0..edits.len()).filter(|&k| edits[k] != Edit::Keep).collect();
+    let mut diff = String::new();
+    let mut c = 0;
+    while c < changes.len() {
+        // A hunk holds the changes which are separated by at most twice the context.
+        let mut last = c;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * context + 1 {
+            last += 1;
+        }
+        let start = changes[c].saturating_sub(context);
+        let end = cmp::min(changes[last] + 1 + context, edits.len());
+        c = last + 1;
+
+        if diff.is_empty() {
+            diff.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+        }
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        diff.push_str(&format!("@@ -{} +{} @@\n", range(old_start, old_end - old_start), range(new_start, new_end - new_start)));
+        for k in start..end {
+            let (i, j) = positions[k];
+            match edits[k] {
+                Edit::Keep => push_line(&mut diff, ' ', old_lines[i]),
+                Edit::Delete => push_line(&mut diff, '-', old_lines[i]),
+                Edit::Insert => push_line(&mut diff, '+', new_lines[j])
+            }
+        }
+    }
+    diff
+}
@@ -0,0 +1,295 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `rpcresed`, a `sed`-like search-and-replace tool which applies `s/pattern/replacement/flags`
+//! expressions with PCRE patterns.
+//!
+//! Each line of the input is edited on its own, or the whole input at once with
+//! `--whole-file`. The exit status is 0 on success and 2 if an error occurred. Errors in
+//! individual files are reported and the other files are still edited.
+
+extern crate enum_set;
+extern crate getopts;
+extern crate pcre;
+
+mod diff;
+mod script;
+
+use getopts::{Matches, Options};
+use pcre::{pcre_version};
+use script::{Substitution};
+use std::borrow::{Cow};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// The number of unchanged lines shown around each change by `--dry-run`.
+const DIFF_CONTEXT: usize = 3;
+
+const STDIN_NAME: &str = "(standard input)";
+
+/// What is done with the edited text.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// The edited text is written to stdout.
+    Print,
+
+    /// Each file is replaced with its edited text.
+    InPlace,
+
+    /// A diff of the changes is written to stdout.
+    DryRun
+}
+
+struct Sed<W: Write> {
+
+    substitutions: Vec<Substitution>,
+
+    whole_file: bool,
+
+    mode: Mode,
+
+    /// The suffix of the backup copies of files edited in place.
+    backup_suffix: Option<String>,
+
+    out: W,
+
+    /// Whether an error was reported.
+    failed: bool
+
+}
+
+fn print_usage(program: &str, opts: &Options) {
+    let brief = format!("Usage: {} [options] script [file...]\n       {} [options] -e script... [file...]", program, program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("rpcresed: {}", message);
+    eprintln!("Try `rpcresed --help` for more information.");
+    process::exit(2);
+}
+
+/// Collects the scripts given with `-e` and `-f`, or else the first free argument.
+fn read_scripts(opt_matches: &Matches, free: &mut Vec<String>) -> Vec<String> {
+    let mut scripts = opt_matches.opt_strs("e");
+    for path in opt_matches.opt_strs("f") {
+        match fs::read_to_string(&path) {
+            Err(err) => {
+                eprintln!("rpcresed: {}: {}", path, err);
+                process::exit(2);
+            },
+            Ok(script) => scripts.push(script)
+        }
+    }
+    if scripts.is_empty() {
+        if free.is_empty() {
+            usage_error("no script");
+        }
+        scripts.push(free.remove(0));
+    }
+    scripts
+}
+
+/// Returns the path of a file next to `path` with `suffix` appended to its name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Replaces the contents of the file at `path` with `text`, keeping its permissions. The
+/// new contents are written to a temporary file which is then renamed, so that the file
+/// is never left half-written. With `backup_suffix`, the original file is kept under its
+/// name with the suffix appended.
+fn replace_file(path: &Path, text: &str, backup_suffix: Option<&str>) -> io::Result<()> {
+    let permissions = fs::metadata(path)?.permissions();
+    let temp = with_suffix(path, ".rpcresed-tmp");
+    let result = fs::write(&temp, text)
+        .and_then(|()| fs::set_permissions(&temp, permissions))
+        .and_then(|()| match backup_suffix {
+            None => Ok(()),
+            Some(suffix) => fs::rename(path, with_suffix(path, suffix))
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+impl<W: Write> Sed<W> {
+    fn error(&mut self, path: &str, message: &dyn std::fmt::Display) {
+        self.failed = true;
+        eprintln!("rpcresed: {}: {}", path, message);
+    }
+
+    /// Applies every substitution in turn to `text`, appending the result to `out`.
+    fn edit_text(&self, text: &str, out: &mut String) {
+        let mut current = Cow::Borrowed(text);
+        for substitution in self.substitutions.iter() {
+            if let Some(replaced) = substitution.apply(&current) {
+                current = Cow::Owned(replaced);
+            }
+        }
+        out.push_str(&current);
+    }
+
+    /// Returns the edited `text`, which is edited a line at a time unless `--whole-file`
+    /// was given.
+    fn edit(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        if self.whole_file {
+            self.edit_text(text, &mut out);
+        } else {
+            for line in text.split_inclusive('\n') {
+                let body = line.strip_suffix('\n').unwrap_or(line);
+                self.edit_text(body, &mut out);
+                out.push_str(&line[body.len()..]);
+            }
+        }
+        out
+    }
+
+    /// Edits the input `text`, read from `display`, and writes or prints the result. `path`
+    /// is the file to replace when editing in place.
+    fn process(&mut self, display: &str, path: Option<&Path>, data: Vec<u8>) -> io::Result<()> {
+        let text = match String::from_utf8(data) {
+            Err(_) => {
+                self.error(display, &"input is not valid UTF-8");
+                return Ok(());
+            },
+            Ok(text) => text
+        };
+        let edited = self.edit(&text);
+        match (self.mode, path) {
+            (Mode::Print, _) => self.out.write_all(edited.as_bytes()),
+            (Mode::DryRun, _) => self.out.write_all(diff::unified(display, display, &text, &edited, DIFF_CONTEXT).as_bytes()),
+            (Mode::InPlace, Some(path)) => {
+                if edited != text {
+                    if let Err(err) = replace_file(path, &edited, self.backup_suffix.as_ref().map(|suffix| &suffix[..])) {
+                        self.error(display, &err);
+                    }
+                }
+                Ok(())
+            },
+            (Mode::InPlace, None) => {
+                self.error(display, &"cannot edit standard input in place");
+                Ok(())
+            }
+        }
+    }
+
+    /// Edits the file at `path`, or standard input if it is `-`.
+    fn process_path(&mut self, path: &str) -> io::Result<()> {
+        if path == "-" {
+            let mut data = Vec::new();
+            if let Err(err) = io::stdin().read_to_end(&mut data) {
+                self.error(STDIN_NAME, &err);
+                return Ok(());
+            }
+            return self.process(STDIN_NAME, None, data);
+        }
+        match fs::read(path) {
+            Err(err) => {
+                self.error(path, &err);
+                Ok(())
+            },
+            Ok(data) => self.process(path, Some(Path::new(path)), data)
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optmulti("e", "expression", "apply the expressions in SCRIPT; can be given several times", "SCRIPT");
+    opts.optmulti("f", "file", "apply the expressions in the file FILE, one per line", "FILE");
+    opts.optflag("i", "in-place", "edit files in place");
+    opts.optopt("b", "backup", "with --in-place, keep the original files with SUFFIX appended to their names", "SUFFIX");
+    opts.optflag("d", "dry-run", "print a diff of the changes instead of the edited text");
+    opts.optflag("z", "whole-file", "edit each input as a whole instead of line by line");
+    opts.optflag("", "no-jit", "do not JIT-compile the patterns");
+    opts.optflag("h", "help", "print usage and exit");
+    opts.optflag("V", "version", "print version information and exit");
+    let opt_matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => usage_error(&f.to_string())
+    };
+
+    if opt_matches.opt_present("help") {
+        print_usage(&program, &opts);
+        return;
+    }
+    if opt_matches.opt_present("version") {
+        println!("rpcresed {} using libpcre {}", env!("CARGO_PKG_VERSION"), pcre_version());
+        return;
+    }
+
+    let mut free = opt_matches.free.clone();
+    let jit = !opt_matches.opt_present("no-jit");
+    let mut substitutions = Vec::new();
+    for script in read_scripts(&opt_matches, &mut free) {
+        match script::parse_script(&script, jit) {
+            Err(message) => usage_error(&message),
+            Ok(parsed) => substitutions.extend(parsed)
+        }
+    }
+
+    let mode = if opt_matches.opt_present("d") {
+        Mode::DryRun
+    } else if opt_matches.opt_present("i") {
+        Mode::InPlace
+    } else {
+        Mode::Print
+    };
+    let backup_suffix = opt_matches.opt_str("b");
+    if backup_suffix.is_some() && !opt_matches.opt_present("i") {
+        usage_error("--backup requires --in-place");
+    }
+    if backup_suffix.as_ref().is_some_and(|suffix| suffix.is_empty()) {
+        usage_error("empty backup suffix");
+    }
+    if free.is_empty() {
+        if mode == Mode::InPlace {
+            usage_error("no files to edit in place");
+        }
+        free.push("-".to_string());
+    }
+
+    let stdout = io::stdout();
+    let mut sed = Sed {
+        substitutions,
+        whole_file: opt_matches.opt_present("z"),
+        mode,
+        backup_suffix,
+        out: io::BufWriter::new(stdout.lock()),
+        failed: false
+    };
+
+    let mut result = Ok(());
+    for path in free.iter() {
+        result = sed.process_path(path);
+        if result.is_err() {
+            break;
+        }
+    }
+    let result = result.and_then(|()| sed.out.flush());
+    if let Err(err) = result {
+        if err.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("rpcresed: {}", err);
+            process::exit(2);
+        }
+    }
+
+    process::exit(if sed.failed { 2 } else { 0 });
+}
@@ -0,0 +1,183 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing and applying `s/pattern/replacement/flags` expressions.
+
+use enum_set::{EnumSet};
+use pcre::{Pcre, StudyOption};
+use pcre::flags;
+use std::string::{String};
+use std::vec::{Vec};
+
+/// A substitution expression.
+pub struct Substitution {
+
+    re: Pcre,
+
+    /// The replacement, in the syntax of `Match::expand()`.
+    replacement: String,
+
+    /// Whether the matches after the first one replaced are also replaced.
+    global: bool,
+
+    /// The number of the first match which is replaced, starting at 1.
+    occurrence: usize
+
+}
+
+/// Splits `rest` at the next `delimiter` which is not escaped with a `\`, returning the
+/// text before it and the text after it. `expr` is the expression for error messages.
+fn split_part<'e>(expr: &str, rest: &'e str, delimiter: char) -> Result<(&'e str, &'e str), String> {
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == delimiter {
+            return Ok((&rest[..i], &rest[(i + delimiter.len_utf8())..]));
+        }
+    }
+    Err(format!("unterminated expression {:?}", expr))
+}
+
+/// Parses `script`, a list of expressions separated by `;` or newlines. Blank lines and
+/// lines starting with `#` are skipped.
+pub fn parse_script(script: &str, jit: bool) -> Result<Vec<Substitution>, String> {
+    let mut substitutions = Vec::new();
+    let mut rest = script;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ';' || c.is_whitespace());
+        if rest.starts_with('#') {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if rest.is_empty() {
+            return Ok(substitutions);
+        } else {
+            let (substitution, next) = Substitution::parse(rest, jit)?;
+            substitutions.push(substitution);
+            rest = next;
+        }
+    }
+}
+
+/// Converts the replacement text of an expression to the syntax of `Match::expand()`.
+///
+/// `\1` to `\9` refer to groups, `\n` and `\t` are a newline and a tab, and a `\` before
+/// any other character makes it literal. `$` references are kept as they are.
+fn parse_replacement(text: &str) -> String {
+    let mut replacement = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            replacement.push(c);
+            continue;
+        }
+        match chars.next() {
+            None => replacement.push('\\'),
+            Some('n') => replacement.push('\n'),
+            Some('t') => replacement.push('\t'),
+            Some('$') => replacement.push_str("$$"),
+            Some(c) if c.is_ascii_digit() => {
+                replacement.push_str("${");
+                replacement.push(c);
+                replacement.push('}');
+            },
+            Some(c) => replacement.push(c)
+        }
+    }
+    replacement
+}
+
+impl Substitution {
+    /// Parses the expression at the start of `script`, which has the form
+    /// `s/pattern/replacement/flags`, returning it and the rest of the script. Any
+    /// character other than a letter, digit, whitespace or `\` can be used as the delimiter
+    /// instead of `/`, and is written as `\` and the delimiter inside the pattern and
+    /// replacement.
+    ///
+    /// The flags end at a `;` or a newline. They are `g` to replace all matches, a number
+    /// N to start replacing at the Nth match, and the pattern flags of `pcre::flags`.
+    /// `jit` sets whether the pattern is JIT-compiled.
+    fn parse(script: &str, jit: bool) -> Result<(Substitution, &str), String> {
+        let flags_end = script.find([';', '\n']).unwrap_or(script.len());
+        let expr = &script[..flags_end];
+        let mut chars = script.chars();
+        if chars.next() != Some('s') {
+            return Err(format!("unknown command in {:?}; expected `s/pattern/replacement/flags`", expr));
+        }
+        let delimiter = match chars.next() {
+            None => return Err(format!("unterminated expression {:?}", expr)),
+            Some(c) if c.is_alphanumeric() || c.is_whitespace() || c == '\\' => {
+                return Err(format!("invalid delimiter '{}' in {:?}", c, expr));
+            },
+            Some(c) => c
+        };
+        let (pattern, rest) = split_part(expr, chars.as_str(), delimiter)?;
+        let (replacement, rest) = split_part(expr, rest, delimiter)?;
+        let flags_end = rest.find([';', '\n']).unwrap_or(rest.len());
+        let (flag_text, rest) = rest.split_at(flags_end);
+        let expr = &script[..(script.len() - rest.len())];
+
+        let mut global = false;
+        let mut occurrence = String::new();
+        let mut pattern_flags = String::new();
+        for c in flag_text.trim_end().chars() {
+            match c {
+                'g' => global = true,
+                '0'..='9' => occurrence.push(c),
+                c => pattern_flags.push(c)
+            }
+        }
+        let occurrence = match occurrence.parse() {
+            Err(_) if occurrence.is_empty() => 1,
+            Ok(n) if n > 0 => n,
+            _ => return Err(format!("invalid occurrence number {:?} in {:?}", occurrence, expr))
+        };
+        let options = flags::parse_flags(&pattern_flags).map_err(|err| format!("{} in {:?}", err, expr))?;
+
+        // Every non-alphanumeric character escaped with a `\` is literal in a pattern, so
+        // an escaped delimiter can be left as it is.
+        let mut re = Pcre::compile_with_options(pattern, &options)
+            .map_err(|err| format!("invalid pattern in {:?}: {}", expr, err))?;
+        if jit {
+            let mut study_options: EnumSet<StudyOption> = EnumSet::new();
+            study_options.insert(StudyOption::StudyJitCompile);
+            re.study_with_options(&study_options);
+        }
+        let substitution = Substitution {
+            re,
+            replacement: parse_replacement(replacement),
+            global,
+            occurrence
+        };
+        Ok((substitution, rest))
+    }
+
+    /// Applies the substitution to `text`, returning the result if anything was replaced.
+    pub fn apply(&self, text: &str) -> Option<String> {
+        let mut result = String::new();
+        let mut end = 0;
+        let mut replaced = false;
+        for (i, m) in self.re.matches(text).enumerate() {
+            if i + 1 < self.occurrence {
+                continue;
+            }
+            // With `\K` in a lookbehind, a match can start before the end of the previous one.
+            result.push_str(&text[end..m.group_start(0).max(end)]);
+            m.expand(&self.replacement, &mut result);
+            end = m.group_end(0);
+            replaced = true;
+            if !self.global {
+                break;
+            }
+        }
+        if !replaced {
+            return None;
+        }
+        result.push_str(&text[end..]);
+        Some(result)
+    }
+}
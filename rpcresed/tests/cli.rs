@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::io::{Write};
+
+/// Runs `rpcresed` with `args` in `dir`, returning its exit status and output.
+fn run_in(dir: &Path, args: &[&str], input: Option<&str>) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rpcresed"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.unwrap_or("").as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap())
+}
+
+/// Runs `rpcresed` with `args` in the fixtures directory.
+fn run(args: &[&str]) -> (i32, String) {
+    run_in(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures"), args, None)
+}
+
+fn run_with_input(args: &[&str], input: &str) -> (i32, String) {
+    run_in(Path::new(env!("CARGO_MANIFEST_DIR")), args, Some(input))
+}
+
+/// Returns an empty scratch directory for the test `name`.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_substitutions() {
+    assert_eq!(run(&["s/(\\w+) (\\w+)/$2 $1/", "words.txt"]), (0, "two one\nfour three\n".to_string()));
+    assert_eq!(run(&["s/o/0/g", "words.txt"]), (0, "0ne tw0\nthree f0ur\n".to_string()));
+    assert_eq!(run(&["s/[a-z]+/X/2", "words.txt"]), (0, "one X\nthree X\n".to_string()));
+    assert_eq!(run(&["s/e/E/2g", "words.txt"]), (0, "one two\nthreE four\n".to_string()));
+    assert_eq!(run(&["s/^(?<first>\\w+)/[${first}]/; s|\\]|\\]!|", "words.txt"]), (0, "[one]! two\n[three]! four\n".to_string()));
+    assert_eq!(run(&["-e", "s/ONE/1/i", "-e", "s/1 (two)/\\1 $$/", "words.txt"]), (0, "two $\nthree four\n".to_string()));
+    assert_eq!(run(&["s#\"([^\"<]*) <(.*)>\"#\"${2}\"#", "config.toml"]).1, "name = \"demo\"\nversion = \"0.1.0\"\nauthors = [\"ada@example.com\"]\n");
+    assert_eq!(run_with_input(&["s/b/\\n/", "-"], "abc\nxyz"), (0, "a\nc\nxyz".to_string()));
+
+    // Lines are edited without their newlines.
+    assert_eq!(run_with_input(&["s/$/;/"], "a\nb\n"), (0, "a;\nb;\n".to_string()));
+
+    // A match can start before the end of the previous one.
+    assert_eq!(run_with_input(&["s/(?<=\\K..)x/<$0>/g"], "aaxax\n"), (0, "<aax><xax>\n".to_string()));
+}
+
+#[test]
+fn test_whole_file() {
+    assert_eq!(run(&["s/two\\nthree/2-3/", "words.txt"]).1, "one two\nthree four\n");
+    assert_eq!(run(&["-z", "s/two\\nthree/2-3/", "words.txt"]).1, "one 2-3 four\n");
+    assert_eq!(run(&["-z", "s/^(\\w+)/<$1>/mg", "words.txt"]).1, "<one> two\n<three> four\n");
+}
+
+#[test]
+fn test_dry_run() {
+    assert_eq!(run(&["-d", "s/four/4/", "words.txt", "config.toml"]),
+               (0, "--- words.txt\n+++ words.txt\n@@ -1,2 +1,2 @@\n one two\n-three four\n+three 4\n".to_string()));
+    assert_eq!(run_with_input(&["--dry-run", "-z", "s/\\n(b)/$1/"], "a\nb"),
+               (0, "--- (standard input)\n+++ (standard input)\n@@ -1,2 +1 @@\n-a\n-b\n\\ No newline at end of file\n+ab\n\\ No newline at end of file\n".to_string()));
+}
+
+#[test]
+fn test_in_place() {
+    let dir = scratch_dir("in_place");
+    fs::write(dir.join("a.txt"), "alpha beta\n").unwrap();
+    fs::write(dir.join("b.txt"), "gamma\n").unwrap();
+
+    assert_eq!(run_in(&dir, &["-i", "s/a\\b/A/g", "a.txt", "b.txt"], None), (0, String::new()));
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "alphA betA\n");
+    assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "gammA\n");
+    assert!(!dir.join("a.txt.bak").exists());
+
+    assert_eq!(run_in(&dir, &["-i", "-b", ".bak", "s/A/a/", "a.txt"], None), (0, String::new()));
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "alpha betA\n");
+    assert_eq!(fs::read_to_string(dir.join("a.txt.bak")).unwrap(), "alphA betA\n");
+
+    // A dry run leaves the files alone.
+    assert_eq!(run_in(&dir, &["-i", "-d", "s/gammA/delta/", "b.txt"], None).1, "--- b.txt\n+++ b.txt\n@@ -1 +1 @@\n-gammA\n+delta\n");
+    assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "gammA\n");
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(run(&["s/a/b", "words.txt"]).0, 2);
+    assert_eq!(run(&["s/a/b/q", "words.txt"]).0, 2);
+    assert_eq!(run(&["s/(/b/", "words.txt"]).0, 2);
+    assert_eq!(run(&["y/a/b/", "words.txt"]).0, 2);
+    assert_eq!(run(&["-i", "s/a/b/"]).0, 2);
+    assert_eq!(run(&["-b", ".bak", "s/a/b/", "words.txt"]).0, 2);
+
+    // The other files are still edited.
+    assert_eq!(run(&["s/one/1/", "missing.txt", "words.txt"]), (2, "1 two\nthree four\n".to_string()));
+}
@@ -0,0 +1,363 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use enum_set::{EnumSet};
+use libc::{c_uchar, c_void};
+use std::fmt;
+use std::string::{String};
+use std::sync::{Arc};
+
+use detail;
+use {CompilationError, CompileOption, Pcre, StudyOption};
+
+/// The character sequences that are recognized as a newline.
+///
+/// The default is set when libpcre is built, and is usually `Lf`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Newline {
+    /// Carriage return only.
+    Cr,
+
+    /// Linefeed only.
+    Lf,
+
+    /// Carriage return followed by linefeed.
+    Crlf,
+
+    /// Any Unicode newline sequence.
+    Any,
+
+    /// Any of carriage return, linefeed or carriage return followed by linefeed.
+    AnyCrlf
+}
+
+/// The character sequences that `\R` matches.
+///
+/// The default is set when libpcre is built, and is usually `Unicode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bsr {
+    /// Carriage return, linefeed or carriage return followed by linefeed.
+    AnyCrlf,
+
+    /// Any Unicode newline sequence.
+    Unicode
+}
+
+/// Character tables, which determine how characters below 256 are classified (letters,
+/// digits, case pairs, etc.) when not using Unicode properties.
+pub struct Tables {
+
+    ptr: *const c_uchar
+
+}
+
+/// The reason that [`PcreBuilder::build()`](struct.PcreBuilder.html#method.build) failed.
+#[derive(Debug)]
+pub enum BuildError {
+    /// The pattern could not be compiled.
+    Compile(CompilationError),
+
+    /// The compiled pattern could not be studied.
+    Study(String)
+}
+
+/// A builder for a [`Pcre`](struct.Pcre.html) with compile options, study options and
+/// match limits.
+///
+/// # Example
+/// ```
+/// use pcre::{Newline, PcreBuilder};
+///
+/// let re = PcreBuilder::new("^abc$")
+///     .caseless(true)
+///     .multiline(true)
+///     .newline(Newline::Crlf)
+///     .match_limit(10000)
+///     .build()
+///     .unwrap();
+/// assert!(re.is_match("x\r\nABC\r\n"));
+/// ```
+#[derive(Clone)]
+pub struct PcreBuilder {
+
+    pattern: String,
+
+    options: EnumSet<CompileOption>,
+
+    newline: Option<Newline>,
+
+    bsr: Option<Bsr>,
+
+    tables: Option<Arc<Tables>>,
+
+    study: bool,
+
+    jit: bool,
+
+    match_limit: Option<u32>,
+
+    match_limit_recursion: Option<u32>
+
+}
+
+// The tables are never modified after they are made.
+unsafe impl Send for Tables {}
+unsafe impl Sync for Tables {}
+
+impl Tables {
+    /// Makes character tables for the current `LC_CTYPE` locale.
+    pub fn from_current_locale() -> Tables {
+        Tables { ptr: detail::pcre_maketables() }
+    }
+
+    /// Returns a pointer to the tables, to pass to libpcre.
+    pub fn as_ptr(&self) -> *const c_uchar {
+        self.ptr
+    }
+}
+
+impl Drop for Tables {
+    fn drop(&mut self) {
+        unsafe {
+            detail::pcre_free(self.ptr as *mut c_void);
+        }
+    }
+}
+
+impl fmt::Debug for Tables {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Tables({:p})", self.ptr)
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::Compile(ref err) => write!(f, "{}", err),
+            BuildError::Study(ref err) => write!(f, "study failed: {}", err)
+        }
+    }
+}
+
+impl ::std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn (::std::error::Error) + 'static)> {
+        match *self {
+            BuildError::Compile(ref err) => Some(err),
+            BuildError::Study(_) => None
+        }
+    }
+}
+
+impl From<CompilationError> for BuildError {
+    fn from(err: CompilationError) -> BuildError {
+        BuildError::Compile(err)
+    }
+}
+
+impl PcreBuilder {
+    /// Creates a builder for the regular expression `pattern` with no options set.
+    pub fn new(pattern: &str) -> PcreBuilder {
+        PcreBuilder {
+            pattern: pattern.to_string(),
+            options: EnumSet::new(),
+            newline: None,
+            bsr: None,
+            tables: None,
+            study: false,
+            jit: false,
+            match_limit: None,
+            match_limit_recursion: None
+        }
+    }
+
+    fn option(&mut self, option: CompileOption, yes: bool) -> &mut PcreBuilder {
+        if yes {
+            self.options.insert(option);
+        } else {
+            self.options.remove(&option);
+        }
+        self
+    }
+
+    /// Letters match both upper and lower case (`(?i)`).
+    pub fn caseless(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::Caseless, yes)
+    }
+
+    /// `^` and `$` also match at newlines within the subject (`(?m)`).
+    pub fn multiline(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::Multiline, yes)
+    }
+
+    /// `.` also matches newlines (`(?s)`).
+    pub fn dotall(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::DotAll, yes)
+    }
+
+    /// Whitespace and `#` comments in the pattern are ignored (`(?x)`).
+    pub fn extended(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::Extended, yes)
+    }
+
+    /// The pattern only matches at the start offset.
+    pub fn anchored(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::Anchored, yes)
+    }
+
+    /// `$` only matches at the very end of the subject, not before a final newline.
+    pub fn dollar_end_only(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::DollarEndOnly, yes)
+    }
+
+    /// Quantifiers are lazy by default and greedy when followed by `?` (`(?U)`).
+    pub fn ungreedy(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::Ungreedy, yes)
+    }
+
+    /// Plain parentheses do not capture; only named groups do.
+    pub fn no_auto_capture(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::NoAutoCapture, yes)
+    }
+
+    /// Inserts a callout before every item of the pattern. Matches with a deadline or
+    /// cancellation check the callouts, and otherwise compile a second copy of the pattern
+    /// with this option; see
+    /// [`Pcre::exec_from_with_interrupt()`](struct.Pcre.html#method.exec_from_with_interrupt).
+    pub fn auto_callout(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::AutoCallout, yes)
+    }
+
+    /// A match must start before the first newline in the subject.
+    pub fn first_line(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::FirstLine, yes)
+    }
+
+    /// Several groups can have the same name (`(?J)`).
+    pub fn dup_names(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::DupNames, yes)
+    }
+
+    /// Uses JavaScript's interpretation of some pattern items instead of Perl's.
+    pub fn javascript_compat(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::JavaScriptCompat, yes)
+    }
+
+    /// `\d`, `\w`, POSIX classes, etc. use Unicode properties.
+    pub fn ucp(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.option(CompileOption::Ucp, yes)
+    }
+
+    /// Sets the newline convention instead of using libpcre's default.
+    pub fn newline(&mut self, newline: Newline) -> &mut PcreBuilder {
+        self.newline = Some(newline);
+        self
+    }
+
+    /// Sets the characters that `\R` matches instead of using libpcre's default.
+    pub fn bsr(&mut self, bsr: Bsr) -> &mut PcreBuilder {
+        self.bsr = Some(bsr);
+        self
+    }
+
+    /// Compiles the pattern with the character tables `tables` instead of the default ones,
+    /// which are for the "C" locale.
+    pub fn tables(&mut self, tables: Arc<Tables>) -> &mut PcreBuilder {
+        self.tables = Some(tables);
+        self
+    }
+
+    /// Studies the compiled pattern, which might speed up matching.
+    pub fn study(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.study = yes;
+        self
+    }
+
+    /// JIT-compiles the pattern, if libpcre was built with JIT support. This implies
+    /// studying it.
+    ///
+    /// The JIT-compiled code runs with a JIT stack of the thread which matches, which can
+    /// grow to 1 MiB, rather than the 32 KiB of machine stack that libpcre uses by default.
+    pub fn jit(&mut self, yes: bool) -> &mut PcreBuilder {
+        self.jit = yes;
+        self
+    }
+
+    /// Limits the number of internal match function calls per match to `limit`, instead of
+    /// libpcre's default (usually 10 million). A match which exceeds the limit makes
+    /// [`Pcre::exec_from_with_interrupt()`](struct.Pcre.html#method.exec_from_with_interrupt)
+    /// return `ExecError::MatchLimit`, and the matching methods which return no error panic.
+    ///
+    /// Setting a limit requires libpcre 8.32 or later.
+    pub fn match_limit(&mut self, limit: u32) -> &mut PcreBuilder {
+        self.match_limit = Some(limit);
+        self
+    }
+
+    /// Limits the recursion depth of the internal match function to `limit`, instead of
+    /// libpcre's default. Exceeding it is reported in the same way as exceeding the
+    /// [match limit](#method.match_limit). The JIT compiler ignores this limit.
+    ///
+    /// Setting a limit requires libpcre 8.32 or later.
+    pub fn match_limit_recursion(&mut self, limit: u32) -> &mut PcreBuilder {
+        self.match_limit_recursion = Some(limit);
+        self
+    }
+
+    /// Compiles the pattern and, if requested, studies it and sets its match limits.
+    pub fn build(&self) -> Result<Pcre, BuildError> {
+        let mut options = self.options;
+        if let Some(newline) = self.newline {
+            options.insert(match newline {
+                Newline::Cr => CompileOption::NewlineCR,
+                Newline::Lf => CompileOption::NewlineLF,
+                Newline::Crlf => CompileOption::NewlineCRLF,
+                Newline::Any => CompileOption::NewlineAny,
+                Newline::AnyCrlf => CompileOption::NewlineAnyCRLF
+            });
+        }
+        if let Some(bsr) = self.bsr {
+            options.insert(match bsr {
+                Bsr::AnyCrlf => CompileOption::BsrAnyCRLF,
+                Bsr::Unicode => CompileOption::BsrUnicode
+            });
+        }
+
+        let mut re = Pcre::compile_with_tables(&self.pattern, &options, self.tables.clone())?;
+
+        let has_limits = self.match_limit.is_some() || self.match_limit_recursion.is_some();
+        if self.study || self.jit || has_limits {
+            let mut study_options: EnumSet<StudyOption> = EnumSet::new();
+            if self.jit {
+                study_options.insert(StudyOption::StudyJitCompile);
+            }
+            if has_limits {
+                study_options.insert(StudyOption::StudyExtraNeeded);
+            }
+            // Nothing else refers to the new pattern yet, so this can study it directly.
+            re.extra = unsafe { detail::pcre_study(re.code, &study_options) }.map_err(BuildError::Study)?;
+        }
+
+        if let Some(extra) = re.extra() {
+            if let Some(limit) = self.match_limit {
+                extra.set_match_limit(limit);
+            }
+            if let Some(limit) = self.match_limit_recursion {
+                extra.set_match_limit_recursion(limit);
+            }
+        }
+
+        Ok(re)
+    }
+}
+
+impl Pcre {
+    /// Returns a [`PcreBuilder`](struct.PcreBuilder.html) for the regular expression `pattern`.
+    pub fn builder(pattern: &str) -> PcreBuilder {
+        PcreBuilder::new(pattern)
+    }
+}
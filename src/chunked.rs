@@ -0,0 +1,212 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finding the matches in input which arrives in chunks, the same as `Pcre::matches()`
+//! would find them in the whole input.
+
+use enum_set::{EnumSet};
+use std::io;
+use std::str;
+use std::vec::{Vec};
+
+use {ExecOption, Match, PartialResult, Pcre};
+
+/// The number of bytes that are read at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The result of a step of a `ChunkedMatcher`.
+pub enum Step<'t> {
+    /// The next match. `before` is the text between the previous step and the match, which
+    /// is empty if the match starts before the end of the previous one (with `\K` in a
+    /// lookbehind), and `offset` is the offset in the input of the text that the match is
+    /// in.
+    Match {
+        before: &'t str,
+        m: Match<'t>,
+        #[cfg_attr(not(feature = "async"), allow(dead_code))]
+        offset: u64
+    },
+
+    /// No more matches can be found before more input is read. `before` is the text after
+    /// the previous step which is known not to be part of a match.
+    NeedInput {
+        before: &'t str
+    },
+
+    /// There are no more matches. `before` is the rest of the input.
+    Done {
+        before: &'t str
+    }
+}
+
+/// Returns the offset in `text` of the character `n` characters before `offset`, or 0 if
+/// there are fewer characters.
+fn chars_before(text: &str, offset: usize, n: usize) -> usize {
+    if n == 0 {
+        return offset;
+    }
+    text[..offset].char_indices().rev().nth(n - 1).map_or(0, |(i, _)| i)
+}
+
+/// Finds the matches of a pattern in input which is read in chunks into its buffer. Only
+/// the text which a match could still start in is kept, along with the characters that
+/// lookbehind assertions look back at. A match which straddles chunks is found by partial
+/// matching with `ExecPartialHard`.
+///
+/// Read input into `spare()` and pass the number of bytes read to `filled()` whenever
+/// `needs_input()` is `true`, and call `next_step()` otherwise.
+pub struct ChunkedMatcher {
+
+    /// The input which is kept, followed by space for reading.
+    buffer: Vec<u8>,
+
+    /// The length of the input in `buffer`.
+    len: usize,
+
+    /// The length of the valid UTF-8 at the start of `buffer`, which can be followed by
+    /// the start of a character.
+    valid: usize,
+
+    /// The offset in the input of the start of `buffer`.
+    offset: u64,
+
+    /// The end of the text that was returned by the previous step.
+    stepped: usize,
+
+    /// The offset at which the search for the next match starts.
+    search: usize,
+
+    /// Whether the last match was an empty match which ended at `search`.
+    after_empty: bool,
+
+    /// Where the text which is kept when more input is read starts, if more input is
+    /// needed.
+    keep: Option<usize>,
+
+    eof: bool,
+
+    /// The number of characters before `search` which are kept.
+    lookbehind: usize
+
+}
+
+impl ChunkedMatcher {
+    pub fn new(re: &Pcre) -> ChunkedMatcher {
+        ChunkedMatcher {
+            buffer: Vec::new(),
+            len: 0,
+            valid: 0,
+            offset: 0,
+            stepped: 0,
+            search: 0,
+            after_empty: false,
+            keep: Some(0),
+            eof: false,
+            // Keeping a character before the search offset also lets `^` tell whether it
+            // follows a newline.
+            lookbehind: re.max_lookbehind().max(1)
+        }
+    }
+
+    /// Returns `true` if more input must be read before the next step.
+    pub fn needs_input(&self) -> bool {
+        self.keep.is_some()
+    }
+
+    /// Returns the space to read the next chunk of input into, discarding the text which
+    /// is no longer needed.
+    pub fn spare(&mut self) -> &mut [u8] {
+        if let Some(keep) = self.keep.filter(|&keep| keep > 0) {
+            self.buffer.copy_within(keep..self.len, 0);
+            self.len -= keep;
+            self.valid -= keep;
+            self.offset += keep as u64;
+            self.stepped -= keep;
+            self.search -= keep;
+            self.keep = Some(0);
+        }
+        self.buffer.resize(self.len + CHUNK_SIZE, 0);
+        &mut self.buffer[self.len..]
+    }
+
+    /// Adds the `n` bytes which were read into `spare()` to the input, where 0 bytes means
+    /// the end of the input.
+    ///
+    /// # Errors
+    /// An error of kind `io::ErrorKind::InvalidData` if the input is not valid UTF-8.
+    pub fn filled(&mut self, n: usize) -> io::Result<()> {
+        self.len += n;
+        self.eof = n == 0;
+        match str::from_utf8(&self.buffer[self.valid..self.len]) {
+            Ok(_) => self.valid = self.len,
+            // The end of the input read so far can be the start of a character.
+            Err(err) if err.error_len().is_none() && !self.eof => self.valid += err.valid_up_to(),
+            Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"))
+        }
+        self.keep = None;
+        Ok(())
+    }
+
+    /// Finds the next match of `re`, which must be the pattern that the matcher was
+    /// created for.
+    ///
+    /// # Panics
+    /// If more input is needed.
+    pub fn next_step(&mut self, re: &Pcre) -> Step<'_> {
+        assert!(self.keep.is_none(), "more input is needed");
+        // The text was checked by `filled()`.
+        let text = unsafe { str::from_utf8_unchecked(&self.buffer[..self.valid]) };
+        let stepped = self.stepped;
+
+        let mut options: EnumSet<ExecOption> = EnumSet::new();
+        if self.after_empty {
+            options.insert(ExecOption::ExecNotEmptyAtStart);
+        }
+        let result = if self.eof {
+            re.exec_from_with_options(text, self.search, &options).map_or(PartialResult::NoMatch, PartialResult::Match)
+        } else {
+            options.insert(ExecOption::ExecPartialHard);
+            re.exec_partial(text, self.search, &options)
+        };
+
+        // The offset up to which the text is returned, the offset where the search
+        // continues, and the first character that the search looked at.
+        let (end, search, inspected) = match result {
+            // libpcre does not report every match which ends at the end of the text as
+            // partial, an empty match at the end for one, although what follows can
+            // change it. Such a match is tried again with more input.
+            PartialResult::Match(ref m) if !self.eof && m.group_end(0) == text.len() => {
+                let start = m.group_start(0).max(stepped);
+                (start, start, None)
+            },
+            PartialResult::Match(m) => {
+                let (start, end) = (m.group_start(0), m.group_end(0));
+                self.after_empty = start == end;
+                self.stepped = end;
+                self.search = end;
+                return Step::Match { before: &text[stepped..start.max(stepped)], m, offset: self.offset };
+            },
+            PartialResult::Partial { start, match_start } => (match_start, match_start, Some(start)),
+            PartialResult::NoMatch => (text.len(), text.len(), None)
+        };
+        self.after_empty &= search == self.search;
+        self.search = search;
+        if self.eof {
+            self.stepped = end;
+            return Step::Done { before: &text[stepped..end] };
+        }
+
+        // With `\K` in a lookbehind, a match can start in the characters which are kept
+        // before `search`, so they are returned with a later step.
+        let keep = chars_before(text, search, self.lookbehind);
+        let end = end.min(keep).max(stepped);
+        self.stepped = end;
+        self.keep = Some(inspected.map_or(keep, |start| keep.min(start)));
+        Step::NeedInput { before: &text[stepped..end] }
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Deserializing the capture groups of a match into typed values, enabled by the `serde`
+//! feature.
+
+use serde::forward_to_deserialize_any;
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use std::collections::btree_map;
+use std::error::{Error};
+use std::fmt;
+use std::str;
+use std::string::{String};
+use std::vec::{Vec};
+
+use {Match, Pcre};
+
+/// The reason that a match could not be deserialized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeserializeError {
+
+    group: Option<String>,
+
+    message: String
+
+}
+
+impl DeserializeError {
+    /// Returns the name (or the number, for a tuple) of the capture group which could not be
+    /// converted, if the error is specific to one group.
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_ref().map(|group| &group[..])
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn in_group(mut self, group: &str) -> DeserializeError {
+        if self.group.is_none() {
+            self.group = Some(group.to_string());
+        }
+        self
+    }
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.group {
+            None => f.write_str(&self.message),
+            Some(ref group) => write!(f, "group `{}`: {}", group, self.message)
+        }
+    }
+}
+
+impl Error for DeserializeError {
+}
+
+impl de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(msg: T) -> DeserializeError {
+        DeserializeError {
+            group: None,
+            message: msg.to_string()
+        }
+    }
+}
+
+/// Deserializes a match as a map from group names to group texts, or as a sequence of the
+/// numbered groups.
+struct MatchDeserializer<'m, 'a: 'm> {
+
+    m: &'m Match<'a>
+
+}
+
+/// Deserializes the text of a capture group, which is `None` if the group did not
+/// participate in the match, and an error if it does not start and end on UTF-8 character
+/// boundaries.
+struct GroupDeserializer<'a> {
+
+    text: Option<Result<&'a str, str::Utf8Error>>
+
+}
+
+impl<'a> GroupDeserializer<'a> {
+    fn get(&self) -> Result<Option<&'a str>, DeserializeError> {
+        match self.text {
+            None => Ok(None),
+            Some(Ok(text)) => Ok(Some(text)),
+            Some(Err(_)) => Err(de::Error::custom("the group does not start and end on UTF-8 character boundaries"))
+        }
+    }
+
+    fn text(&self) -> Result<&'a str, DeserializeError> {
+        self.get()?.ok_or_else(|| de::Error::custom("the group did not participate in the match"))
+    }
+}
+
+macro_rules! deserialize_from_str {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+                let text = self.text()?;
+                match text.parse() {
+                    Err(err) => Err(de::Error::custom(format_args!("cannot convert {:?}: {}", text, err))),
+                    Ok(value) => visitor.$visit(value)
+                }
+            }
+        )*
+    }
+}
+
+impl<'a> Deserializer<'a> for GroupDeserializer<'a> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.get()? {
+            None => visitor.visit_none(),
+            Some(text) => visitor.visit_borrowed_str(text)
+        }
+    }
+
+    deserialize_from_str! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        visitor.visit_borrowed_str(self.text()?)
+    }
+
+    fn deserialize_string<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.get()? {
+            None => visitor.visit_none(),
+            Some(_) => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'a>>(self, _name: &'static str, visitor: V) -> Result<V::Value, DeserializeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Deserializes a unit variant with the same name as the group text.
+    fn deserialize_enum<V: Visitor<'a>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, DeserializeError> {
+        let text: &'a str = self.text()?;
+        visitor.visit_enum(text.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        <V: Visitor<'a>>
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'m, 'a> MatchDeserializer<'m, 'a> {
+    fn group(&self, n: usize) -> GroupDeserializer<'a> {
+        GroupDeserializer {
+            text: if self.m.is_set(n) { Some(str::from_utf8(self.m.group_bytes(n))) } else { None }
+        }
+    }
+}
+
+/// The named groups of a match, in name order.
+struct Names<'m, 'a: 'm> {
+
+    de: MatchDeserializer<'m, 'a>,
+
+    names: btree_map::Iter<'m, String, Vec<usize>>,
+
+    value: Option<(&'m str, GroupDeserializer<'a>)>
+
+}
+
+impl<'m, 'a> MapAccess<'a> for Names<'m, 'a> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'a>>(&mut self, seed: K) -> Result<Option<K::Value>, DeserializeError> {
+        match self.names.next() {
+            None => Ok(None),
+            Some((name, ns)) => {
+                // With duplicate names, the first group which participated in the match.
+                let n = ns.iter().cloned().find(|&n| self.de.m.is_set(n)).unwrap_or(ns[0]);
+                self.value = Some((name, self.de.group(n)));
+                seed.deserialize(name.as_str().into_deserializer()).map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'a>>(&mut self, seed: V) -> Result<V::Value, DeserializeError> {
+        let (name, group) = match self.value.take() {
+            None => return Err(de::Error::custom("value requested before key")),
+            Some(value) => value
+        };
+        seed.deserialize(group).map_err(|err| err.in_group(name))
+    }
+}
+
+/// The numbered groups of a match, starting at group 1.
+struct Numbers<'m, 'a: 'm> {
+
+    de: MatchDeserializer<'m, 'a>,
+
+    n: usize
+
+}
+
+impl<'m, 'a> SeqAccess<'a> for Numbers<'m, 'a> {
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'a>>(&mut self, seed: T) -> Result<Option<T::Value>, DeserializeError> {
+        if self.n * 2 >= self.de.m.partial_ovector.len() {
+            return Ok(None);
+        }
+        let n = self.n;
+        self.n += 1;
+        seed.deserialize(self.de.group(n)).map(Some).map_err(|err| err.in_group(&n.to_string()))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.de.m.partial_ovector.len() / 2).saturating_sub(self.n))
+    }
+}
+
+impl<'m, 'a> Deserializer<'a> for MatchDeserializer<'m, 'a> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        let names = self.m.name_table.iter();
+        visitor.visit_map(Names { de: self, names, value: None })
+    }
+
+    fn deserialize_seq<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        visitor.visit_seq(Numbers { de: self, n: 1 })
+    }
+
+    fn deserialize_tuple<V: Visitor<'a>>(self, _len: usize, visitor: V) -> Result<V::Value, DeserializeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'a>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, DeserializeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        <V: Visitor<'a>>
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct struct enum identifier ignored_any
+    }
+}
+
+impl<'a> Match<'a> {
+    /// Deserializes the capture groups of the match into a `T`.
+    ///
+    /// A struct or a map is deserialized from the named groups, and a tuple or a sequence
+    /// from the numbered groups starting at group 1. Groups which did not participate in the
+    /// match deserialize as `None`. Numbers, `bool`s and `char`s are converted from the
+    /// group text with `FromStr`, and enums from the variant name.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate pcre;
+    /// # #[macro_use] extern crate serde_derive;
+    /// # fn main() {
+    /// use pcre::Pcre;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Request<'a> {
+    ///     method: &'a str,
+    ///     path: &'a str,
+    ///     status: u16,
+    ///     size: Option<u64>
+    /// }
+    ///
+    /// let re = Pcre::compile(r"^(?<method>\w+) (?<path>\S+) (?<status>\d+)(?: (?<size>\d+))?$").unwrap();
+    /// let m = re.exec("GET /x 200 512").unwrap();
+    /// let request: Request = m.deserialize().unwrap();
+    /// assert_eq!((request.method, request.path, request.status, request.size), ("GET", "/x", 200, Some(512)));
+    /// # }
+    /// ```
+    pub fn deserialize<T: Deserialize<'a>>(&self) -> Result<T, DeserializeError> {
+        T::deserialize(MatchDeserializer { m: self })
+    }
+}
+
+impl Pcre {
+    /// Matches the regular expression against `subject` and deserializes the capture groups
+    /// of the first match into a `T`. See [Match::deserialize()](struct.Match.html#method.deserialize).
+    ///
+    /// # Return value
+    /// `Ok(None)` if there is no match, and `Err` if the match could not be deserialized.
+    pub fn captures_into<'a, T: Deserialize<'a>>(&self, subject: &'a str) -> Result<Option<T>, DeserializeError> {
+        match self.exec(subject) {
+            None => Ok(None),
+            Some(m) => m.deserialize().map(Some)
+        }
+    }
+}
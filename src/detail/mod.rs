@@ -7,13 +7,103 @@
 // except according to those terms.
 
 use enum_set::{EnumSet};
-use libc::{c_char, c_int, c_uchar, c_void};
+use libc::{c_char, c_int, c_uchar, c_ulong, c_void, size_t};
 use libpcre_sys;
-pub use libpcre_sys::{pcre, compile_options, exec_options, fullinfo_field, study_options, PCRE_UTF8, PCRE_NO_UTF8_CHECK, PCRE_ERROR_NOMATCH, PCRE_ERROR_NULL};
+pub use libpcre_sys::{pcre, pcre_callout_block, compile_options, exec_options, fullinfo_field, study_options, PCRE_UTF8, PCRE_NO_UTF8_CHECK, PCRE_ERROR_NOMATCH, PCRE_ERROR_NULL, PCRE_ERROR_CALLOUT, PCRE_ERROR_MATCHLIMIT, PCRE_ERROR_PARTIAL, PCRE_ERROR_RECURSIONLIMIT};
+use std::cell::{Cell};
 use std::ffi::{CStr};
 use std::ptr;
 use std::result::{Result};
 use std::string::{String};
+use std::sync::{Once};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant};
+
+/// The clock is only read on every `CLOCK_CHECK_INTERVAL`th callout because reading it is
+/// much more expensive than a callout.
+const CLOCK_CHECK_INTERVAL: u32 = 32;
+
+/// The size that the JIT stack of each thread starts at.
+const JIT_STACK_START_SIZE: c_int = 32 * 1024;
+
+/// The size that the JIT stack of each thread can grow to. libpcre uses 32 KiB of machine
+/// stack when no JIT stack is assigned.
+const JIT_STACK_MAX_SIZE: c_int = 1024 * 1024;
+
+/// Callout data for `interrupt_callout()`.
+pub struct InterruptState<'t> {
+    pub deadline: Option<Instant>,
+    pub cancelled: Option<&'t AtomicBool>,
+    pub callout_count: u32,
+    pub error: Option<::ExecError>
+}
+
+static INSTALL_CALLOUT: Once = Once::new();
+
+/// The callout function which was installed before `interrupt_callout()`, to which the
+/// callouts of other matches are passed on. It is only written by `INSTALL_CALLOUT`.
+static mut PREVIOUS_CALLOUT: Option<extern "C" fn(block: *mut pcre_callout_block) -> c_int> = None;
+
+thread_local! {
+    /// The callout data of the match which `with_interrupt_state()` is running on this
+    /// thread, if any.
+    static ACTIVE_STATE: Cell<*mut c_void> = const { Cell::new(ptr::null_mut()) };
+}
+
+/// Installs `interrupt_callout()` as libpcre's callout function, keeping the function which
+/// was installed before it.
+pub fn install_interrupt_callout() {
+    INSTALL_CALLOUT.call_once(|| unsafe {
+        PREVIOUS_CALLOUT = libpcre_sys::pcre_callout;
+        libpcre_sys::pcre_callout = Some(interrupt_callout);
+    });
+}
+
+/// Restores the previously active callout data of this thread when dropped.
+struct ActiveState(*mut c_void);
+
+impl Drop for ActiveState {
+    fn drop(&mut self) {
+        ACTIVE_STATE.with(|active| active.set(self.0));
+    }
+}
+
+/// Calls `f` with the callout data for `state`, which `interrupt_callout()` recognizes
+/// while `f` runs.
+pub fn with_interrupt_state<R, F: FnOnce(*mut c_void) -> R>(state: &mut InterruptState, f: F) -> R {
+    let data = state as *mut InterruptState as *mut c_void;
+    let _restore = ActiveState(ACTIVE_STATE.with(|active| active.replace(data)));
+    f(data)
+}
+
+extern "C" fn interrupt_callout(block: *mut pcre_callout_block) -> c_int {
+    unsafe {
+        let data = (*block).callout_data;
+        // The callouts of matches which were not started by `with_interrupt_state()` go to
+        // the callout function of whoever started them.
+        if data.is_null() || ACTIVE_STATE.with(|active| active.get()) != data {
+            return match PREVIOUS_CALLOUT {
+                Some(previous) => previous(block),
+                None => 0
+            };
+        }
+        let state = &mut *(data as *mut InterruptState);
+        if let Some(cancelled) = state.cancelled {
+            if cancelled.load(Ordering::Relaxed) {
+                state.error = Some(::ExecError::Cancelled);
+                return PCRE_ERROR_CALLOUT;
+            }
+        }
+        if let Some(deadline) = state.deadline {
+            if state.callout_count.is_multiple_of(CLOCK_CHECK_INTERVAL) && Instant::now() >= deadline {
+                state.error = Some(::ExecError::TimedOut);
+                return PCRE_ERROR_CALLOUT;
+            }
+        }
+        state.callout_count = state.callout_count.wrapping_add(1);
+        0
+    }
+}
 
 pub unsafe fn pcre_compile(pattern: *const c_char, options: &EnumSet<::CompileOption>, tableptr: *const c_uchar) -> Result<*mut pcre, (Option<String>, c_int)> {
     assert!(!pattern.is_null());
@@ -43,20 +133,117 @@ pub unsafe fn pcre_compile(pattern: *const c_char, options: &EnumSet<::CompileOp
     }
 }
 
-pub unsafe fn pcre_exec(code: *const pcre, extra: *const ::PcreExtra, subject: *const c_char, length: c_int, startoffset: c_int, options: &EnumSet<::ExecOption>, ovector: *mut c_int, ovecsize: c_int) -> c_int {
+/// Converts `options` to the bits to pass to `pcre_exec()`.
+pub fn exec_options(options: &EnumSet<::ExecOption>) -> exec_options {
+    options.iter().fold(0, |converted_options, option| converted_options | (option as exec_options))
+}
+
+/// Like `try_pcre_exec()`, but panics if a match limit is exceeded, for the methods which
+/// cannot report it.
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn pcre_exec(code: *const pcre, extra: *const ::PcreExtra, subject: *const c_char, length: c_int, startoffset: c_int, options: exec_options, ovector: *mut c_int, ovecsize: c_int) -> c_int {
+    let rc = try_pcre_exec(code, extra, subject, length, startoffset, options, ovector, ovecsize);
+    if rc == PCRE_ERROR_MATCHLIMIT {
+        match_limit_exceeded();
+    }
+    rc
+}
+
+/// Panics because a match exceeded the match limit or the recursion depth limit.
+pub fn match_limit_exceeded() -> ! {
+    panic!("pcre_exec: the match limit or the recursion depth limit was exceeded");
+}
+
+/// Calls libpcre's `pcre_exec()`, returning `PCRE_ERROR_MATCHLIMIT` if the match limit or
+/// the recursion depth limit is exceeded.
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn try_pcre_exec(code: *const pcre, extra: *const ::PcreExtra, subject: *const c_char, length: c_int, startoffset: c_int, options: exec_options, ovector: *mut c_int, ovecsize: c_int) -> c_int {
     assert!(!code.is_null());
     assert!(ovecsize >= 0 && ovecsize % 3 == 0);
-    let converted_options = options.iter().fold(0, |converted_options, option| converted_options | (option as compile_options)) | PCRE_NO_UTF8_CHECK;
+    let converted_options = options | PCRE_NO_UTF8_CHECK;
     let rc = libpcre_sys::pcre_exec(code, extra, subject, length, startoffset, converted_options, ovector, ovecsize);
+    exec_result(rc, "pcre_exec")
+}
+
+/// Converts the return code `rc` of `function`, panicking on errors that cannot happen or
+/// are not handled.
+fn exec_result(rc: c_int, function: &str) -> c_int {
     if rc == PCRE_ERROR_NOMATCH {
         return -1;
-    } else if rc < 0 && rc != PCRE_ERROR_NULL {
-        panic!("pcre_exec");
+    } else if rc == PCRE_ERROR_RECURSIONLIMIT || rc == libpcre_sys::PCRE_ERROR_JIT_STACKLIMIT {
+        // These limits are all reported as `ExecError::MatchLimit`.
+        return PCRE_ERROR_MATCHLIMIT;
+    } else if rc < 0 && rc != PCRE_ERROR_NULL && rc != PCRE_ERROR_CALLOUT && rc != PCRE_ERROR_MATCHLIMIT && rc != PCRE_ERROR_PARTIAL {
+        panic!("{}", function);
     }
 
     rc
 }
 
+/// A JIT stack, which is only used by the thread that owns it.
+struct JitStack(*mut libpcre_sys::pcre_jit_stack);
+
+impl Drop for JitStack {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { libpcre_sys::pcre_jit_stack_free(self.0) };
+        }
+    }
+}
+
+thread_local! {
+    /// The JIT stack of this thread, which is allocated when it is first used. It is null if
+    /// the allocation failed.
+    static JIT_STACK: JitStack = JitStack(unsafe { libpcre_sys::pcre_jit_stack_alloc(JIT_STACK_START_SIZE, JIT_STACK_MAX_SIZE) });
+}
+
+/// Returns the JIT stack of the calling thread to libpcre, which uses the 32 KiB of machine
+/// stack instead if it is null.
+extern "C" fn jit_stack_callback(_: *mut c_void) -> *mut libpcre_sys::pcre_jit_stack {
+    JIT_STACK.try_with(|stack| stack.0).unwrap_or(ptr::null_mut())
+}
+
+/// Returns `true` if CRLF is a valid newline sequence when matching `code` with the exec
+/// options `options`.
+pub unsafe fn crlf_is_newline(code: *const pcre, options: exec_options) -> bool {
+    use libpcre_sys::{PCRE_NEWLINE_CR, PCRE_NEWLINE_LF, PCRE_NEWLINE_CRLF, PCRE_NEWLINE_ANY, PCRE_NEWLINE_ANYCRLF, PCRE_NEWLINE_MASK};
+
+    // A newline convention passed to pcre_exec() overrides the one of the pattern, which
+    // overrides the default that libpcre was built with.
+    let mut newline = options & PCRE_NEWLINE_MASK;
+    if newline == 0 {
+        let mut compile_options: c_ulong = 0;
+        pcre_fullinfo(code, ptr::null(), libpcre_sys::PCRE_INFO_OPTIONS, &mut compile_options as *mut c_ulong as *mut c_void);
+        newline = (compile_options as c_int) & PCRE_NEWLINE_MASK;
+    }
+    if newline == 0 {
+        let mut default_newline: c_int = 0;
+        libpcre_sys::pcre_config(libpcre_sys::PCRE_CONFIG_NEWLINE, &mut default_newline as *mut c_int as *mut c_void);
+        newline = match default_newline {
+            13 => PCRE_NEWLINE_CR,
+            10 => PCRE_NEWLINE_LF,
+            0x0d0a => PCRE_NEWLINE_CRLF,
+            -2 => PCRE_NEWLINE_ANYCRLF,
+            -1 => PCRE_NEWLINE_ANY,
+            _ => 0
+        };
+    }
+    newline == PCRE_NEWLINE_CRLF || newline == PCRE_NEWLINE_ANY || newline == PCRE_NEWLINE_ANYCRLF
+}
+
+pub unsafe fn pcre_malloc(size: usize) -> *mut c_void {
+    let ptr = (libpcre_sys::pcre_malloc)(size as size_t);
+    assert!(!ptr.is_null(), "pcre_malloc() failed");
+    ptr
+}
+
+/// Converts the compiled pattern `code` to the host's byte order if needed, returning `false`
+/// if it is not a compiled pattern.
+pub unsafe fn pcre_pattern_to_host_byte_order(code: *mut pcre) -> bool {
+    assert!(!code.is_null());
+    libpcre_sys::pcre_pattern_to_host_byte_order(code, ptr::null_mut(), ptr::null()) == 0
+}
+
 pub unsafe fn pcre_free(ptr: *mut c_void) {
     libpcre_sys::pcre_free(ptr);
 }
@@ -84,7 +271,13 @@ pub unsafe fn pcre_refcount(code: *mut ::detail::pcre, adjust: c_int) -> c_int {
     libpcre_sys::pcre_refcount(code, adjust)
 }
 
-pub unsafe fn pcre_study(code: *const ::detail::pcre, options: &EnumSet<::StudyOption>) -> *mut ::PcreExtra {
+pub fn pcre_maketables() -> *const c_uchar {
+    let tables = unsafe { libpcre_sys::pcre_maketables() };
+    assert!(!tables.is_null(), "pcre_maketables() failed");
+    tables
+}
+
+pub unsafe fn pcre_study(code: *const ::detail::pcre, options: &EnumSet<::StudyOption>) -> Result<*mut ::PcreExtra, String> {
     assert!(!code.is_null());
     let converted_options = options.iter().fold(0, |converted_options, option| converted_options | (option as study_options));
     let mut err: *const c_char = ptr::null();
@@ -96,15 +289,16 @@ pub unsafe fn pcre_study(code: *const ::detail::pcre, options: &EnumSet<::StudyO
     // http://pcre.org/pcre.txt
     if !err.is_null() {
         let err_cstr = CStr::from_ptr(err);
-        match String::from_utf8(Vec::from(err_cstr.to_bytes())) {
-            Err(_) => panic!("pcre_study() failed"),
-            Ok(err_str) => panic!("pcre_study() failed: {}", err_str)
-        }
-        panic!("pcre_study");
+        return Err(String::from_utf8_lossy(err_cstr.to_bytes()).into_owned());
+    }
+
+    // JIT-compiled code runs with the JIT stack of the thread which matches. This does
+    // nothing if the pattern was not JIT-compiled.
+    if !extra.is_null() {
+        libpcre_sys::pcre_assign_jit_stack(extra, Some(jit_stack_callback), ptr::null_mut());
     }
-    assert!(err.is_null());
 
-    extra
+    Ok(extra)
 }
 
 pub fn pcre_version() -> String {
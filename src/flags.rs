@@ -0,0 +1,283 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversions between compile options and Perl-style flag strings, and parsing of
+//! delimited pattern literals such as `/^foo.*bar$/imx`.
+//!
+//! The flags are those of the `pcretest` program:
+//!
+//! | Flag         | Option             |
+//! |--------------|--------------------|
+//! | `i`          | `Caseless`         |
+//! | `m`          | `Multiline`        |
+//! | `s`          | `DotAll`           |
+//! | `x`          | `Extended`         |
+//! | `A`          | `Anchored`         |
+//! | `E`          | `DollarEndOnly`    |
+//! | `X`          | `Extra`            |
+//! | `U`          | `Ungreedy`         |
+//! | `N`          | `NoAutoCapture`    |
+//! | `C`          | `AutoCallout`      |
+//! | `f`          | `FirstLine`        |
+//! | `J`          | `DupNames`         |
+//! | `W`          | `Ucp`              |
+//! | `<cr>`       | `NewlineCR`        |
+//! | `<lf>`       | `NewlineLF`        |
+//! | `<crlf>`     | `NewlineCRLF`      |
+//! | `<any>`      | `NewlineAny`       |
+//! | `<anycrlf>`  | `NewlineAnyCRLF`   |
+//! | `<bsr_anycrlf>` | `BsrAnyCRLF`    |
+//! | `<bsr_unicode>` | `BsrUnicode`    |
+//! | `<JS>`       | `JavaScriptCompat` |
+//!
+//! The names between angle brackets are not case-sensitive.
+
+use enum_set::{EnumSet};
+use std::error::{Error};
+use std::fmt;
+use std::string::{String};
+
+use CompileOption;
+use CompileOption::*;
+
+/// The single-character flags.
+const FLAGS: &[(char, CompileOption)] = &[
+    ('i', Caseless),
+    ('m', Multiline),
+    ('s', DotAll),
+    ('x', Extended),
+    ('A', Anchored),
+    ('E', DollarEndOnly),
+    ('X', Extra),
+    ('U', Ungreedy),
+    ('N', NoAutoCapture),
+    ('C', AutoCallout),
+    ('f', FirstLine),
+    ('J', DupNames),
+    ('W', Ucp)
+];
+
+/// The flags between angle brackets.
+const NAMED_FLAGS: &[(&str, CompileOption)] = &[
+    ("cr", NewlineCR),
+    ("lf", NewlineLF),
+    ("crlf", NewlineCRLF),
+    ("any", NewlineAny),
+    ("anycrlf", NewlineAnyCRLF),
+    ("bsr_anycrlf", BsrAnyCRLF),
+    ("bsr_unicode", BsrUnicode),
+    ("JS", JavaScriptCompat)
+];
+
+const NEWLINE_OPTIONS: &[CompileOption] = &[NewlineCR, NewlineLF, NewlineCRLF, NewlineAny, NewlineAnyCRLF];
+
+const BSR_OPTIONS: &[CompileOption] = &[BsrAnyCRLF, BsrUnicode];
+
+/// The reason that a flag string or a pattern literal could not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// A flag character is not recognized.
+    UnknownFlag(char),
+
+    /// A flag name between angle brackets is not recognized, or the closing `>` is missing.
+    UnknownNamedFlag(String),
+
+    /// The literal is empty.
+    Empty,
+
+    /// The first character of the literal cannot be used as a delimiter. Letters, digits,
+    /// whitespace and `\` cannot be.
+    InvalidDelimiter(char),
+
+    /// There is no closing delimiter.
+    Unterminated
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::UnknownFlag(c) => write!(f, "unknown flag '{}'", c),
+            ParseError::UnknownNamedFlag(ref name) => write!(f, "unknown flag '<{}>'", name),
+            ParseError::Empty => write!(f, "empty pattern literal"),
+            ParseError::InvalidDelimiter(c) => write!(f, "invalid delimiter '{}'", c),
+            ParseError::Unterminated => write!(f, "missing closing delimiter")
+        }
+    }
+}
+
+impl Error for ParseError {
+}
+
+/// Converts the flag string `flags` to compile options.
+///
+/// When several newline or `\R` flags are given, the last one of each kind wins.
+///
+/// # Example
+/// ```
+/// use pcre::CompileOption;
+/// use pcre::flags;
+///
+/// let options = flags::parse_flags("imx<crlf>").unwrap();
+/// assert!(options.contains(&CompileOption::Caseless));
+/// assert!(options.contains(&CompileOption::NewlineCRLF));
+/// ```
+pub fn parse_flags(flags: &str) -> Result<EnumSet<CompileOption>, ParseError> {
+    let mut options: EnumSet<CompileOption> = EnumSet::new();
+    let mut rest = flags;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = match rest.find('>') {
+                None => return Err(ParseError::UnknownNamedFlag(rest[1..].to_string())),
+                Some(end) => end
+            };
+            let name = &rest[1..end];
+            let option = match NAMED_FLAGS.iter().find(|&&(n, _)| n.eq_ignore_ascii_case(name)) {
+                None => return Err(ParseError::UnknownNamedFlag(name.to_string())),
+                Some(&(_, option)) => option
+            };
+            // These options are values of a single field rather than independent bits.
+            let kind = if NEWLINE_OPTIONS.contains(&option) {
+                NEWLINE_OPTIONS
+            } else if BSR_OPTIONS.contains(&option) {
+                BSR_OPTIONS
+            } else {
+                &[]
+            };
+            for other in kind.iter() {
+                options.remove(other);
+            }
+            options.insert(option);
+            rest = &rest[(end + 1)..];
+        } else {
+            match FLAGS.iter().find(|&&(f, _)| f == c) {
+                None => return Err(ParseError::UnknownFlag(c)),
+                Some(&(_, option)) => options.insert(option)
+            };
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Ok(options)
+}
+
+/// Converts compile options to a flag string. The single-character flags come first, in
+/// the order of the table above.
+pub fn format_flags(options: &EnumSet<CompileOption>) -> String {
+    let mut flags = String::new();
+    for &(c, option) in FLAGS.iter() {
+        if options.contains(&option) {
+            flags.push(c);
+        }
+    }
+    for &(name, option) in NAMED_FLAGS.iter() {
+        if options.contains(&option) {
+            flags.push('<');
+            flags.push_str(name);
+            flags.push('>');
+        }
+    }
+    flags
+}
+
+/// Parses a delimited pattern literal such as `/^foo.*bar$/imx` into the pattern and its
+/// compile options.
+///
+/// The first character is the delimiter. If it is one of `(`, `[`, `{` or `<`, then the
+/// pattern ends at the matching closing bracket, and pairs of brackets within the pattern
+/// nest. Otherwise, the pattern ends at the next occurrence of the delimiter. A delimiter
+/// preceded by `\` does not end the pattern; the backslash is kept, so that the pattern is
+/// passed to libpcre exactly as written. The rest of the literal is a flag string as for
+/// [parse_flags()](fn.parse_flags.html).
+///
+/// # Example
+/// ```
+/// use pcre::flags;
+///
+/// let (pattern, options) = flags::parse_literal(r"/a\/b/i").unwrap();
+/// assert_eq!(pattern, r"a\/b");
+/// assert_eq!(flags::format_flags(&options), "i");
+/// ```
+pub fn parse_literal(literal: &str) -> Result<(String, EnumSet<CompileOption>), ParseError> {
+    let mut chars = literal.char_indices();
+    let open = match chars.next() {
+        None => return Err(ParseError::Empty),
+        Some((_, c)) => c
+    };
+    if open.is_alphanumeric() || open.is_whitespace() || open == '\\' {
+        return Err(ParseError::InvalidDelimiter(open));
+    }
+    let close = match open {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '<' => '>',
+        c => c
+    };
+
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in chars {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == close {
+            if depth == 0 {
+                let options = parse_flags(&literal[(i + c.len_utf8())..])?;
+                return Ok((literal[open.len_utf8()..i].to_string(), options));
+            }
+            depth -= 1;
+        } else if c == open {
+            depth += 1;
+        }
+    }
+    Err(ParseError::Unterminated)
+}
+
+/// Formats a pattern and its compile options as a `/`-delimited literal which
+/// [parse_literal()](fn.parse_literal.html) parses back to the same pattern (or to an
+/// equivalent one, if `pattern` contains an unescaped `/`) and options.
+pub fn format_literal(pattern: &str, options: &EnumSet<CompileOption>) -> String {
+    let mut literal = String::with_capacity(pattern.len() + 2);
+    literal.push('/');
+
+    // Within `\Q...\E`, a backslash only escapes `E`, so the quote has to be ended to
+    // escape a `/`.
+    let mut in_quote = false;
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quote {
+            match c {
+                '\\' if chars.peek() == Some(&'E') => {
+                    chars.next();
+                    literal.push_str("\\E");
+                    in_quote = false;
+                },
+                // A backslash at the end would escape the closing delimiter.
+                '\\' if chars.peek().is_none() => literal.push_str("\\\\E"),
+                '/' => literal.push_str("\\E\\/\\Q"),
+                c => literal.push(c)
+            }
+        } else {
+            match c {
+                '\\' => {
+                    literal.push(c);
+                    if let Some(next) = chars.next() {
+                        in_quote = next == 'Q';
+                        literal.push(next);
+                    }
+                },
+                '/' => literal.push_str("\\/"),
+                c => literal.push(c)
+            }
+        }
+    }
+
+    literal.push('/');
+    literal.push_str(&format_flags(options));
+    literal
+}
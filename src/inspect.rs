@@ -0,0 +1,97 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checks on the text of patterns which libpcre does not report, such as whether a pattern
+//! can be combined with others.
+
+use enum_set::{EnumSet};
+
+use CompileOption;
+use CompileOption::*;
+
+/// Returns `true` if `pattern` refers to a capture group by its absolute number, in a back
+/// reference (`\1`, `\g1`, `\g{1}`), a subroutine call (`(?1)`, `\g<1>`) or a condition
+/// (`(?(1)`, `(?(R1)`).
+///
+/// Such numbers refer to other groups when the pattern is combined with others, for
+/// example into an alternation. `options` are the compile options of the pattern; with
+/// `Extended`, `#` starts a comment.
+///
+/// # Example
+/// ```
+/// use pcre::inspect::has_numbered_reference;
+///
+/// assert!(has_numbered_reference(r#"(['"]).*?\1"#, &Default::default()));
+/// assert!(!has_numbered_reference(r#"(['"]).*?\g{-1}"#, &Default::default()));
+/// ```
+pub fn has_numbered_reference(pattern: &str, options: &EnumSet<CompileOption>) -> bool {
+    let extended = options.contains(&Extended);
+    let bytes = pattern.as_bytes();
+    let is_digit = |i: usize| i < bytes.len() && bytes[i].is_ascii_digit();
+    let skip_past = |i: usize, end: &[u8]| {
+        bytes[i..].windows(end.len()).position(|window| window == end).map_or(bytes.len(), |n| i + n + end.len())
+    };
+    let mut in_class = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => match bytes.get(i + 1) {
+                Some(&b'Q') => {
+                    i = skip_past(i + 2, b"\\E");
+                    continue;
+                },
+                // In a character class, `\1` is an octal escape.
+                Some(b) if !in_class && (b'1'..=b'9').contains(b) => return true,
+                Some(&b'g') if !in_class => {
+                    let open = matches!(bytes.get(i + 2), Some(&b'{') | Some(&b'<') | Some(&b'\''));
+                    if is_digit(if open { i + 3 } else { i + 2 }) {
+                        return true;
+                    }
+                },
+                _ => {}
+            },
+            b'[' if !in_class => {
+                in_class = true;
+                // A `]` at the start of the class is a literal.
+                if bytes.get(i + 1) == Some(&b'^') {
+                    i += 1;
+                }
+                if bytes.get(i + 1) == Some(&b']') {
+                    i += 1;
+                }
+            },
+            b'[' if bytes.get(i + 1) == Some(&b':') => {
+                i = skip_past(i + 2, b":]");
+                continue;
+            },
+            b']' => in_class = false,
+            b'#' if extended && !in_class => {
+                i = skip_past(i + 1, b"\n");
+                continue;
+            },
+            b'(' if !in_class && bytes.get(i + 1) == Some(&b'?') => {
+                if bytes.get(i + 2) == Some(&b'#') {
+                    i = skip_past(i + 3, b")");
+                    continue;
+                }
+                if is_digit(i + 2) {
+                    return true;
+                }
+                if bytes.get(i + 2) == Some(&b'(') {
+                    let condition = if bytes.get(i + 3) == Some(&b'R') { i + 4 } else { i + 3 };
+                    if is_digit(condition) {
+                        return true;
+                    }
+                }
+            },
+            _ => {}
+        }
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    false
+}
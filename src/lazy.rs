@@ -0,0 +1,95 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::iter::{Map};
+use std::marker::{PhantomData};
+use std::ops::{Deref};
+use std::sync::{OnceLock};
+
+use {Match, MatchIterator, Pcre};
+
+/// A type which wraps a [`Match`](struct.Match.html) to give typed access to its capture
+/// groups. The `pcre!` macro implements this for the captures types that it generates.
+pub trait CaptureGroups {
+    /// The wrapper type for a match in a subject string with lifetime `'a`.
+    type Captures<'a>;
+
+    /// Wraps the match `m`.
+    fn from_match(m: Match<'_>) -> Self::Captures<'_>;
+}
+
+impl<'x> CaptureGroups for Match<'x> {
+    type Captures<'a> = Match<'a>;
+
+    fn from_match(m: Match<'_>) -> Match<'_> {
+        m
+    }
+}
+
+/// A regular expression which is compiled the first time that it is used, so that it can
+/// be stored in a `static`.
+///
+/// `C` is the type that matches are returned as. See [`CaptureGroups`](trait.CaptureGroups.html).
+///
+/// # Example
+/// ```
+/// use pcre::LazyPcre;
+///
+/// static WORD: LazyPcre = LazyPcre::new(r"\w+");
+///
+/// assert_eq!(WORD.captures("  hello ").unwrap().group(0), "hello");
+/// ```
+pub struct LazyPcre<C = Match<'static>> {
+
+    pattern: &'static str,
+
+    re: OnceLock<Pcre>,
+
+    _marker: PhantomData<fn() -> C>
+
+}
+
+impl<C> LazyPcre<C> {
+    /// Creates a regular expression which is compiled from `pattern` when first used.
+    pub const fn new(pattern: &'static str) -> LazyPcre<C> {
+        LazyPcre {
+            pattern,
+            re: OnceLock::new(),
+            _marker: PhantomData
+        }
+    }
+}
+
+impl<C: CaptureGroups> LazyPcre<C> {
+    /// Matches the regular expression against `subject`, returning the first match.
+    pub fn captures<'a>(&self, subject: &'a str) -> Option<C::Captures<'a>> {
+        self.exec(subject).map(C::from_match)
+    }
+
+    /// Returns an iterator over the matches within `subject`. See
+    /// [Pcre::matches()](struct.Pcre.html#method.matches).
+    pub fn captures_iter<'a, 'p>(&'p self, subject: &'a str) -> Map<MatchIterator<'a, 'p>, fn(Match<'a>) -> C::Captures<'a>> {
+        self.matches(subject).map(C::from_match)
+    }
+}
+
+impl<C> Deref for LazyPcre<C> {
+    type Target = Pcre;
+
+    /// Returns the compiled regular expression, compiling it if needed.
+    ///
+    /// # Panics
+    /// If the pattern does not compile. Patterns given to the `pcre!` macro are checked
+    /// when the program is built.
+    fn deref(&self) -> &Pcre {
+        self.re.get_or_init(|| match Pcre::compile(self.pattern) {
+            Err(err) => panic!("invalid pattern {:?}: {}", self.pattern, err),
+            Ok(re) => re
+        })
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Tokenizing with a set of named rules, which are combined into a single regular
+//! expression.
+//!
+//! Each rule becomes one branch of an alternation which ends with `(*MARK:kind)`, so one
+//! anchored match at the current position both finds the token and, through the MARK, the
+//! rule that matched it. Branches are tried in priority order, so the first rule that
+//! matches wins, not the longest match. The capture groups of all of the rules are
+//! numbered together, so a rule must refer to its groups by name or relatively, as in
+//! `\g{-1}`; rules with numbered references are rejected.
+//!
+//! # Example
+//! ```
+//! use pcre::lexer::{LexerBuilder, Token};
+//!
+//! let lexer = LexerBuilder::new()
+//!     .token("number", r"\d+")
+//!     .token("ident", r"[a-z]\w*")
+//!     .token("keyword", r"(?:let|in)\b")
+//!     .token("op", r"[=+]")
+//!     .skip("space", r"\s+")
+//!     .priority("keyword", 1)
+//!     .build()
+//!     .unwrap();
+//!
+//! let kinds: Vec<&str> = lexer.tokens("let x = 1 + y?").map(|token| token.kind).collect();
+//! assert_eq!(kinds, ["keyword", "ident", "op", "number", "op", "ident", "error"]);
+//! ```
+
+use enum_set::{EnumSet};
+use std::cmp::{Reverse};
+use std::error::{Error};
+use std::fmt;
+use std::ops::{Range};
+use std::string::{String};
+use std::vec::{Vec};
+
+use inspect;
+use {CompilationError, CompileOption, ExecOption, Pcre};
+
+/// The kind of the tokens which cover input that no rule matches.
+pub const ERROR: &str = "error";
+
+/// The longest name that `(*MARK)` accepts.
+const MAX_KIND_LEN: usize = 255;
+
+/// A token rule.
+#[derive(Clone, Debug)]
+struct Rule {
+
+    kind: String,
+
+    pattern: String,
+
+    skip: bool,
+
+    priority: i32
+
+}
+
+/// A builder for a [`Lexer`](struct.Lexer.html).
+#[derive(Clone, Debug, Default)]
+pub struct LexerBuilder {
+
+    rules: Vec<Rule>,
+
+    priorities: Vec<(String, i32)>,
+
+    options: EnumSet<CompileOption>
+
+}
+
+/// The reason that [`LexerBuilder::build()`](struct.LexerBuilder.html#method.build) failed.
+#[derive(Debug)]
+pub enum LexerError {
+    /// The kind cannot be used as a `(*MARK)` name: it is empty, longer than 255 bytes,
+    /// contains `)` or a NUL character, or is [`ERROR`](constant.ERROR.html).
+    InvalidKind(String),
+
+    /// Two rules have the same kind.
+    DuplicateKind(String),
+
+    /// A priority was given for a kind that has no rule.
+    UnknownKind(String),
+
+    /// The pattern of the rule for `kind` refers to a capture group by number, with `\1`,
+    /// `\g{1}`, `(?1)` or `(?(1)...)`. The groups of all of the rules are numbered together
+    /// in the combined pattern, so such a number would refer to the wrong group. Name the
+    /// group, or use a relative reference such as `\g{-1}` or `(?-1)`.
+    NumberedReference(String),
+
+    /// The pattern of the rule for `kind` could not be compiled. If each pattern compiles on
+    /// its own but the combined pattern does not, for example because two rules have a
+    /// group with the same name, then `kind` is the rule at the error offset and `error` is
+    /// for the combined pattern.
+    Compile {
+        kind: String,
+        error: CompilationError
+    }
+}
+
+/// A tokenizer built from a set of rules. See the [module documentation](index.html).
+#[derive(Debug)]
+pub struct Lexer {
+
+    re: Pcre,
+
+    rules: Vec<Rule>,
+
+    /// Options for matching a token at the current position.
+    anchored: EnumSet<ExecOption>,
+
+    /// Options for finding the end of unmatched input.
+    search: EnumSet<ExecOption>
+
+}
+
+/// A token, with its kind (the name of the rule which matched it, or
+/// [`ERROR`](constant.ERROR.html)), its byte offsets in the subject, and its text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+
+    pub kind: &'a str,
+
+    pub span: Range<usize>,
+
+    pub text: &'a str
+
+}
+
+/// An iterator over the tokens of a subject string, excluding the tokens of skip rules.
+#[derive(Debug)]
+pub struct Tokens<'a> {
+
+    lexer: &'a Lexer,
+
+    subject: &'a str,
+
+    offset: usize
+
+}
+
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LexerError::InvalidKind(ref kind) => write!(f, "invalid token kind {:?}", kind),
+            LexerError::DuplicateKind(ref kind) => write!(f, "duplicate token kind {:?}", kind),
+            LexerError::UnknownKind(ref kind) => write!(f, "no rule for token kind {:?}", kind),
+            LexerError::NumberedReference(ref kind) => write!(f, "rule {:?}: numbered group references are not supported", kind),
+            LexerError::Compile { ref kind, ref error } => write!(f, "rule {:?}: {}", kind, error)
+        }
+    }
+}
+
+impl Error for LexerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LexerError::Compile { ref error, .. } => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl LexerBuilder {
+    /// Creates a builder with no rules.
+    pub fn new() -> LexerBuilder {
+        LexerBuilder::default()
+    }
+
+    fn rule(&mut self, kind: &str, pattern: &str, skip: bool) -> &mut LexerBuilder {
+        self.rules.push(Rule {
+            kind: kind.to_string(),
+            pattern: pattern.to_string(),
+            skip,
+            priority: 0
+        });
+        self
+    }
+
+    /// Adds a rule for tokens of kind `kind`, which match `pattern`.
+    pub fn token(&mut self, kind: &str, pattern: &str) -> &mut LexerBuilder {
+        self.rule(kind, pattern, false)
+    }
+
+    /// Adds a rule for input which is matched but not returned as a token, such as
+    /// whitespace and comments.
+    pub fn skip(&mut self, kind: &str, pattern: &str) -> &mut LexerBuilder {
+        self.rule(kind, pattern, true)
+    }
+
+    /// Sets the priority of the rule for `kind`. Rules are tried from the highest priority
+    /// to the lowest, and rules with the same priority in the order that they were added.
+    /// The default priority is 0.
+    pub fn priority(&mut self, kind: &str, priority: i32) -> &mut LexerBuilder {
+        self.priorities.push((kind.to_string(), priority));
+        self
+    }
+
+    /// Sets the compile options of the combined pattern, which apply to every rule.
+    pub fn options(&mut self, options: &EnumSet<CompileOption>) -> &mut LexerBuilder {
+        self.options = *options;
+        self
+    }
+
+    /// Compiles the rules into a lexer.
+    pub fn build(&self) -> Result<Lexer, LexerError> {
+        let mut rules = self.rules.clone();
+        for (i, rule) in rules.iter().enumerate() {
+            let kind = &rule.kind[..];
+            if kind.is_empty() || kind.len() > MAX_KIND_LEN || kind.contains([')', '\0']) || kind == ERROR {
+                return Err(LexerError::InvalidKind(kind.to_string()));
+            }
+            if rules[..i].iter().any(|other| other.kind == kind) {
+                return Err(LexerError::DuplicateKind(kind.to_string()));
+            }
+            if let Err(error) = Pcre::compile_with_options(&rule.pattern, &self.options) {
+                return Err(LexerError::Compile { kind: kind.to_string(), error });
+            }
+            if inspect::has_numbered_reference(&rule.pattern, &self.options) {
+                return Err(LexerError::NumberedReference(kind.to_string()));
+            }
+        }
+        for &(ref kind, priority) in self.priorities.iter() {
+            match rules.iter_mut().find(|rule| rule.kind == *kind) {
+                None => return Err(LexerError::UnknownKind(kind.clone())),
+                Some(rule) => rule.priority = priority
+            }
+        }
+        // A stable sort, so that rules with the same priority stay in order.
+        rules.sort_by_key(|rule| Reverse(rule.priority));
+
+        // In extended mode, the pattern can end with a comment.
+        let end = if self.options.contains(&CompileOption::Extended) { "\n" } else { "" };
+        let mut pattern = String::new();
+        let mut starts = Vec::with_capacity(rules.len());
+        for rule in rules.iter() {
+            if !pattern.is_empty() {
+                pattern.push('|');
+            }
+            starts.push(pattern.len());
+            pattern.push_str(&format!("(?:{}{})(*MARK:{})", rule.pattern, end, rule.kind));
+        }
+        let mut re = match Pcre::compile_with_options(&pattern, &self.options) {
+            Err(error) => {
+                let i = starts.iter().rposition(|&start| start <= error.offset()).unwrap_or(0);
+                return Err(LexerError::Compile { kind: rules[i].kind.clone(), error });
+            },
+            Ok(re) => re
+        };
+        re.study();
+
+        let mut anchored = EnumSet::new();
+        anchored.insert(ExecOption::ExecAnchored);
+        anchored.insert(ExecOption::ExecNotEmpty);
+        let mut search = EnumSet::new();
+        search.insert(ExecOption::ExecNotEmpty);
+        Ok(Lexer { re, rules, anchored, search })
+    }
+}
+
+impl Lexer {
+    /// Returns an iterator over the tokens of `subject`.
+    ///
+    /// Input that no rule matches at the current position is returned as a token of kind
+    /// [`ERROR`](constant.ERROR.html), which extends to the next position where a rule
+    /// matches. Rules never match the empty string.
+    pub fn tokens<'a>(&'a self, subject: &'a str) -> Tokens<'a> {
+        Tokens {
+            lexer: self,
+            subject,
+            offset: 0
+        }
+    }
+
+    /// Returns the combined regular expression.
+    pub fn regex(&self) -> &Pcre {
+        &self.re
+    }
+}
+
+impl<'a> Token<'a> {
+    /// Returns `true` if this token covers input that no rule matches.
+    pub fn is_error(&self) -> bool {
+        self.kind == ERROR
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let lexer = self.lexer;
+        let subject = self.subject;
+        while self.offset < subject.len() {
+            let start = self.offset;
+            match lexer.re.exec_from_with_options(subject, start, &lexer.anchored) {
+                Some(m) => {
+                    let end = m.group_end(0);
+                    self.offset = end;
+                    let rule = m.mark().and_then(|mark| lexer.rules.iter().find(|rule| rule.kind == mark)).expect("every branch sets a MARK");
+                    if !rule.skip {
+                        return Some(Token { kind: &rule.kind, span: start..end, text: &subject[start..end] });
+                    }
+                },
+                None => {
+                    // The unmatched input is at least one character long.
+                    let next = start + subject[start..].chars().next().map_or(1, |c| c.len_utf8());
+                    let end = lexer.re.exec_from_with_options(subject, next, &lexer.search).map_or(subject.len(), |m| m.group_start(0));
+                    self.offset = end;
+                    return Some(Token { kind: ERROR, span: start..end, text: &subject[start..end] });
+                }
+            }
+        }
+        None
+    }
+}
@@ -7,23 +7,66 @@
 // except according to those terms.
 
 extern crate enum_set;
+#[cfg(feature = "async")]
+extern crate futures;
 extern crate libc;
 extern crate libpcre_sys;
+#[cfg(feature = "macros")]
+extern crate pcre_macros;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 use enum_set::{CLike, EnumSet};
 use libc::{c_char, c_int, c_uchar, c_void};
 use std::collections::{BTreeMap};
 use std::ffi::{CStr, CString};
-use std::marker::{PhantomData};
+use std::ops::{Range};
 use std::option::{Option};
 use std::ptr;
 use std::result::{Result};
+use std::str;
 use std::string::{String};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::time::{Instant};
 use std::vec::{Vec};
 
+mod builder;
+mod chunked;
+#[cfg(feature = "serde")]
+mod de;
 mod detail;
-
-#[derive(Clone)]
+pub mod flags;
+pub mod inspect;
+mod lazy;
+pub mod lexer;
+mod offsets;
+mod owned;
+#[cfg(feature = "rayon")]
+mod parallel;
+pub mod precompile;
+pub mod regex;
+pub mod replace;
+mod scanner;
+pub mod search;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(feature = "async")]
+pub mod stream;
+
+pub use builder::{Bsr, BuildError, Newline, PcreBuilder, Tables};
+#[cfg(feature = "serde")]
+pub use de::{DeserializeError};
+pub use lazy::{CaptureGroups, LazyPcre};
+pub use offsets::{LineColumn, OffsetMap};
+pub use owned::{OwnedMatch};
+pub use scanner::{Scanner};
+#[cfg(feature = "macros")]
+pub use pcre_macros::pcre;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CompileOption {
     Caseless = 0x00000001,
     Multiline = 0x00000002,
@@ -48,7 +91,7 @@ pub enum CompileOption {
     Ucp = 0x20000000
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum ExecOption {
     ExecAnchored = 0x00000010,
     ExecNotBol = 0x00000080,
@@ -91,6 +134,48 @@ pub struct CompilationError {
 
 }
 
+/// The reason that a match was abandoned before it completed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecError {
+    /// The deadline passed while the match was in progress.
+    TimedOut,
+
+    /// The match was cancelled through a [`CancellationToken`](struct.CancellationToken.html).
+    Cancelled,
+
+    /// The match limit or the recursion depth limit was exceeded. See
+    /// [`PcreBuilder::match_limit()`](struct.PcreBuilder.html#method.match_limit).
+    MatchLimit
+}
+
+/// The result of [`Pcre::exec_partial()`](struct.Pcre.html#method.exec_partial).
+pub enum PartialResult<'a> {
+    /// The pattern matched completely.
+    Match(Match<'a>),
+
+    /// The end of the subject was reached before the pattern could match. The partially
+    /// matched text is from `start` to the end of the subject; it can start before
+    /// `match_start`, where matching started, if a lookbehind assertion or `\b` inspected
+    /// earlier characters.
+    Partial {
+        start: usize,
+        match_start: usize
+    },
+
+    /// The pattern did not match, even partially.
+    NoMatch
+}
+
+/// A handle for cancelling matches that are in progress, possibly on another thread.
+///
+/// Clones of a token share the same cancellation state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+
+    cancelled: Arc<AtomicBool>
+
+}
+
 /// Wrapper for libpcre's `pcre` object (representing a compiled regular expression).
 #[derive(Debug)]
 pub struct Pcre {
@@ -101,8 +186,24 @@ pub struct Pcre {
 
     capture_count_: c_int,
 
-    /// A spot to place a pointer-to-mark name string.
-    mark_: *mut c_uchar
+    pattern: String,
+
+    options: EnumSet<CompileOption>,
+
+    /// The name table, shared with matches.
+    name_table_: Arc<BTreeMap<String, Vec<usize>>>,
+
+    /// The character tables that the pattern was compiled with, if not the default ones.
+    /// libpcre keeps a pointer to them in the compiled pattern.
+    tables: Option<Arc<Tables>>,
+
+    /// The pattern compiled with `AutoCallout`, for matches which check a deadline or a
+    /// cancellation token. It is compiled the first time that it is needed.
+    auto_callout_pcre: Mutex<Option<Arc<Pcre>>>,
+
+    /// The MARK of the last match, for the deprecated `mark()`, once `enable_mark()` has
+    /// been called. It points into the compiled pattern or its `AutoCallout` copy.
+    last_mark: Option<AtomicPtr<c_uchar>>
 
 }
 
@@ -115,7 +216,11 @@ pub struct Match<'a> {
 
     partial_ovector: Vec<c_int>,
 
-    string_count_: c_int
+    string_count_: c_int,
+
+    mark_: Option<Vec<u8>>,
+
+    name_table: Arc<BTreeMap<String, Vec<usize>>>
 
 }
 
@@ -130,13 +235,78 @@ pub struct MatchIterator<'a, 'p> {
 
     subject: &'a str,
 
-    offset: c_int,
+    state: GlobalMatchState,
 
-    options: EnumSet<ExecOption>,
+    options: detail::exec_options,
 
     ovector: Vec<c_int>,
 
-    _marker: PhantomData<&'p mut Pcre>
+    name_table: Arc<BTreeMap<String, Vec<usize>>>,
+
+    re: &'p Pcre
+
+}
+
+/// A reusable buffer that receives the results of matching with [Pcre::exec_into()](struct.Pcre.html#method.exec_into)
+/// or [Pcre::matches_into()](struct.Pcre.html#method.matches_into).
+///
+/// Reusing a `MatchData` for many matches avoids allocating for each match. The matching
+/// options are converted once, when they are set on the `MatchData`.
+#[derive(Clone, Debug, Default)]
+pub struct MatchData {
+
+    ovector: Vec<c_int>,
+
+    string_count_: c_int,
+
+    options: detail::exec_options,
+
+    mark_: Vec<u8>,
+
+    has_mark: bool
+
+}
+
+/// The result of a successful match into a [`MatchData`](struct.MatchData.html) buffer,
+/// which provides access to the captured substrings as slices of the subject string.
+pub struct Captures<'a, 'm> {
+
+    subject: &'a str,
+
+    data: &'m MatchData
+
+}
+
+/// A lending iterator over the matches within a subject string, each of which is stored
+/// into the same [`MatchData`](struct.MatchData.html) buffer.
+///
+/// Call [next_match()](#method.next_match) in a `while let` loop to get the matches.
+pub struct MatchDataIterator<'a, 'p, 'm> {
+
+    re: &'p Pcre,
+
+    subject: &'a str,
+
+    state: GlobalMatchState,
+
+    data: &'m mut MatchData
+
+}
+
+/// The state of a Perl-style global match (as with Perl's `/g` modifier), following the
+/// algorithm of libpcre's `pcredemo` program.
+struct GlobalMatchState {
+
+    /// The offset at which to run the next match.
+    offset: usize,
+
+    /// Whether the previous match was for an empty string.
+    last_match_empty: bool,
+
+    /// Whether CRLF is a valid newline sequence.
+    crlf_is_newline: bool,
+
+    done: bool
 
 }
 
@@ -284,6 +454,42 @@ impl std::fmt::Display for CompilationError {
     }
 }
 
+impl std::error::Error for CompilationError {
+}
+
+impl std::fmt::Display for ExecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ExecError::TimedOut => write!(f, "match timed out"),
+            ExecError::Cancelled => write!(f, "match cancelled"),
+            ExecError::MatchLimit => write!(f, "match limit exceeded")
+        }
+    }
+}
+
+impl std::error::Error for ExecError {
+}
+
+impl CancellationToken {
+    /// Creates a new token which has not been cancelled.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Requests cancellation of every match that is using this token or a clone of it.
+    ///
+    /// Cancellation is permanent; matches started with this token after it has been
+    /// cancelled are abandoned at their first callout.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if [cancel()](#method.cancel) has been called on this token or a clone of it.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
 impl Pcre {
     /// Compiles the given regular expression.
     ///
@@ -301,38 +507,54 @@ impl Pcre {
     /// * `options` - Bitwise-OR'd compilation options. See the libpcre manpages,
     ///   `man 3 pcre_compile`, for more information.
     pub fn compile_with_options(pattern: &str, options: &EnumSet<CompileOption>) -> Result<Pcre, CompilationError> {
+        // Use the default character tables.
+        Pcre::compile_with_tables(pattern, options, None)
+    }
+
+    /// Compiles a regular expression using the given options and character tables.
+    fn compile_with_tables(pattern: &str, options: &EnumSet<CompileOption>, tables: Option<Arc<Tables>>) -> Result<Pcre, CompilationError> {
         let pattern_cstring = CString::new(pattern).unwrap();
         unsafe {
-            // Use the default character tables.
-            let tableptr: *const c_uchar = ptr::null();
+            let tableptr: *const c_uchar = tables.as_ref().map_or(ptr::null(), |tables| tables.as_ptr());
             match detail::pcre_compile(pattern_cstring.as_ptr(), options, tableptr) {
                 Err((opt_err, erroffset)) => Err(CompilationError {
-                    opt_err: opt_err,
-                    erroffset: erroffset
+                    opt_err,
+                    erroffset
                 }),
                 Ok(mut_code) => {
                     let code = mut_code as *const detail::pcre;
                     assert!(!code.is_null());
                     // Take a reference.
                     detail::pcre_refcount(code as *mut detail::pcre, 1);
-
-                    let extra: *mut PcreExtra = ptr::null_mut();
-
-                    let mut capture_count: c_int = 0;
-                    detail::pcre_fullinfo(code, extra as *const PcreExtra, libpcre_sys::PCRE_INFO_CAPTURECOUNT,
-                        &mut capture_count as *mut c_int as *mut c_void);
-
-                    Ok(Pcre {
-                        code: code,
-                        extra: extra,
-                        capture_count_: capture_count,
-                        mark_: ptr::null_mut()
-                    })
+                    Ok(Pcre::from_code(code, pattern, options, tables))
                 }
             }
         }
     }
 
+    /// Wraps the compiled pattern `code`, which must have a reference count of 1.
+    unsafe fn from_code(code: *const detail::pcre, pattern: &str, options: &EnumSet<CompileOption>, tables: Option<Arc<Tables>>) -> Pcre {
+        let extra: *mut PcreExtra = ptr::null_mut();
+
+        let mut capture_count: c_int = 0;
+        detail::pcre_fullinfo(code, extra as *const PcreExtra, libpcre_sys::PCRE_INFO_CAPTURECOUNT,
+            &mut capture_count as *mut c_int as *mut c_void);
+
+        let mut re = Pcre {
+            code,
+            extra,
+            capture_count_: capture_count,
+            pattern: pattern.to_string(),
+            options: *options,
+            name_table_: Arc::default(),
+            tables,
+            auto_callout_pcre: Mutex::new(None),
+            last_mark: None
+        };
+        re.name_table_ = Arc::new(re.name_table());
+        re
+    }
+
     /// Returns the number of capture groups in the regular expression, including one for
     /// each named capture group.
     ///
@@ -345,23 +567,64 @@ impl Pcre {
         self.capture_count_ as usize
     }
 
-    /// Enables the use of the mark field when matching the compiled regular expression. The
-    /// pattern must have been previously studied and an extra block must have been created.
+    /// Returns the pattern that the regular expression was compiled from.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the options that the regular expression was compiled with.
+    pub fn compile_options(&self) -> EnumSet<CompileOption> {
+        self.options
+    }
+
+    /// Returns the character tables that the regular expression was compiled with, or `None`
+    /// if it was compiled with the default tables.
+    pub fn tables(&self) -> Option<&Arc<Tables>> {
+        self.tables.as_ref()
+    }
+
+    /// Makes [mark()](#method.mark) and [mark_bytes()](#method.mark_bytes) return the MARK
+    /// name set by the last match run with this regular expression, on any thread.
     ///
-    /// To ensure that an extra block has been created, call [study_with_options()](#method.study_with_options)
-    /// passing the [`StudyExtraNeeded`](enum.StudyOption.html#variant.StudyExtraNeeded) study option.
+    /// Each match records its own MARK, which [Match::mark()](struct.Match.html#method.mark)
+    /// returns, so this is only needed by code written for the older API.
     ///
     /// # Return value
-    /// `true` if the use of the mark field could be enabled. `false` otherwise, which signifies
-    /// that an extra block needs to be created.
+    /// Always `true`. Studying is no longer needed.
+    #[deprecated(note = "marks are recorded for each match; use `Match::mark()`")]
     pub fn enable_mark(&mut self) -> bool {
-        unsafe {
-            if self.extra.is_null() {
-                false
-            } else {
-                (*self.extra).set_mark(&mut self.mark_);
-                true
-            }
+        if self.last_mark.is_none() {
+            self.last_mark = Some(AtomicPtr::new(ptr::null_mut()));
+        }
+        true
+    }
+
+    /// Returns the MARK name set by the last match run with this regular expression.
+    ///
+    /// # Return value
+    /// `None` if there was no MARK, no match has been run, or
+    /// [enable_mark()](#method.enable_mark) was not called.
+    ///
+    /// # Panics
+    /// If the MARK name is not valid UTF-8.
+    #[deprecated(note = "marks are recorded for each match; use `Match::mark()`")]
+    #[allow(deprecated)]
+    pub fn mark(&self) -> Option<String> {
+        self.mark_bytes().map(|bytes| String::from_utf8(Vec::from(bytes)).unwrap())
+    }
+
+    /// Returns the MARK name set by the last match run with this regular expression, as
+    /// [mark()](#method.mark) does.
+    #[deprecated(note = "marks are recorded for each match; use `Match::mark_bytes()`")]
+    pub fn mark_bytes(&self) -> Option<&[u8]> {
+        // The MARK is a string in the compiled pattern, which lives as long as `self`.
+        self.last_mark.as_ref().and_then(|last_mark| unsafe { mark_bytes(last_mark.load(Ordering::Relaxed)) })
+    }
+
+    /// Records `mark`, the MARK of a match, for [mark()](#method.mark) if it is enabled.
+    fn record_mark(&self, mark: *mut c_uchar) {
+        if let Some(ref last_mark) = self.last_mark {
+            last_mark.store(mark, Ordering::Relaxed);
         }
     }
 
@@ -390,7 +653,7 @@ impl Pcre {
     /// If a regular expression will be used often, it might be worth studying it to possibly
     /// speed up matching. See the [study()](#method.study) method.
     #[inline]
-    pub fn exec<'a, 'p>(&'p mut self, subject: &'a str) -> Option<Match<'a>> {
+    pub fn exec<'a>(&self, subject: &'a str) -> Option<Match<'a>> {
         self.exec_from(subject, 0)
     }
 
@@ -404,6 +667,10 @@ impl Pcre {
     /// * `startoffset` - Starting offset within `subject` at which to begin looking for
     ///   a match.
     ///
+    /// # Panics
+    /// If `startoffset` is greater than the length of `subject` or is not on a UTF-8
+    /// character boundary, or if `subject` is longer than `i32::MAX` bytes.
+    ///
     /// # Performance notes
     /// This method is intended to be used to find individual matches. If multiple matches
     /// are desired, then a `MatchIterator` should be used because it is more efficient.
@@ -411,7 +678,7 @@ impl Pcre {
     /// If a regular expression will be used often, it might be worth studying it to possibly
     /// speed up matching. See the [study()](#method.study) method.
     #[inline]
-    pub fn exec_from<'a, 'p>(&'p mut self, subject: &'a str, startoffset: usize) -> Option<Match<'a>> {
+    pub fn exec_from<'a>(&self, subject: &'a str, startoffset: usize) -> Option<Match<'a>> {
         let no_options: EnumSet<ExecOption> = EnumSet::new();
         self.exec_from_with_options(subject, startoffset, &no_options)
     }
@@ -429,6 +696,14 @@ impl Pcre {
     /// * `options` - Bitwise-OR'd matching options. See the libpcre manpages, `man 3 pcre_exec`,
     ///   for more information.
     ///
+    /// # Panics
+    /// If `startoffset` is greater than the length of `subject` or is not on a UTF-8
+    /// character boundary, or if `subject` is longer than `i32::MAX` bytes. Also if the
+    /// match limit or the recursion depth limit is exceeded, as in all of the matching
+    /// methods which return no error;
+    /// [exec_from_with_interrupt()](#method.exec_from_with_interrupt) returns
+    /// `ExecError::MatchLimit` instead.
+    ///
     /// # Performance notes
     /// This method is intended to be used to find individual matches. If multiple matches
     /// are desired, then a `MatchIterator` should be used because it is more efficient.
@@ -436,67 +711,303 @@ impl Pcre {
     /// If a regular expression will be used often, it might be worth studying it to possibly
     /// speed up matching. See the [study()](#method.study) method.
     #[inline]
-    pub fn exec_from_with_options<'a, 'p>(&'p mut self, subject: &'a str, startoffset: usize, options: &EnumSet<ExecOption>) -> Option<Match<'a>> {
+    pub fn exec_from_with_options<'a>(&self, subject: &'a str, startoffset: usize, options: &EnumSet<ExecOption>) -> Option<Match<'a>> {
+        check_subject(subject, startoffset);
+
+        match self.exec_raw(subject, startoffset, options) {
+            (rc, ovector, mark) if rc >= 0 => Some(self.new_match(subject, rc, ovector, mark)),
+            _ => None
+        }
+    }
+
+    /// Matches the compiled regular expression against the part of `subject` in `window`,
+    /// as [exec_in_with_options()](#method.exec_in_with_options) does with no options.
+    ///
+    /// # Panics
+    /// If the ends of `window` are out of order, greater than the length of `subject` or
+    /// not on UTF-8 character boundaries, or if `subject` is longer than `i32::MAX` bytes.
+    ///
+    /// # Example
+    /// ```
+    /// use pcre::Pcre;
+    ///
+    /// let re = Pcre::compile(r"\b\w+").unwrap();
+    /// let m = re.exec_in("one two three", 5..9).unwrap();
+    /// assert_eq!((m.group_start(0), m.group(0)), (8, "t"));
+    /// ```
+    #[inline]
+    pub fn exec_in<'a>(&self, subject: &'a str, window: Range<usize>) -> Option<Match<'a>> {
+        self.exec_in_with_options(subject, window, &EnumSet::new())
+    }
+
+    /// Matches the compiled regular expression against the part of `subject` in `window`
+    /// with the given options. The match starts and ends within the window, and its offsets
+    /// are offsets in `subject`.
+    ///
+    /// Matching starts at `window.start` in the whole of `subject`, so lookbehind
+    /// assertions, `\b` and `^` see the text before the window. The subject is cut off at
+    /// `window.end`, so nothing matches past it. `$` matches at `window.end` where it would
+    /// in `subject`: at the end of `subject`, before a `\n` with the `Multiline` option, or
+    /// before a `\n` which ends `subject` unless the `DollarEndOnly` option is set. Otherwise
+    /// `ExecNotEol` is added so that it does not match there.
+    ///
+    /// Only the compile options are taken into account, so with `(?m)` in the pattern
+    /// rather than the `Multiline` option, `$` does not match before a `\n` at `window.end`,
+    /// and other newline conventions than `\n` are not recognized. Other assertions at the
+    /// end of the window, such as lookahead assertions, `\b`, `\z` and `\Z`, see it as the
+    /// end of the subject.
+    ///
+    /// # Panics
+    /// If the ends of `window` are out of order, greater than the length of `subject` or
+    /// not on UTF-8 character boundaries, if `subject` is longer than `i32::MAX` bytes, or
+    /// if a match limit is exceeded.
+    pub fn exec_in_with_options<'a>(&self, subject: &'a str, window: Range<usize>, options: &EnumSet<ExecOption>) -> Option<Match<'a>> {
+        assert!(window.start <= window.end && subject.is_char_boundary(window.end),
+                "window {:?} is out of bounds or not on character boundaries", window);
+        let mut options = options.clone();
+        let rest = &subject.as_bytes()[window.end..];
+        let eol = rest.is_empty() || (rest[0] == b'\n' && (self.options.contains(&CompileOption::Multiline)
+                                                          || (rest.len() == 1 && !self.options.contains(&CompileOption::DollarEndOnly))));
+        if !eol {
+            options.insert(ExecOption::ExecNotEol);
+        }
+        self.exec_from_with_options(&subject[..window.end], window.start, &options).map(|m| Match { subject, ..m })
+    }
+
+    /// Matches the compiled regular expression against `subject` starting at offset
+    /// `startoffset`, reporting a partial match if the end of the subject is reached before
+    /// the pattern can match completely.
+    ///
+    /// With `ExecPartialSoft`, a partial match is only reported if there is no complete
+    /// match. With `ExecPartialHard`, a partial match is reported as soon as one is found,
+    /// even if a complete match could be found by trying other alternatives. If `options`
+    /// contains neither, `ExecPartialSoft` is used. See `man 3 pcrepartial`.
+    ///
+    /// # Panics
+    /// If `startoffset` is greater than the length of `subject` or is not on a UTF-8
+    /// character boundary, or if `subject` is longer than `i32::MAX` bytes.
+    ///
+    /// # Example
+    /// ```
+    /// use pcre::{Pcre, PartialResult};
+    /// use enum_set::EnumSet;
+    /// # extern crate enum_set;
+    /// # extern crate pcre;
+    /// # fn main() {
+    ///
+    /// let re = Pcre::compile(r"\d{4}-\d{2}").unwrap();
+    /// match re.exec_partial("due 2015-0", 0, &EnumSet::new()) {
+    ///     PartialResult::Partial { start, .. } => assert_eq!(start, 4),
+    ///     _ => panic!()
+    /// }
+    /// # }
+    /// ```
+    pub fn exec_partial<'a>(&self, subject: &'a str, startoffset: usize, options: &EnumSet<ExecOption>) -> PartialResult<'a> {
+        check_subject(subject, startoffset);
+        let mut options = options.clone();
+        if !options.contains(&ExecOption::ExecPartialSoft) && !options.contains(&ExecOption::ExecPartialHard) {
+            options.insert(ExecOption::ExecPartialSoft);
+        }
+        match self.exec_raw(subject, startoffset, &options) {
+            (rc, ovector, mark) if rc >= 0 => PartialResult::Match(self.new_match(subject, rc, ovector, mark)),
+            (detail::PCRE_ERROR_PARTIAL, ovector, _) => PartialResult::Partial {
+                start: ovector[0] as usize,
+                match_start: ovector[2] as usize
+            },
+            _ => PartialResult::NoMatch
+        }
+    }
+
+    /// Calls `pcre_exec()` with a new ovector, returning the return code, the ovector and
+    /// the MARK.
+    fn exec_raw(&self, subject: &str, startoffset: usize, options: &EnumSet<ExecOption>) -> (c_int, Vec<c_int>, Option<Vec<u8>>) {
         let ovecsize = (self.capture_count_ + 1) * 3;
         let mut ovector = vec![0 as c_int; ovecsize as usize];
+        let mut mark: *mut c_uchar = ptr::null_mut();
 
         unsafe {
+            let extra = exec_extra(self.extra, &mut mark);
             let rc = detail::pcre_exec(self.code,
-                                       self.extra as *const PcreExtra,
+                                       &extra,
                                        subject.as_ptr() as *const c_char,
                                        subject.len() as c_int,
                                        startoffset as c_int,
-                                       options,
+                                       detail::exec_options(options),
                                        ovector.as_mut_ptr(),
                                        ovecsize as c_int);
-            if rc >= 0 {
-                Some(Match {
-                    subject: subject,
-                    partial_ovector: ovector[..(((self.capture_count_ + 1) * 2) as usize)].to_vec(),
-                    string_count_: rc
-                })
-            } else {
-                None
-            }
+            self.record_mark(mark);
+            (rc, ovector, mark_bytes(mark).map(Vec::from))
+        }
+    }
+
+    /// Wraps the result of a successful `exec_raw()`.
+    fn new_match<'a>(&self, subject: &'a str, rc: c_int, mut ovector: Vec<c_int>, mark: Option<Vec<u8>>) -> Match<'a> {
+        // The last third of the ovector is only used by libpcre as workspace.
+        ovector.truncate(((self.capture_count_ + 1) * 2) as usize);
+        Match {
+            subject,
+            partial_ovector: ovector,
+            string_count_: rc,
+            mark_: mark,
+            name_table: self.name_table_.clone()
         }
     }
 
-    /// Returns the mark name from PCRE if set.
+    /// Matches the compiled regular expression against a given subject string `subject`,
+    /// giving up if the match is still in progress at `deadline`.
     ///
-    /// # Return value
-    /// `Some(str)` if PCRE returned a value for the mark.
-    /// `None` if either there was no mark set or [enable_mark()](#method.enable_mark) was not called,
-    /// or was unsuccessful.
+    /// See [exec_from_with_interrupt()](#method.exec_from_with_interrupt) for how the
+    /// deadline is checked.
+    ///
+    /// # Arguments
+    /// * `subject` - The subject string.
+    /// * `deadline` - The point in time after which the match is abandoned.
     #[inline]
-    pub fn mark(&self) -> Option<String> {
-        self.mark_bytes().map (|bytes| String::from_utf8(Vec::from(bytes)).unwrap())
+    pub fn exec_with_deadline<'a>(&self, subject: &'a str, deadline: Instant) -> Result<Option<Match<'a>>, ExecError> {
+        let no_options: EnumSet<ExecOption> = EnumSet::new();
+        self.exec_from_with_interrupt(subject, 0, &no_options, Some(deadline), None)
     }
 
-    /// Returns the mark name from PCRE if set.
+    /// Matches the compiled regular expression against a given subject string `subject`,
+    /// giving up if `token` is cancelled while the match is in progress.
     ///
-    /// # Return value
-    /// `Some(&[u8])` if PCRE returned a value for the mark.
-    /// `None` if either there was no mark set or [enable_mark()](#method.enable_mark) was not called,
-    /// or was unsuccessful.
+    /// See [exec_from_with_interrupt()](#method.exec_from_with_interrupt) for how the
+    /// token is checked.
+    ///
+    /// # Arguments
+    /// * `subject` - The subject string.
+    /// * `token` - The cancellation token.
     #[inline]
-    pub fn mark_bytes(&self) -> Option<&[u8]> {
+    pub fn exec_with_cancellation<'a>(&self, subject: &'a str, token: &CancellationToken) -> Result<Option<Match<'a>>, ExecError> {
+        let no_options: EnumSet<ExecOption> = EnumSet::new();
+        self.exec_from_with_interrupt(subject, 0, &no_options, None, Some(token))
+    }
+
+    /// Matches the compiled regular expression against a given subject string `subject`
+    /// starting at offset `startoffset` and using the given matching options `options`,
+    /// giving up if `deadline` passes or `token` is cancelled while the match is in progress.
+    ///
+    /// The deadline and the token are checked from a libpcre callout function before every
+    /// item of the pattern. If the pattern was not compiled with the
+    /// [`AutoCallout`](enum.CompileOption.html#variant.AutoCallout) option, a copy of it is
+    /// compiled with the option the first time that this method is called with a deadline
+    /// or a token, and that copy is used to match, with the match limits of this pattern. The copy is not JIT-compiled,
+    /// so to avoid compiling twice, compile patterns which are always matched with a
+    /// deadline with `AutoCallout`.
+    ///
+    /// The callout function is installed as libpcre's process-wide `pcre_callout` the first
+    /// time that this method is called. The callouts of other matches are passed on to the
+    /// function that was installed before it, if any. If another function is installed
+    /// afterwards, deadlines and tokens are no longer checked.
+    ///
+    /// An abandoned match does not affect the compiled regular expression, which can be
+    /// used again afterwards.
+    ///
+    /// # Arguments
+    /// * `subject` - The subject string.
+    /// * `startoffset` - Starting offset within `subject` at which to begin looking for
+    ///   a match.
+    /// * `options` - Bitwise-OR'd matching options. See the libpcre manpages, `man 3 pcre_exec`,
+    ///   for more information.
+    /// * `deadline` - The point in time after which the match is abandoned, if any.
+    /// * `token` - A cancellation token, if any.
+    ///
+    /// # Panics
+    /// If `startoffset` is greater than the length of `subject` or is not on a UTF-8
+    /// character boundary, or if `subject` is longer than `i32::MAX` bytes.
+    ///
+    /// # Return value
+    /// `Ok(None)` if there is no match, `Ok(Some(m))` if there is, and `Err` if the match
+    /// was abandoned or a match limit was exceeded.
+    pub fn exec_from_with_interrupt<'a>(&self, subject: &'a str, startoffset: usize, options: &EnumSet<ExecOption>, deadline: Option<Instant>, token: Option<&CancellationToken>) -> Result<Option<Match<'a>>, ExecError> {
+        check_subject(subject, startoffset);
+        detail::install_interrupt_callout();
+        let auto_callout_pcre;
+        let re = if self.options.contains(&CompileOption::AutoCallout) || (deadline.is_none() && token.is_none()) {
+            self
+        } else {
+            auto_callout_pcre = self.auto_callout_pcre();
+            &*auto_callout_pcre
+        };
+
+        let mut state = detail::InterruptState {
+            deadline,
+            cancelled: token.map(|token| &*token.cancelled),
+            callout_count: 0,
+            error: None
+        };
+
+        let ovecsize = (self.capture_count_ + 1) * 3;
+        let mut ovector = vec![0 as c_int; ovecsize as usize];
+        let mut mark: *mut c_uchar = ptr::null_mut();
+
         unsafe {
-            if self.mark_.is_null() {
-                None
+            let mut extra = exec_extra(re.extra, &mut mark);
+            if let Some(limits) = self.extra.as_ref() {
+                if let Some(limit) = limits.match_limit() {
+                    extra.set_match_limit(limit as u32);
+                }
+                if let Some(limit) = limits.match_limit_recursion() {
+                    extra.set_match_limit_recursion(limit as u32);
+                }
+            }
+            let rc = detail::with_interrupt_state(&mut state, |data| {
+                extra.set_callout_data(data);
+                detail::try_pcre_exec(re.code,
+                                      &extra,
+                                      subject.as_ptr() as *const c_char,
+                                      subject.len() as c_int,
+                                      startoffset as c_int,
+                                      detail::exec_options(options),
+                                      ovector.as_mut_ptr(),
+                                      ovecsize as c_int)
+            });
+            self.record_mark(mark);
+            if let Some(err) = state.error {
+                Err(err)
+            } else if rc == detail::PCRE_ERROR_MATCHLIMIT {
+                Err(ExecError::MatchLimit)
+            } else if rc >= 0 {
+                ovector.truncate(((self.capture_count_ + 1) * 2) as usize);
+                Ok(Some(Match {
+                    subject,
+                    partial_ovector: ovector,
+                    string_count_: rc,
+                    mark_: mark_bytes(mark).map(Vec::from),
+                    name_table: self.name_table_.clone()
+                }))
             } else {
-                let mark_cstr = CStr::from_ptr(self.mark_ as *const c_char);
-                Some(mark_cstr.to_bytes())
+                Ok(None)
             }
         }
     }
 
+    /// Returns this pattern compiled with `AutoCallout`, compiling it if it has not been
+    /// compiled yet.
+    fn auto_callout_pcre(&self) -> Arc<Pcre> {
+        let mut auto_callout_pcre = self.auto_callout_pcre.lock().unwrap_or_else(|err| err.into_inner());
+        auto_callout_pcre.get_or_insert_with(|| {
+            let mut options = self.options;
+            options.insert(CompileOption::AutoCallout);
+            let mut re = Pcre::compile_with_tables(&self.pattern, &options, self.tables.clone())
+                .expect("the pattern compiles with AutoCallout");
+            re.study();
+            Arc::new(re)
+        }).clone()
+    }
+
     /// Creates a `MatchIterator` for iterating through matches within the given subject
     /// string `subject`.
     ///
+    /// The iterator finds successive matches the way Perl's `/g` modifier does. After an
+    /// empty match, a non-empty match is looked for at the same offset before moving on by
+    /// one character (or by both characters of a CRLF newline sequence), so patterns which
+    /// can match the empty string do not match at the same offset forever.
+    ///
     /// # Argument
     /// * `subject` - The subject string.
     #[inline]
-    pub fn matches<'a, 'p>(&'p mut self, subject: &'a str) -> MatchIterator<'a, 'p> {
+    pub fn matches<'a, 'p>(&'p self, subject: &'a str) -> MatchIterator<'a, 'p> {
         let no_options: EnumSet<ExecOption> = EnumSet::new();
         self.matches_with_options(subject, &no_options)
     }
@@ -508,23 +1019,138 @@ impl Pcre {
     /// * `subject` - The subject string.
     /// * `options` - Bitwise-OR'd matching options. See the libpcre manpages, `man 3 pcre_exec`,
     ///   for more information.
+    ///
+    /// # Panics
+    /// If `subject` is longer than `i32::MAX` bytes.
     #[inline]
-    pub fn matches_with_options<'a, 'p>(&'p mut self, subject: &'a str, options: &EnumSet<ExecOption>) -> MatchIterator<'a, 'p> {
+    pub fn matches_with_options<'a, 'p>(&'p self, subject: &'a str, options: &EnumSet<ExecOption>) -> MatchIterator<'a, 'p> {
+        check_subject(subject, 0);
         unsafe {
             let ovecsize = (self.capture_count_ + 1) * 3;
+            let options = detail::exec_options(options);
             MatchIterator {
-                code: { detail::pcre_refcount(self.code as *mut detail::pcre, 1); self.code },
+                code: self.code,
                 extra: self.extra as *const PcreExtra,
                 capture_count: self.capture_count_,
-                subject: subject,
-                offset: 0,
-                options: options.clone(),
+                subject,
+                state: GlobalMatchState::new(detail::crlf_is_newline(self.code, options)),
+                options,
                 ovector: vec![0 as c_int; ovecsize as usize],
-                _marker: PhantomData
+                name_table: self.name_table_.clone(),
+                re: self
             }
         }
     }
 
+    /// Creates a [`MatchData`](struct.MatchData.html) buffer which is large enough to
+    /// receive the results of matching this regular expression.
+    pub fn match_data(&self) -> MatchData {
+        MatchData {
+            ovector: vec![0 as c_int; ((self.capture_count_ + 1) * 3) as usize],
+            ..MatchData::default()
+        }
+    }
+
+    /// Matches the compiled regular expression against a given subject string `subject`
+    /// starting at offset `startoffset`, storing the result in `data`. The matching options
+    /// set on `data` are used.
+    ///
+    /// The MARK is stored in `data` even if there is no match. When a match fails, it is the
+    /// last MARK encountered in the last match attempt.
+    ///
+    /// Unlike [exec_from_with_options()](#method.exec_from_with_options), this method does
+    /// not allocate unless `data` is too small for this regular expression.
+    ///
+    /// # Arguments
+    /// * `subject` - The subject string.
+    /// * `startoffset` - Starting offset within `subject` at which to begin looking for
+    ///   a match.
+    /// * `data` - The buffer which receives the result.
+    ///
+    /// # Panics
+    /// If `startoffset` is greater than the length of `subject` or is not on a UTF-8
+    /// character boundary, or if `subject` is longer than `i32::MAX` bytes.
+    pub fn exec_into<'a, 'm>(&self, subject: &'a str, startoffset: usize, data: &'m mut MatchData) -> Option<Captures<'a, 'm>> {
+        check_subject(subject, startoffset);
+        data.reserve(self.capture_count_);
+        let mut mark: *mut c_uchar = ptr::null_mut();
+
+        unsafe {
+            let extra = exec_extra(self.extra, &mut mark);
+            let rc = detail::pcre_exec(self.code,
+                                       &extra,
+                                       subject.as_ptr() as *const c_char,
+                                       subject.len() as c_int,
+                                       startoffset as c_int,
+                                       data.options,
+                                       data.ovector.as_mut_ptr(),
+                                       data.ovector.len() as c_int);
+            self.record_mark(mark);
+            data.set_mark(mark);
+            if rc >= 0 {
+                data.string_count_ = rc;
+                Some(Captures {
+                    subject,
+                    data
+                })
+            } else {
+                data.string_count_ = 0;
+                None
+            }
+        }
+    }
+
+    /// Creates a `MatchDataIterator` for iterating through matches within the given subject
+    /// string `subject`, storing each match in `data`. The matching options set on `data`
+    /// are used.
+    ///
+    /// Matches are found in the same way as by [matches()](#method.matches), but without
+    /// allocating for each match.
+    ///
+    /// # Arguments
+    /// * `subject` - The subject string.
+    /// * `data` - The buffer which receives each match.
+    ///
+    /// # Panics
+    /// If `subject` is longer than `i32::MAX` bytes.
+    pub fn matches_into<'a, 'p, 'm>(&'p self, subject: &'a str, data: &'m mut MatchData) -> MatchDataIterator<'a, 'p, 'm> {
+        check_subject(subject, 0);
+        data.reserve(self.capture_count_);
+        let crlf_is_newline = unsafe { detail::crlf_is_newline(self.code, data.options) };
+        MatchDataIterator {
+            re: self,
+            subject,
+            state: GlobalMatchState::new(crlf_is_newline),
+            data
+        }
+    }
+
+    /// Returns `true` if the compiled regular expression matches somewhere in the subject
+    /// string `subject`.
+    ///
+    /// This is faster than checking the result of [exec()](#method.exec) because the
+    /// offsets of the match and of the capture groups are not computed.
+    ///
+    /// # Panics
+    /// If `subject` is longer than `i32::MAX` bytes.
+    pub fn is_match(&self, subject: &str) -> bool {
+        check_subject(subject, 0);
+        let mut mark: *mut c_uchar = ptr::null_mut();
+        unsafe {
+            let extra = exec_extra(self.extra, &mut mark);
+            let rc = detail::pcre_exec(self.code,
+                                       &extra,
+                                       subject.as_ptr() as *const c_char,
+                                       subject.len() as c_int,
+                                       0,
+                                       0,
+                                       ptr::null_mut(),
+                                       0);
+            self.record_mark(mark);
+            rc >= 0
+        }
+    }
+
     /// Returns the number of named capture groups in the regular expression.
     pub fn name_count(&self) -> usize {
         unsafe {
@@ -534,6 +1160,20 @@ impl Pcre {
         }
     }
 
+    /// Returns the largest number of characters that a lookbehind assertion in the regular
+    /// expression looks back, or 1 if the pattern uses `\b` or `\B` and has no longer
+    /// lookbehind. When matching a subject in segments, as many characters before the start
+    /// of a segment must be kept for the results to be the same.
+    ///
+    /// Requires libpcre 8.34 or later.
+    pub fn max_lookbehind(&self) -> usize {
+        unsafe {
+            let mut max_lookbehind: c_int = 0;
+            detail::pcre_fullinfo(self.code, self.extra as *const PcreExtra, libpcre_sys::PCRE_INFO_MAXLOOKBEHIND, &mut max_lookbehind as *mut c_int as *mut c_void);
+            max_lookbehind as usize
+        }
+    }
+
     /// Creates a name-to-number translation table that maps the name of each named capture
     /// group to the assigned group numbers.
     ///
@@ -556,13 +1196,7 @@ impl Pcre {
                 let name_cstr = CStr::from_ptr(tabptr.offset(2) as *const c_char);
                 // TODO Check memory allocations
                 let name: String = String::from_utf8(Vec::from(name_cstr.to_bytes())).unwrap();
-                // TODO Avoid the double lookup.
-                // https://github.com/mozilla/rust/issues/9068
-                if !name_table.contains_key(&name) {
-                    name_table.insert(name, vec![n]);
-                } else {
-                    name_table.get_mut(&name).unwrap().push(n);
-                }
+                name_table.entry(name).or_default().push(n);
                 tabptr = tabptr.offset(name_entry_size as isize);
                 i += 1;
             }
@@ -603,7 +1237,10 @@ impl Pcre {
                 detail::pcre_free_study(self.extra as *mut PcreExtra);
                 self.extra = ptr::null_mut();
 
-                let extra = detail::pcre_study(self.code, options);
+                let extra = match detail::pcre_study(self.code, options) {
+                    Err(err) => panic!("pcre_study() failed: {}", err),
+                    Ok(extra) => extra
+                };
                 self.extra = extra;
                 !extra.is_null()
             }
@@ -611,6 +1248,21 @@ impl Pcre {
     }
 }
 
+// libpcre only reads the compiled pattern and the extra block while matching, and the
+// per-match state (the MARK and callout data) lives in a copy of the extra block. Studying,
+// which replaces the extra block, requires `&mut self`. An iterator borrows the `Pcre` for
+// as long as it uses the pattern, so it cannot outlive it.
+unsafe impl Send for Pcre {}
+unsafe impl Sync for Pcre {}
+
+impl std::fmt::Display for Pcre {
+    /// Formats the regular expression as a pattern literal such as `/^foo.*bar$/imx`. See
+    /// [flags::format_literal()](flags/fn.format_literal.html).
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&flags::format_literal(&self.pattern, &self.options))
+    }
+}
+
 impl Drop for Pcre {
     fn drop(&mut self) {
         unsafe {
@@ -627,52 +1279,282 @@ impl Drop for Pcre {
 impl<'a> Match<'a> {
     /// Returns the start index within the subject string of capture group `n`.
     ///
-    /// If the capture group is present in the pattern but wasn't captured then the start of it will be `usize::max_value()`.
+    /// If the capture group is present in the pattern but wasn't captured then the start of it will be `usize::MAX`.
     /// Happens with the optional groups, `/(optional)?/`.
     pub fn group_start(&self, n: usize) -> usize {
-        self.partial_ovector[(n * 2) as usize] as usize
+        self.partial_ovector[n * 2] as usize
     }
 
     /// Returns the end index within the subject string of capture group `n`.
     ///
-    /// If the capture group is present in the pattern but wasn't captured then the end of it will be `usize::max_value()`.
+    /// If the capture group is present in the pattern but wasn't captured then the end of it will be `usize::MAX`.
     /// Happens with the optional groups, `/(optional)?/`.
     pub fn group_end(&self, n: usize) -> usize {
-        self.partial_ovector[(n * 2 + 1) as usize] as usize
+        self.partial_ovector[n * 2 + 1] as usize
     }
 
     /// Returns the length of the substring for capture group `n`.
     pub fn group_len(&self, n: usize) -> usize {
-        let group_offsets = &self.partial_ovector[((n * 2) as usize)..];
+        let group_offsets = &self.partial_ovector[(n * 2)..];
         (group_offsets[1] - group_offsets[0]) as usize
     }
 
     /// Returns the substring for capture group `n` as a slice.
+    ///
+    /// # Panics
+    /// If the capture group did not participate in the match, or if the substring does not
+    /// start and end on UTF-8 character boundaries. The latter can happen when the pattern
+    /// uses `\C` to match a single byte; use [group_bytes()](#method.group_bytes) instead.
     #[inline]
     pub fn group(&self, n: usize) -> &'a str {
-        let group_offsets = &self.partial_ovector[((n * 2) as usize)..];
+        let group_offsets = &self.partial_ovector[(n * 2)..];
         let start = group_offsets[0];
         let end = group_offsets[1];
         &self.subject[(start as usize)..(end as usize)]
     }
 
+    /// Returns the substring for capture group `n` as a byte slice.
+    ///
+    /// Unlike [group()](#method.group), this works for substrings which do not start and
+    /// end on UTF-8 character boundaries.
+    ///
+    /// # Panics
+    /// If the capture group did not participate in the match.
+    #[inline]
+    pub fn group_bytes(&self, n: usize) -> &'a [u8] {
+        let group_offsets = &self.partial_ovector[(n * 2)..];
+        let start = group_offsets[0];
+        let end = group_offsets[1];
+        &self.subject.as_bytes()[(start as usize)..(end as usize)]
+    }
+
+    /// Returns the substring for the capture group named `name`.
+    ///
+    /// If several groups have the name (see [`DupNames`](enum.CompileOption.html#variant.DupNames)),
+    /// the first one which participated in the match is used.
+    ///
+    /// # Return value
+    /// `None` if there is no group named `name` or it did not participate in the match.
+    pub fn name(&self, name: &str) -> Option<&'a str> {
+        self.name_table.get(name)
+            .and_then(|ns| ns.iter().cloned().find(|&n| self.is_set(n)))
+            .map(|n| self.group(n))
+    }
+
+    /// Returns `true` if capture group `n` participated in the match.
+    fn is_set(&self, n: usize) -> bool {
+        n < self.string_count() && self.partial_ovector[n * 2] >= 0
+    }
+
     /// Returns the number of substrings captured.
     pub fn string_count(&self) -> usize {
         self.string_count_ as usize
     }
+
+    /// Returns the MARK name that was set by this match, if any.
+    ///
+    /// # Return value
+    /// `Some(str)` if the match passed a `(*MARK:NAME)`, `(*PRUNE:NAME)` or `(*THEN:NAME)`
+    /// item. `None` otherwise.
+    pub fn mark(&self) -> Option<&str> {
+        self.mark_bytes().map(|bytes| str::from_utf8(bytes).unwrap())
+    }
+
+    /// Returns the MARK name that was set by this match, if any.
+    ///
+    /// # Return value
+    /// `Some(&[u8])` if the match passed a `(*MARK:NAME)`, `(*PRUNE:NAME)` or `(*THEN:NAME)`
+    /// item. `None` otherwise.
+    pub fn mark_bytes(&self) -> Option<&[u8]> {
+        self.mark_.as_ref().map(|mark| &mark[..])
+    }
 }
 
-impl<'a, 'p> Drop for MatchIterator<'a, 'p> {
-    fn drop(&mut self) {
-        unsafe {
-            if detail::pcre_refcount(self.code as *mut detail::pcre, -1) == 0 {
-                detail::pcre_free_study(self.extra as *mut PcreExtra);
-                detail::pcre_free(self.code as *mut detail::pcre as *mut c_void);
+impl MatchData {
+    /// Creates an empty buffer. It grows to the size needed by the first regular expression
+    /// that it is used with.
+    pub fn new() -> MatchData {
+        MatchData::default()
+    }
+
+    /// Creates an empty buffer with the given bitwise-OR'd matching options `options`.
+    ///
+    /// # Argument
+    /// * `options` - Bitwise-OR'd matching options. See the libpcre manpages, `man 3 pcre_exec`,
+    ///   for more information.
+    pub fn with_options(options: &EnumSet<ExecOption>) -> MatchData {
+        let mut data = MatchData::new();
+        data.set_options(options);
+        data
+    }
+
+    /// Sets the bitwise-OR'd matching options `options` to be used for matches into this buffer.
+    pub fn set_options(&mut self, options: &EnumSet<ExecOption>) {
+        self.options = detail::exec_options(options);
+    }
+
+    /// Returns the number of substrings captured by the last match, or 0 if the last match
+    /// failed.
+    pub fn string_count(&self) -> usize {
+        self.string_count_ as usize
+    }
+
+    /// Returns the start index within the subject string of capture group `n`.
+    ///
+    /// If the capture group is present in the pattern but wasn't captured then the start of it will be `usize::MAX`.
+    pub fn group_start(&self, n: usize) -> usize {
+        self.ovector[n * 2] as usize
+    }
+
+    /// Returns the end index within the subject string of capture group `n`.
+    ///
+    /// If the capture group is present in the pattern but wasn't captured then the end of it will be `usize::MAX`.
+    pub fn group_end(&self, n: usize) -> usize {
+        self.ovector[n * 2 + 1] as usize
+    }
+
+    /// Returns the length of the substring for capture group `n`.
+    pub fn group_len(&self, n: usize) -> usize {
+        (self.ovector[n * 2 + 1] - self.ovector[n * 2]) as usize
+    }
+
+    /// Returns the MARK name that was set by the last match, even if it failed.
+    ///
+    /// # Return value
+    /// `Some(str)` if a MARK was set. `None` otherwise.
+    pub fn mark(&self) -> Option<&str> {
+        self.mark_bytes().map(|bytes| str::from_utf8(bytes).unwrap())
+    }
+
+    /// Returns the MARK name that was set by the last match, even if it failed.
+    ///
+    /// # Return value
+    /// `Some(&[u8])` if a MARK was set. `None` otherwise.
+    pub fn mark_bytes(&self) -> Option<&[u8]> {
+        if self.has_mark {
+            Some(&self.mark_)
+        } else {
+            None
+        }
+    }
+
+    /// Copies the MARK name pointed to by `mark` (which can be null).
+    unsafe fn set_mark(&mut self, mark: *const c_uchar) {
+        self.mark_.clear();
+        match mark_bytes(mark) {
+            None => self.has_mark = false,
+            Some(bytes) => {
+                self.mark_.extend_from_slice(bytes);
+                self.has_mark = true;
             }
-            self.extra = ptr::null();
-            self.code = ptr::null();
         }
     }
+
+    /// Grows the ovector if needed to hold the results for a pattern with `capture_count`
+    /// capture groups.
+    fn reserve(&mut self, capture_count: c_int) {
+        let ovecsize = ((capture_count + 1) * 3) as usize;
+        if self.ovector.len() < ovecsize {
+            self.ovector.resize(ovecsize, 0);
+        }
+    }
+}
+
+impl<'a, 'm> Captures<'a, 'm> {
+    /// Returns the start index within the subject string of capture group `n`.
+    ///
+    /// If the capture group is present in the pattern but wasn't captured then the start of it will be `usize::MAX`.
+    pub fn group_start(&self, n: usize) -> usize {
+        self.data.group_start(n)
+    }
+
+    /// Returns the end index within the subject string of capture group `n`.
+    ///
+    /// If the capture group is present in the pattern but wasn't captured then the end of it will be `usize::MAX`.
+    pub fn group_end(&self, n: usize) -> usize {
+        self.data.group_end(n)
+    }
+
+    /// Returns the length of the substring for capture group `n`.
+    pub fn group_len(&self, n: usize) -> usize {
+        self.data.group_len(n)
+    }
+
+    /// Returns the substring for capture group `n` as a slice.
+    ///
+    /// # Panics
+    /// Under the same conditions as [Match::group()](struct.Match.html#method.group).
+    #[inline]
+    pub fn group(&self, n: usize) -> &'a str {
+        &self.subject[self.data.group_start(n)..self.data.group_end(n)]
+    }
+
+    /// Returns the substring for capture group `n` as a byte slice.
+    ///
+    /// # Panics
+    /// If the capture group did not participate in the match.
+    #[inline]
+    pub fn group_bytes(&self, n: usize) -> &'a [u8] {
+        &self.subject.as_bytes()[self.data.group_start(n)..self.data.group_end(n)]
+    }
+
+    /// Returns the number of substrings captured.
+    pub fn string_count(&self) -> usize {
+        self.data.string_count()
+    }
+
+    /// Returns the MARK name that was set by this match, if any.
+    pub fn mark(&self) -> Option<&str> {
+        self.data.mark()
+    }
+
+    /// Returns the MARK name that was set by this match, if any.
+    pub fn mark_bytes(&self) -> Option<&[u8]> {
+        self.data.mark_bytes()
+    }
+}
+
+impl<'a, 'p, 'm> MatchDataIterator<'a, 'p, 'm> {
+    /// Gets the next match.
+    ///
+    /// The returned `Captures` borrows the iterator, so it must be dropped before the
+    /// following match can be found.
+    ///
+    /// # Panics
+    /// If the match limit or the recursion depth limit is exceeded. Use
+    /// [try_next_match()](#method.try_next_match) to handle that.
+    pub fn next_match(&mut self) -> Option<Captures<'a, '_>> {
+        match self.try_next_match() {
+            Ok(captures) => captures,
+            Err(_) => detail::match_limit_exceeded()
+        }
+    }
+
+    /// Gets the next match, as [next_match()](#method.next_match) does, but returns
+    /// `ExecError::MatchLimit` if the match limit or the recursion depth limit is exceeded.
+    /// There are no more matches after an error.
+    pub fn try_next_match(&mut self) -> Result<Option<Captures<'a, '_>>, ExecError> {
+        let re = self.re;
+        let subject = self.subject;
+        let options = self.data.options;
+        let ovector = &mut self.data.ovector;
+        let mut mark: *mut c_uchar = ptr::null_mut();
+        let rc = self.state.next(subject, |offset, not_empty_at_start| unsafe {
+            global_exec(re.code, re.extra, subject, offset, options, not_empty_at_start, ovector, &mut mark)
+        });
+        re.record_mark(mark);
+        let rc = rc?;
+        let rc = match rc {
+            None => return Ok(None),
+            Some(rc) => rc
+        };
+        self.data.string_count_ = rc;
+        unsafe { self.data.set_mark(mark) };
+        Ok(Some(Captures {
+            subject,
+            data: self.data
+        }))
+    }
 }
 
 impl<'a, 'p> Iterator for MatchIterator<'a, 'p> {
@@ -681,29 +1563,168 @@ impl<'a, 'p> Iterator for MatchIterator<'a, 'p> {
     /// Gets the next match.
     #[inline]
     fn next(&mut self) -> Option<Match<'a>> {
-        unsafe {
-            let rc = detail::pcre_exec(self.code,
-                                       self.extra,
-                                       self.subject.as_ptr() as *const c_char,
-                                       self.subject.len() as c_int,
-                                       self.offset,
-                                       &self.options,
-                                       self.ovector.as_mut_ptr(),
-                                       self.ovector.len() as c_int);
-            if rc >= 0 {
-                // Update the iterator state.
-                self.offset = self.ovector[1];
+        let code = self.code;
+        let extra = self.extra;
+        let subject = self.subject;
+        let options = self.options;
+        let ovector = &mut self.ovector;
+        let mut mark: *mut c_uchar = ptr::null_mut();
+        let rc = self.state.next(subject, |offset, not_empty_at_start| unsafe {
+            global_exec(code, extra, subject, offset, options, not_empty_at_start, ovector, &mut mark)
+        });
+        self.re.record_mark(mark);
+        let rc = rc.unwrap_or_else(|_| detail::match_limit_exceeded());
+        rc.map(|rc| Match {
+            subject: self.subject,
+            partial_ovector: self.ovector[..(((self.capture_count + 1) * 2) as usize)].to_vec(),
+            string_count_: rc,
+            mark_: unsafe { mark_bytes(mark).map(Vec::from) },
+            name_table: self.name_table.clone()
+        })
+    }
+}
 
-                Some(Match {
-                    subject: self.subject,
-                    partial_ovector: self.ovector[..(((self.capture_count + 1) * 2) as usize)].to_vec(),
-                    string_count_: rc
-                })
-            } else {
-                None
+impl GlobalMatchState {
+    fn new(crlf_is_newline: bool) -> GlobalMatchState {
+        GlobalMatchState {
+            offset: 0,
+            last_match_empty: false,
+            crlf_is_newline,
+            done: false
+        }
+    }
+
+    /// Finds the next match of a global match in `subject`.
+    ///
+    /// `exec` is called to run a match at a given offset. Its second argument is `true` if
+    /// the match must be run with the `ExecNotEmptyAtStart` and `ExecAnchored` options.
+    /// It returns the return code of `pcre_exec()` and the start and end offsets of the
+    /// match, or `None` if there is no match.
+    ///
+    /// # Return value
+    /// The return code of the call to `exec` that found the next match, or `None` if all
+    /// matches have been found. An error from `exec` ends the global match.
+    fn next<F>(&mut self, subject: &str, mut exec: F) -> Result<Option<c_int>, ExecError>
+        where F: FnMut(usize, bool) -> Result<Option<(c_int, usize, usize)>, ExecError> {
+        let bytes = subject.as_bytes();
+        while !self.done {
+            // After an empty match, first see if a non-empty match can be found at the
+            // same point.
+            let not_empty_at_start = self.last_match_empty;
+            let result = exec(self.offset, not_empty_at_start);
+            if result.is_err() {
+                self.done = true;
+            }
+            match result? {
+                Some((rc, start, end)) => {
+                    self.last_match_empty = start == end;
+                    if self.last_match_empty && end == subject.len() {
+                        self.done = true;
+                    } else if !self.last_match_empty && end <= self.offset {
+                        // With `\K` in a lookbehind assertion, a match can end at or before
+                        // the offset it was run at, so running the next match there would
+                        // find the same match again. Do what pcretest does and move on by
+                        // one character, which may find the same match a few more times.
+                        if self.offset == subject.len() {
+                            self.done = true;
+                        } else {
+                            self.offset = ceil_char_boundary(subject, self.offset + 1);
+                        }
+                    } else {
+                        // A match that ends with `\C` can end in the middle of a character,
+                        // but the next match must not start there.
+                        self.offset = ceil_char_boundary(subject, end);
+                    }
+                    return Ok(Some(rc));
+                },
+                None if not_empty_at_start => {
+                    // Do what Perl does: advance by one character and continue. When CRLF
+                    // is a valid newline sequence and we are just before one, advance by
+                    // both bytes.
+                    self.last_match_empty = false;
+                    if self.crlf_is_newline && bytes[self.offset..].starts_with(b"\r\n") {
+                        self.offset += 2;
+                    } else {
+                        self.offset = ceil_char_boundary(subject, self.offset + 1);
+                    }
+                },
+                None => {
+                    self.done = true;
+                }
             }
         }
+        Ok(None)
+    }
+}
+
+/// Runs one match of a global match, storing the MARK in `mark`. See `GlobalMatchState::next()`.
+#[allow(clippy::too_many_arguments)]
+unsafe fn global_exec(code: *const detail::pcre, extra: *const PcreExtra, subject: &str, offset: usize, options: detail::exec_options, not_empty_at_start: bool, ovector: &mut [c_int], mark: &mut *mut c_uchar) -> Result<Option<(c_int, usize, usize)>, ExecError> {
+    let options = if not_empty_at_start {
+        options | ExecOption::ExecNotEmptyAtStart as c_int | ExecOption::ExecAnchored as c_int
+    } else {
+        options
+    };
+    let extra = exec_extra(extra, mark);
+    let rc = detail::try_pcre_exec(code,
+                                   &extra,
+                                   subject.as_ptr() as *const c_char,
+                                   subject.len() as c_int,
+                                   offset as c_int,
+                                   options,
+                                   ovector.as_mut_ptr(),
+                                   ovector.len() as c_int);
+    if rc == detail::PCRE_ERROR_MATCHLIMIT {
+        Err(ExecError::MatchLimit)
+    } else if rc >= 0 {
+        Ok(Some((rc, ovector[0] as usize, ovector[1] as usize)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns a copy of the extra block `extra` (or an empty extra block if `extra` is null) for
+/// a single call to `pcre_exec()`, which stores the MARK in `mark`.
+///
+/// Using a copy means that matches never share the location of the MARK, and that nothing
+/// is left pointing at the caller's state after the match.
+unsafe fn exec_extra(extra: *const PcreExtra, mark: &mut *mut c_uchar) -> PcreExtra {
+    let mut exec_extra = if extra.is_null() {
+        PcreExtra::default()
+    } else {
+        (*extra).clone()
+    };
+    exec_extra.set_mark(mark);
+    exec_extra
+}
+
+/// Returns the MARK name pointed to by `mark`, which points into the compiled pattern.
+unsafe fn mark_bytes<'c>(mark: *const c_uchar) -> Option<&'c [u8]> {
+    if mark.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(mark as *const c_char).to_bytes())
+    }
+}
+
+/// Panics unless `subject` can be matched starting at offset `startoffset`.
+///
+/// libpcre represents lengths and offsets as `c_int`s. Subjects are passed to libpcre with
+/// `PCRE_NO_UTF8_CHECK`, so starting a match in the middle of a UTF-8 character would be
+/// undefined behavior.
+fn check_subject(subject: &str, startoffset: usize) {
+    assert!(subject.len() <= c_int::MAX as usize,
+            "subject length {} exceeds the maximum supported by libpcre ({})", subject.len(), c_int::MAX);
+    assert!(subject.is_char_boundary(startoffset),
+            "startoffset {} is out of bounds or not on a character boundary", startoffset);
+}
+
+/// Returns the smallest character boundary in `s` that is not less than `i`.
+fn ceil_char_boundary(s: &str, mut i: usize) -> usize {
+    while i < s.len() && !s.is_char_boundary(i) {
+        i += 1;
     }
+    i
 }
 
 /// Returns libpcre version information.
@@ -0,0 +1,288 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Converting the byte offsets that libpcre reports to character offsets, UTF-16 offsets
+//! and lines and columns, and matching from a character offset.
+
+use enum_set::{EnumSet};
+use std::fmt;
+use std::ops::{Range};
+use std::vec::{Vec};
+
+use {ExecOption, Match, Pcre};
+
+/// The number of bytes between the offsets at which the character and UTF-16 counts are
+/// stored.
+const BLOCK_SIZE: usize = 64;
+
+/// A position in a subject string as a line and a column, both counted from 0. Lines are
+/// separated by `\n`.
+///
+/// It displays itself counted from 1, as `line:column`, which is the usual form in error
+/// messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineColumn {
+
+    pub line: usize,
+
+    pub column: usize
+
+}
+
+/// Converts the byte offsets in a subject string, such as those of a
+/// [Match](struct.Match.html), to character offsets, UTF-16 offsets and lines and columns,
+/// and back.
+///
+/// The map is built in one pass over the subject, after which a conversion looks at no
+/// more than 64 bytes of the subject, so build one map for a subject and reuse it for all
+/// of the matches in it.
+///
+/// # Example
+/// ```
+/// use pcre::{LineColumn, OffsetMap, Pcre};
+///
+/// let subject = "naïve\nrésumé 😀 done";
+/// let map = OffsetMap::new(subject);
+/// let m = Pcre::compile("done").unwrap().exec(subject).unwrap();
+///
+/// assert_eq!(m.group_start(0), 21);
+/// assert_eq!(m.char_span(0, &map), 15..19);
+/// assert_eq!(m.utf16_span(0, &map), 16..20);
+/// assert_eq!(m.line_column_span(0, &map).start, LineColumn { line: 1, column: 9 });
+/// assert_eq!(map.line_column(21).to_string(), "2:10");
+/// ```
+pub struct OffsetMap<'a> {
+
+    subject: &'a str,
+
+    /// The number of characters which start before each multiple of `BLOCK_SIZE`, and
+    /// in the whole subject.
+    chars: Vec<usize>,
+
+    /// The number of UTF-16 code units in the characters which start before each multiple
+    /// of `BLOCK_SIZE`, and in the whole subject.
+    utf16: Vec<usize>,
+
+    /// The byte offset of the start of each line.
+    lines: Vec<usize>
+
+}
+
+/// Returns the number of characters which start with `byte`.
+fn char_units(byte: u8) -> usize {
+    (byte & 0xc0 != 0x80) as usize
+}
+
+/// Returns the number of UTF-16 code units of the character which starts with `byte`, or 0
+/// if `byte` does not start a character.
+fn utf16_units(byte: u8) -> usize {
+    char_units(byte) + (byte >= 0xf0) as usize
+}
+
+impl<'a> OffsetMap<'a> {
+    /// Builds the map for `subject`.
+    pub fn new(subject: &'a str) -> OffsetMap<'a> {
+        let blocks = subject.len() / BLOCK_SIZE + 2;
+        let mut chars = Vec::with_capacity(blocks);
+        let mut utf16 = Vec::with_capacity(blocks);
+        let (mut char_count, mut utf16_count) = (0, 0);
+        for block in subject.as_bytes().chunks(BLOCK_SIZE) {
+            chars.push(char_count);
+            utf16.push(utf16_count);
+            char_count += block.iter().map(|&b| char_units(b)).sum::<usize>();
+            utf16_count += block.iter().map(|&b| utf16_units(b)).sum::<usize>();
+        }
+        chars.push(char_count);
+        utf16.push(utf16_count);
+
+        let mut lines = vec![0];
+        lines.extend(subject.bytes().enumerate().filter(|&(_, b)| b == b'\n').map(|(i, _)| i + 1));
+        OffsetMap {
+            subject,
+            chars,
+            utf16,
+            lines
+        }
+    }
+
+    /// Returns the subject string of the map.
+    pub fn subject(&self) -> &'a str {
+        self.subject
+    }
+
+    /// Returns the number of units before byte offset `offset`, given the counts in
+    /// `table` and the units of each byte.
+    fn count(&self, table: &[usize], units: fn(u8) -> usize, offset: usize) -> usize {
+        assert!(self.subject.is_char_boundary(offset),
+                "offset {} is out of bounds or not on a character boundary", offset);
+        let block = offset / BLOCK_SIZE;
+        table[block] + self.subject.as_bytes()[(block * BLOCK_SIZE)..offset].iter().map(|&b| units(b)).sum::<usize>()
+    }
+
+    /// Returns the byte offset of the character which `n` units start before, given the
+    /// counts in `table` and the units of each byte.
+    fn find(&self, table: &[usize], units: fn(u8) -> usize, n: usize) -> Option<usize> {
+        if n > table[table.len() - 1] {
+            return None;
+        }
+        // The counts only increase, since a block holds the start of a character.
+        let block = table.partition_point(|&count| count <= n) - 1;
+        let mut remaining = n - table[block];
+        let bytes = self.subject.as_bytes();
+        for (i, &b) in bytes.iter().enumerate().skip(block * BLOCK_SIZE) {
+            if units(b) > 0 {
+                if remaining == 0 {
+                    return Some(i);
+                }
+                if remaining < units(b) {
+                    // `n` is between the two UTF-16 code units of a surrogate pair.
+                    return None;
+                }
+                remaining -= units(b);
+            }
+        }
+        if remaining == 0 { Some(bytes.len()) } else { None }
+    }
+
+    /// Returns the number of characters before byte offset `offset`.
+    ///
+    /// # Panics
+    /// If `offset` is greater than the length of the subject or is not on a UTF-8
+    /// character boundary.
+    pub fn char_index(&self, offset: usize) -> usize {
+        self.count(&self.chars, char_units, offset)
+    }
+
+    /// Returns the number of UTF-16 code units before byte offset `offset`.
+    ///
+    /// # Panics
+    /// If `offset` is greater than the length of the subject or is not on a UTF-8
+    /// character boundary.
+    pub fn utf16_index(&self, offset: usize) -> usize {
+        self.count(&self.utf16, utf16_units, offset)
+    }
+
+    /// Returns the line of byte offset `offset` and its column in characters.
+    ///
+    /// # Panics
+    /// If `offset` is greater than the length of the subject or is not on a UTF-8
+    /// character boundary.
+    pub fn line_column(&self, offset: usize) -> LineColumn {
+        let line = self.line(offset);
+        LineColumn { line, column: self.char_index(offset) - self.char_index(self.lines[line]) }
+    }
+
+    /// Returns the line of byte offset `offset` and its column in UTF-16 code units, which
+    /// is a `Position` of the Language Server Protocol.
+    ///
+    /// # Panics
+    /// If `offset` is greater than the length of the subject or is not on a UTF-8
+    /// character boundary.
+    pub fn line_column_utf16(&self, offset: usize) -> LineColumn {
+        let line = self.line(offset);
+        LineColumn { line, column: self.utf16_index(offset) - self.utf16_index(self.lines[line]) }
+    }
+
+    /// Returns the line that byte offset `offset` is in.
+    fn line(&self, offset: usize) -> usize {
+        self.lines.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// Returns the byte offset of the character at character offset `index`.
+    ///
+    /// # Return value
+    /// `None` if the subject has fewer than `index` characters.
+    pub fn char_to_byte(&self, index: usize) -> Option<usize> {
+        self.find(&self.chars, char_units, index)
+    }
+
+    /// Returns the byte offset of the character at UTF-16 offset `index`.
+    ///
+    /// # Return value
+    /// `None` if the subject has fewer than `index` UTF-16 code units, or `index` is
+    /// between the code units of a surrogate pair.
+    pub fn utf16_to_byte(&self, index: usize) -> Option<usize> {
+        self.find(&self.utf16, utf16_units, index)
+    }
+}
+
+impl fmt::Display for LineColumn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.column + 1)
+    }
+}
+
+impl<'a> Match<'a> {
+    /// Returns the byte range of capture group `n`, checking that `map` is for the subject
+    /// string of the match.
+    fn mapped_span(&self, n: usize, map: &OffsetMap) -> Range<usize> {
+        assert!(self.subject.as_ptr() == map.subject.as_ptr() && self.subject.len() == map.subject.len(),
+                "the offset map is not for the subject string of the match");
+        self.group_start(n)..self.group_end(n)
+    }
+
+    /// Returns the character offsets of the start and end of capture group `n`.
+    ///
+    /// # Panics
+    /// If `map` is not for the subject string of the match, or the capture group did not
+    /// participate in the match.
+    pub fn char_span(&self, n: usize, map: &OffsetMap) -> Range<usize> {
+        let span = self.mapped_span(n, map);
+        map.char_index(span.start)..map.char_index(span.end)
+    }
+
+    /// Returns the UTF-16 offsets of the start and end of capture group `n`.
+    ///
+    /// # Panics
+    /// If `map` is not for the subject string of the match, or the capture group did not
+    /// participate in the match.
+    pub fn utf16_span(&self, n: usize, map: &OffsetMap) -> Range<usize> {
+        let span = self.mapped_span(n, map);
+        map.utf16_index(span.start)..map.utf16_index(span.end)
+    }
+
+    /// Returns the lines and columns, in characters, of the start and end of capture group
+    /// `n`.
+    ///
+    /// # Panics
+    /// If `map` is not for the subject string of the match, or the capture group did not
+    /// participate in the match.
+    pub fn line_column_span(&self, n: usize, map: &OffsetMap) -> Range<LineColumn> {
+        let span = self.mapped_span(n, map);
+        map.line_column(span.start)..map.line_column(span.end)
+    }
+}
+
+impl Pcre {
+    /// Matches the compiled regular expression against `subject` starting at character
+    /// offset `charoffset`, as [exec_from()](#method.exec_from) does from a byte offset.
+    ///
+    /// Finding the byte offset takes time in proportion to `charoffset`; to match from
+    /// many character offsets in a subject, convert them with an
+    /// [OffsetMap](struct.OffsetMap.html) instead.
+    ///
+    /// # Panics
+    /// If `subject` has fewer than `charoffset` characters, or is longer than `i32::MAX`
+    /// bytes.
+    pub fn exec_from_char<'a>(&self, subject: &'a str, charoffset: usize) -> Option<Match<'a>> {
+        self.exec_from_char_with_options(subject, charoffset, &EnumSet::new())
+    }
+
+    /// Matches the compiled regular expression against `subject` starting at character
+    /// offset `charoffset` with the given options, as
+    /// [exec_from_with_options()](#method.exec_from_with_options) does from a byte offset.
+    ///
+    /// # Panics
+    /// If `subject` has fewer than `charoffset` characters, or is longer than `i32::MAX`
+    /// bytes.
+    pub fn exec_from_char_with_options<'a>(&self, subject: &'a str, charoffset: usize, options: &EnumSet<ExecOption>) -> Option<Match<'a>> {
+        let startoffset = subject.char_indices().map(|(i, _)| i).chain(Some(subject.len())).nth(charoffset);
+        let startoffset = startoffset.unwrap_or_else(|| panic!("character offset {} is out of bounds", charoffset));
+        self.exec_from_with_options(subject, startoffset, options)
+    }
+}
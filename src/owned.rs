@@ -0,0 +1,189 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Matches which own their subject strings, so that they are not tied to the lifetime of
+//! a borrowed subject.
+
+use libc::{c_int};
+use std::collections::{BTreeMap};
+use std::str;
+use std::string::{String};
+use std::sync::{Arc};
+use std::vec::{Vec};
+
+use {Match, Pcre};
+
+/// A match which owns its subject string, so that it can be returned from a function which
+/// owns the subject or sent to another thread.
+///
+/// The subject can be stored in any type which implements `AsRef<str>`, such as `String`,
+/// `Box<str>` or `Arc<str>`; sharing an `Arc<str>` between the matches in a subject avoids
+/// copying it for each match. The accessors are the same as those of [Match](struct.Match.html),
+/// and [as_match()](#method.as_match) borrows an `OwnedMatch` as a `Match` for everything
+/// else.
+///
+/// # Example
+/// ```
+/// use pcre::{OwnedMatch, Pcre};
+/// use std::sync::Arc;
+///
+/// fn first_word(re: &Pcre, line: String) -> Option<OwnedMatch> {
+///     re.exec_owned(line)
+/// }
+///
+/// let re = Pcre::compile(r"\w+").unwrap();
+/// let m = first_word(&re, "  hello world".to_string()).unwrap();
+/// assert_eq!((m.group_start(0), m.group(0)), (2, "hello"));
+///
+/// let subject: Arc<str> = Arc::from("to be");
+/// let words: Vec<OwnedMatch<Arc<str>>> = re.matches(&subject).map(|m| m.with_subject(subject.clone())).collect();
+/// assert_eq!(words[1].group(0), "be");
+/// ```
+#[derive(Clone, Debug)]
+pub struct OwnedMatch<S = String> {
+
+    subject: S,
+
+    partial_ovector: Vec<c_int>,
+
+    string_count_: c_int,
+
+    mark_: Option<Vec<u8>>,
+
+    name_table: Arc<BTreeMap<String, Vec<usize>>>
+
+}
+
+impl Pcre {
+    /// Matches the compiled regular expression against `subject`, as [exec()](#method.exec)
+    /// does, returning a match which owns the subject.
+    pub fn exec_owned<S: AsRef<str>>(&self, subject: S) -> Option<OwnedMatch<S>> {
+        let Match { partial_ovector, string_count_, mark_, name_table, .. } = self.exec(subject.as_ref())?;
+        Some(OwnedMatch {
+            subject,
+            partial_ovector,
+            string_count_,
+            mark_,
+            name_table
+        })
+    }
+}
+
+impl<'a> Match<'a> {
+    /// Converts the match into one which owns a copy of its subject string.
+    pub fn into_owned(self) -> OwnedMatch {
+        let subject = self.subject.to_string();
+        self.with_subject(subject)
+    }
+
+    /// Converts the match into one which owns `subject`, which must hold the same text as
+    /// the subject string of the match, for example an `Arc<str>` which the subject string
+    /// was borrowed from.
+    ///
+    /// # Panics
+    /// If `subject` is not the subject string of the match.
+    pub fn with_subject<S: AsRef<str>>(self, subject: S) -> OwnedMatch<S> {
+        let same = subject.as_ref().as_ptr() == self.subject.as_ptr() && subject.as_ref().len() == self.subject.len();
+        assert!(same || subject.as_ref() == self.subject, "the subject of an owned match must be the subject string of the match");
+        OwnedMatch {
+            subject,
+            partial_ovector: self.partial_ovector,
+            string_count_: self.string_count_,
+            mark_: self.mark_,
+            name_table: self.name_table
+        }
+    }
+}
+
+impl<S: AsRef<str>> OwnedMatch<S> {
+    /// Returns the subject string.
+    pub fn subject(&self) -> &str {
+        self.subject.as_ref()
+    }
+
+    /// Returns the storage of the subject string, dropping the match.
+    pub fn into_subject(self) -> S {
+        self.subject
+    }
+
+    /// Borrows the match as a [Match](struct.Match.html), for example to call
+    /// [expand()](struct.Match.html#method.expand) on it.
+    pub fn as_match(&self) -> Match<'_> {
+        Match {
+            subject: self.subject.as_ref(),
+            partial_ovector: self.partial_ovector.clone(),
+            string_count_: self.string_count_,
+            mark_: self.mark_.clone(),
+            name_table: self.name_table.clone()
+        }
+    }
+
+    /// Returns the start index within the subject string of capture group `n`.
+    ///
+    /// If the capture group is present in the pattern but wasn't captured then the start of it will be `usize::MAX`.
+    pub fn group_start(&self, n: usize) -> usize {
+        self.partial_ovector[n * 2] as usize
+    }
+
+    /// Returns the end index within the subject string of capture group `n`.
+    ///
+    /// If the capture group is present in the pattern but wasn't captured then the end of it will be `usize::MAX`.
+    pub fn group_end(&self, n: usize) -> usize {
+        self.partial_ovector[n * 2 + 1] as usize
+    }
+
+    /// Returns the length of the substring for capture group `n`.
+    pub fn group_len(&self, n: usize) -> usize {
+        (self.partial_ovector[n * 2 + 1] - self.partial_ovector[n * 2]) as usize
+    }
+
+    /// Returns the substring for capture group `n` as a slice.
+    ///
+    /// # Panics
+    /// If the capture group did not participate in the match, or if the substring does not
+    /// start and end on UTF-8 character boundaries.
+    pub fn group(&self, n: usize) -> &str {
+        &self.subject()[self.group_start(n)..self.group_end(n)]
+    }
+
+    /// Returns the substring for capture group `n` as a byte slice.
+    ///
+    /// # Panics
+    /// If the capture group did not participate in the match.
+    pub fn group_bytes(&self, n: usize) -> &[u8] {
+        &self.subject().as_bytes()[self.group_start(n)..self.group_end(n)]
+    }
+
+    /// Returns the substring for the capture group named `name`.
+    ///
+    /// If several groups have the name, the first one which participated in the match is
+    /// used.
+    ///
+    /// # Return value
+    /// `None` if there is no group named `name` or it did not participate in the match.
+    pub fn name(&self, name: &str) -> Option<&str> {
+        self.name_table.get(name)
+            .and_then(|ns| ns.iter().cloned().find(|&n| n < self.string_count() && self.partial_ovector[n * 2] >= 0))
+            .map(|n| self.group(n))
+    }
+
+    /// Returns the number of substrings captured.
+    pub fn string_count(&self) -> usize {
+        self.string_count_ as usize
+    }
+
+    /// Returns the MARK name that was set by this match, if any.
+    pub fn mark(&self) -> Option<&str> {
+        self.mark_bytes().map(|bytes| str::from_utf8(bytes).unwrap())
+    }
+
+    /// Returns the MARK name that was set by this match, if any.
+    pub fn mark_bytes(&self) -> Option<&[u8]> {
+        self.mark_.as_ref().map(|mark| &mark[..])
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Matching one pattern against many subjects on the threads of rayon's thread pool,
+//! enabled by the `rayon` feature.
+
+use libc::{c_char, c_int, c_uchar};
+use rayon::prelude::*;
+use std::ptr;
+use std::vec::{Vec};
+
+use {Match, Pcre, check_subject, detail, exec_extra, mark_bytes};
+
+impl Pcre {
+    /// Matches `subject` from its start, storing the offsets into `ovector` and returning
+    /// the return code and the MARK.
+    fn exec_on_thread(&self, subject: &str, ovector: &mut [c_int]) -> (c_int, Option<Vec<u8>>) {
+        check_subject(subject, 0);
+        let mut mark: *mut c_uchar = ptr::null_mut();
+        unsafe {
+            let extra = exec_extra(self.extra, &mut mark);
+            let rc = detail::pcre_exec(self.code, &extra, subject.as_ptr() as *const c_char, subject.len() as c_int, 0, 0, ovector.as_mut_ptr(), ovector.len() as c_int);
+            self.record_mark(mark);
+            (rc, mark_bytes(mark).map(Vec::from))
+        }
+    }
+
+    /// Matches the regular expression against each of `subjects` on the threads of rayon's
+    /// current thread pool, returning the first match in each subject, in the order of the
+    /// subjects. Each result is the same as that of [exec()](#method.exec).
+    ///
+    /// The compiled pattern is shared by the threads, and each thread reuses one offset
+    /// vector for its matches. A JIT-compiled pattern runs with the JIT stack of the thread
+    /// which matches, as it does for `exec()`.
+    ///
+    /// This method is only available with the `rayon` feature.
+    ///
+    /// # Panics
+    /// If a subject is longer than `i32::MAX` bytes, or the match limit or the recursion
+    /// depth limit is exceeded.
+    ///
+    /// # Example
+    /// ```
+    /// use pcre::Pcre;
+    ///
+    /// let re = Pcre::builder(r"(\w+)@example\.com").jit(true).build().unwrap();
+    /// let records = ["ada@example.com", "nobody", "grace@example.com"];
+    /// let users: Vec<_> = re.par_matches(&records).iter().map(|m| m.as_ref().map(|m| m.group(1))).collect();
+    /// assert_eq!(users, vec![Some("ada"), None, Some("grace")]);
+    /// ```
+    pub fn par_matches<'a>(&self, subjects: &[&'a str]) -> Vec<Option<Match<'a>>> {
+        let ovecsize = ((self.capture_count_ + 1) * 3) as usize;
+        subjects.par_iter().map_init(|| vec![0 as c_int; ovecsize], |ovector, &subject| {
+            let (rc, mark) = self.exec_on_thread(subject, ovector);
+            if rc < 0 {
+                return None;
+            }
+            Some(Match {
+                subject,
+                partial_ovector: ovector[..(ovecsize / 3 * 2)].to_vec(),
+                string_count_: rc,
+                mark_: mark,
+                name_table: self.name_table_.clone()
+            })
+        }).collect()
+    }
+
+    /// Returns whether the regular expression matches each of `subjects`, in the order of
+    /// the subjects, matching on the threads of rayon's current thread pool as
+    /// [par_matches()](#method.par_matches) does.
+    ///
+    /// Like [is_match()](#method.is_match), this is faster than checking the results of
+    /// `par_matches()` because the offsets of the matches are not computed.
+    ///
+    /// This method is only available with the `rayon` feature.
+    ///
+    /// # Panics
+    /// If a subject is longer than `i32::MAX` bytes, or the match limit or the recursion
+    /// depth limit is exceeded.
+    pub fn par_is_match(&self, subjects: &[&str]) -> Vec<bool> {
+        subjects.par_iter().map(|&subject| self.exec_on_thread(subject, &mut []).0 >= 0).collect()
+    }
+}
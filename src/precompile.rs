@@ -0,0 +1,254 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Compiling patterns ahead of time, typically in a build script, and loading the compiled
+//! patterns at run time without calling `pcre_compile()`.
+//!
+//! In `build.rs`:
+//!
+//! ```no_run
+//! extern crate enum_set;
+//! extern crate pcre;
+//!
+//! use enum_set::{EnumSet};
+//! use pcre::CompileOption;
+//! use std::env;
+//! use std::path::{Path};
+//!
+//! fn main() {
+//!     let mut caseless = EnumSet::new();
+//!     caseless.insert(CompileOption::Caseless);
+//!     let patterns = [(r"^\d+$", EnumSet::new()), ("^yes$", caseless)];
+//!     let path = Path::new(&env::var("OUT_DIR").unwrap()).join("patterns.bin");
+//!     pcre::precompile::write_file(&patterns, path).unwrap();
+//! }
+//! ```
+//!
+//! And in the program:
+//!
+//! ```ignore
+//! static PATTERNS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/patterns.bin"));
+//!
+//! let patterns = unsafe { pcre::precompile::load(PATTERNS) }.unwrap();
+//! assert!(patterns[1].is_match("YES"));
+//! ```
+//!
+//! The compiled form of a pattern depends on the version of libpcre, so loading fails if the
+//! patterns were compiled with a different version than the one that the program runs with.
+//! Patterns which were compiled on a host with a different byte order are converted when
+//! they are loaded.
+
+use enum_set::{EnumSet};
+use libc::{c_void, size_t};
+use libpcre_sys;
+use std::error::{Error};
+use std::fmt;
+use std::fs::{File};
+use std::io::{self, Write};
+use std::path::{Path};
+use std::ptr;
+use std::slice;
+use std::string::{String};
+use std::vec::{Vec};
+
+use {detail, flags, CompilationError, CompileOption, Pcre};
+
+/// Identifies the format of the data written by [write()](fn.write.html).
+const MAGIC: &[u8] = b"RPCRE\x00\x01";
+
+/// The reason that patterns could not be precompiled.
+#[derive(Debug)]
+pub enum PrecompileError {
+    /// The pattern at index `index` could not be compiled.
+    Compile {
+        index: usize,
+        error: CompilationError
+    },
+
+    /// The compiled patterns could not be written.
+    Io(io::Error)
+}
+
+/// The reason that precompiled patterns could not be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// The data was not written by [write()](fn.write.html), or is truncated.
+    Format,
+
+    /// The patterns were compiled with the libpcre version `compiled`, but the program is
+    /// running with `running`.
+    VersionMismatch {
+        compiled: String,
+        running: String
+    },
+
+    /// The compiled pattern at index `index` is not valid.
+    InvalidCode(usize)
+}
+
+impl fmt::Display for PrecompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PrecompileError::Compile { index, ref error } => write!(f, "pattern {}: {}", index, error),
+            PrecompileError::Io(ref error) => write!(f, "{}", error)
+        }
+    }
+}
+
+impl Error for PrecompileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PrecompileError::Compile { ref error, .. } => Some(error),
+            PrecompileError::Io(ref error) => Some(error)
+        }
+    }
+}
+
+impl From<io::Error> for PrecompileError {
+    fn from(error: io::Error) -> PrecompileError {
+        PrecompileError::Io(error)
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Format => write!(f, "not precompiled patterns, or truncated"),
+            LoadError::VersionMismatch { ref compiled, ref running } => {
+                write!(f, "patterns were compiled with libpcre {}, but libpcre {} is in use", compiled, running)
+            },
+            LoadError::InvalidCode(index) => write!(f, "compiled pattern {} is invalid", index)
+        }
+    }
+}
+
+impl Error for LoadError {
+}
+
+fn write_bytes<W: Write>(out: &mut W, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    out.write_all(bytes)
+}
+
+/// Compiles `patterns`, each with its compile options, and writes the compiled patterns to
+/// `out`.
+pub fn write<W: Write>(patterns: &[(&str, EnumSet<CompileOption>)], out: &mut W) -> Result<(), PrecompileError> {
+    out.write_all(MAGIC)?;
+    write_bytes(out, detail::pcre_version().as_bytes())?;
+    out.write_all(&(patterns.len() as u32).to_le_bytes())?;
+    for (index, &(pattern, ref options)) in patterns.iter().enumerate() {
+        let re = match Pcre::compile_with_options(pattern, options) {
+            Err(error) => return Err(PrecompileError::Compile { index, error }),
+            Ok(re) => re
+        };
+        let mut size: size_t = 0;
+        let code = unsafe {
+            detail::pcre_fullinfo(re.code, ptr::null(), libpcre_sys::PCRE_INFO_SIZE, &mut size as *mut size_t as *mut c_void);
+            slice::from_raw_parts(re.code as *const u8, size)
+        };
+        write_bytes(out, flags::format_flags(options).as_bytes())?;
+        write_bytes(out, pattern.as_bytes())?;
+        write_bytes(out, code)?;
+    }
+    Ok(())
+}
+
+/// Compiles `patterns` like [write()](fn.write.html), writing the compiled patterns to the
+/// file at `path`.
+pub fn write_file<P: AsRef<Path>>(patterns: &[(&str, EnumSet<CompileOption>)], path: P) -> Result<(), PrecompileError> {
+    let mut out = io::BufWriter::new(File::create(path)?);
+    write(patterns, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Reads the data written by `write()`.
+struct Reader<'d> {
+
+    data: &'d [u8]
+
+}
+
+impl<'d> Reader<'d> {
+    fn take(&mut self, n: usize) -> Result<&'d [u8], LoadError> {
+        if self.data.len() < n {
+            return Err(LoadError::Format);
+        }
+        let (bytes, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<usize, LoadError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn read_bytes(&mut self) -> Result<&'d [u8], LoadError> {
+        let len = self.read_u32()?;
+        self.take(len)
+    }
+
+    fn read_str(&mut self) -> Result<&'d str, LoadError> {
+        ::std::str::from_utf8(self.read_bytes()?).map_err(|_| LoadError::Format)
+    }
+}
+
+/// Loads the patterns compiled by [write()](fn.write.html), in the same order.
+///
+/// # Safety
+/// libpcre does not check compiled patterns beyond their header, so `data` must have been
+/// written by `write()`, for example by a build script whose output is embedded with
+/// `include_bytes!`. Loading corrupted data can cause undefined behavior when matching.
+pub unsafe fn load(data: &[u8]) -> Result<Vec<Pcre>, LoadError> {
+    let mut reader = Reader { data };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(LoadError::Format);
+    }
+    let compiled = reader.read_str()?;
+    let running = detail::pcre_version();
+    if compiled != running {
+        return Err(LoadError::VersionMismatch {
+            compiled: compiled.to_string(),
+            running
+        });
+    }
+
+    let count = reader.read_u32()?;
+    let mut patterns = Vec::with_capacity(count);
+    for index in 0..count {
+        let options = flags::parse_flags(reader.read_str()?).map_err(|_| LoadError::Format)?;
+        let pattern = reader.read_str()?;
+        let code_bytes = reader.read_bytes()?;
+        if code_bytes.is_empty() {
+            return Err(LoadError::InvalidCode(index));
+        }
+
+        // The code is freed with pcre_free() when the `Pcre` is dropped.
+        let code = detail::pcre_malloc(code_bytes.len()) as *mut detail::pcre;
+        ptr::copy_nonoverlapping(code_bytes.as_ptr(), code as *mut u8, code_bytes.len());
+        let mut size: size_t = 0;
+        if detail::pcre_pattern_to_host_byte_order(code) {
+            detail::pcre_fullinfo(code, ptr::null(), libpcre_sys::PCRE_INFO_SIZE, &mut size as *mut size_t as *mut c_void);
+        }
+        if size != code_bytes.len() {
+            detail::pcre_free(code as *mut c_void);
+            return Err(LoadError::InvalidCode(index));
+        }
+
+        // The reference count is part of the compiled pattern, so it was saved too.
+        let refcount = detail::pcre_refcount(code, 0);
+        detail::pcre_refcount(code, 1 - refcount);
+
+        patterns.push(Pcre::from_code(code, pattern, &options, None));
+    }
+    if !reader.data.is_empty() {
+        return Err(LoadError::Format);
+    }
+    Ok(patterns)
+}
@@ -0,0 +1,421 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An API modelled on the `regex` crate's `Regex` type, built on [`Pcre`](../struct.Pcre.html).
+//!
+//! Code written against the `regex` crate can switch to PCRE syntax (backreferences,
+//! lookaround, etc.) by changing its imports to this module.
+
+use std::collections::{BTreeMap};
+use std::fmt;
+use std::ops::{Index, Range};
+use std::str::{FromStr};
+use std::sync::{Arc};
+
+use {CompilationError, Pcre};
+
+/// A compiled regular expression.
+pub struct Regex {
+
+    re: Pcre,
+
+    name_table: Arc<BTreeMap<String, Vec<usize>>>,
+
+    group_names: Vec<Option<String>>
+
+}
+
+/// A single match of a regular expression in a subject string.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Match<'t> {
+
+    text: &'t str,
+
+    start: usize,
+
+    end: usize
+
+}
+
+/// The capture groups of a single match.
+pub struct Captures<'t> {
+
+    text: &'t str,
+
+    locs: Vec<Option<(usize, usize)>>,
+
+    name_table: Arc<BTreeMap<String, Vec<usize>>>
+
+}
+
+/// Iterator over the successive non-overlapping matches in a subject string.
+pub struct Matches<'r, 't> {
+
+    re: &'r Regex,
+
+    text: &'t str,
+
+    last_end: usize,
+
+    last_match: Option<usize>
+
+}
+
+/// Iterator over the capture groups of successive non-overlapping matches in a subject string.
+pub struct CaptureMatches<'r, 't> {
+
+    re: &'r Regex,
+
+    text: &'t str,
+
+    last_end: usize,
+
+    last_match: Option<usize>
+
+}
+
+/// Iterator over the names of the capture groups of a regular expression.
+pub struct CaptureNames<'r> {
+
+    it: ::std::slice::Iter<'r, Option<String>>
+
+}
+
+/// Iterator over the capture groups of a single match.
+pub struct SubCaptureMatches<'c, 't: 'c> {
+
+    caps: &'c Captures<'t>,
+
+    i: usize
+
+}
+
+impl Regex {
+    /// Compiles a regular expression.
+    pub fn new(re: &str) -> Result<Regex, CompilationError> {
+        let pcre = Pcre::compile(re)?;
+        let name_table = pcre.name_table();
+        let mut group_names = vec![None; pcre.capture_count() + 1];
+        for (name, ns) in name_table.iter() {
+            for n in ns.iter() {
+                group_names[*n] = Some(name.clone());
+            }
+        }
+        Ok(Regex {
+            re: pcre,
+            name_table: Arc::new(name_table),
+            group_names
+        })
+    }
+
+    /// Returns the pattern that this regular expression was compiled from.
+    pub fn as_str(&self) -> &str {
+        self.re.pattern()
+    }
+
+    /// Returns `true` if there is a match anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.re.is_match(text)
+    }
+
+    /// Returns the leftmost match in `text`, if any.
+    pub fn find<'t>(&self, text: &'t str) -> Option<Match<'t>> {
+        self.find_at(text, 0)
+    }
+
+    /// Returns an iterator over the successive non-overlapping matches in `text`.
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> Matches<'r, 't> {
+        Matches {
+            re: self,
+            text,
+            last_end: 0,
+            last_match: None
+        }
+    }
+
+    /// Returns the capture groups of the leftmost match in `text`, if any.
+    pub fn captures<'t>(&self, text: &'t str) -> Option<Captures<'t>> {
+        self.captures_at(text, 0)
+    }
+
+    /// Returns an iterator over the capture groups of the successive non-overlapping
+    /// matches in `text`.
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> CaptureMatches<'r, 't> {
+        CaptureMatches {
+            re: self,
+            text,
+            last_end: 0,
+            last_match: None
+        }
+    }
+
+    /// Returns the number of capture groups, including group 0.
+    pub fn captures_len(&self) -> usize {
+        self.group_names.len()
+    }
+
+    /// Returns an iterator over the names of the capture groups, in group number order.
+    /// Group 0 and unnamed groups yield `None`.
+    pub fn capture_names(&self) -> CaptureNames<'_> {
+        CaptureNames { it: self.group_names.iter() }
+    }
+
+    /// Returns the end offset of a match in `text`, if any.
+    pub fn shortest_match(&self, text: &str) -> Option<usize> {
+        self.find(text).map(|m| m.end())
+    }
+
+    fn find_at<'t>(&self, text: &'t str, start: usize) -> Option<Match<'t>> {
+        self.re.exec_from(text, start).map(|m| Match {
+            text,
+            start: m.group_start(0),
+            end: m.group_end(0)
+        })
+    }
+
+    fn captures_at<'t>(&self, text: &'t str, start: usize) -> Option<Captures<'t>> {
+        self.re.exec_from(text, start).map(|m| {
+            let locs = (0..self.captures_len()).map(|n| {
+                if n < m.string_count() && m.group_start(n) != usize::MAX {
+                    Some((m.group_start(n), m.group_end(n)))
+                } else {
+                    None
+                }
+            }).collect();
+            Captures {
+                text,
+                locs,
+                name_table: self.name_table.clone()
+            }
+        })
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Regex {
+    type Err = CompilationError;
+
+    fn from_str(s: &str) -> Result<Regex, CompilationError> {
+        Regex::new(s)
+    }
+}
+
+impl<'t> Match<'t> {
+    /// Returns the start offset of the match within the subject string.
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the end offset of the match within the subject string.
+    #[inline]
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the range of the match within the subject string.
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the matched text.
+    ///
+    /// # Panics
+    /// If the match does not start and end on UTF-8 character boundaries, which can happen
+    /// when the pattern uses `\C` to match a single byte; use [as_bytes()](#method.as_bytes)
+    /// instead.
+    #[inline]
+    pub fn as_str(&self) -> &'t str {
+        self.text.get(self.range()).unwrap_or_else(|| {
+            panic!("match {}..{} is not on UTF-8 character boundaries", self.start, self.end)
+        })
+    }
+
+    /// Returns the matched text as a byte slice.
+    ///
+    /// Unlike [as_str()](#method.as_str), this works for matches which do not start and end
+    /// on UTF-8 character boundaries.
+    #[inline]
+    pub fn as_bytes(&self) -> &'t [u8] {
+        &self.text.as_bytes()[self.range()]
+    }
+
+    /// Returns `true` if the match is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the length of the match in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+impl<'t> Captures<'t> {
+    /// Returns the match for capture group `i`, or `None` if the group did not participate
+    /// in the match.
+    pub fn get(&self, i: usize) -> Option<Match<'t>> {
+        match self.locs.get(i) {
+            Some(&Some((start, end))) => Some(Match { text: self.text, start, end }),
+            _ => None
+        }
+    }
+
+    /// Returns the match for the capture group named `name`, or `None` if there is no
+    /// such group or it did not participate in the match.
+    ///
+    /// If several groups share the name, the first one that participated in the match
+    /// is returned.
+    pub fn name(&self, name: &str) -> Option<Match<'t>> {
+        self.name_table.get(name).and_then(|ns| ns.iter().filter_map(|n| self.get(*n)).next())
+    }
+
+    /// Returns the number of capture groups, including group 0.
+    pub fn len(&self) -> usize {
+        self.locs.len()
+    }
+
+    /// Returns `true` if there are no capture groups. This is never the case, because
+    /// group 0 is always present.
+    pub fn is_empty(&self) -> bool {
+        self.locs.is_empty()
+    }
+
+    /// Returns an iterator over all capture groups in group number order.
+    pub fn iter<'c>(&'c self) -> SubCaptureMatches<'c, 't> {
+        SubCaptureMatches { caps: self, i: 0 }
+    }
+}
+
+impl<'t> Index<usize> for Captures<'t> {
+    type Output = str;
+
+    /// Returns the text of capture group `i`.
+    ///
+    /// # Panics
+    /// If the group did not participate in the match, or does not start and end on UTF-8
+    /// character boundaries.
+    fn index(&self, i: usize) -> &str {
+        self.get(i).map(|m| m.as_str()).unwrap_or_else(|| panic!("no group at index '{}'", i))
+    }
+}
+
+impl<'t, 'i> Index<&'i str> for Captures<'t> {
+    type Output = str;
+
+    /// Returns the text of the capture group named `name`.
+    ///
+    /// # Panics
+    /// If there is no such group or it did not participate in the match.
+    fn index(&self, name: &'i str) -> &str {
+        self.name(name).map(|m| m.as_str()).unwrap_or_else(|| panic!("no group named '{}'", name))
+    }
+}
+
+impl<'t> fmt::Debug for Captures<'t> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter().map(|m| m.map(|m| m.as_str()))).finish()
+    }
+}
+
+/// Advances global iteration state past a match from `start` to `end` the way that the
+/// `regex` crate does: an empty match which immediately follows the previous match is
+/// skipped, and the search after an empty match resumes at the next character.
+///
+/// With `\K` in a lookbehind assertion, a match can end at or before `last_end`. The
+/// search then resumes at the next character too, and the match is skipped unless it ends
+/// after the previous match, so that each match is found once.
+///
+/// Returns `false` if the match should be skipped.
+fn advance(text: &str, last_end: &mut usize, last_match: &mut Option<usize>, start: usize, end: usize) -> bool {
+    if start == end {
+        *last_end = ::ceil_char_boundary(text, end + 1);
+        if Some(end) == *last_match {
+            return false;
+        }
+    } else if end <= *last_end {
+        *last_end = ::ceil_char_boundary(text, *last_end + 1);
+        if matches!(*last_match, Some(last) if end <= last) {
+            return false;
+        }
+    } else {
+        // A match that ends with `\C` can end in the middle of a character.
+        *last_end = ::ceil_char_boundary(text, end);
+    }
+    *last_match = Some(end);
+    true
+}
+
+impl<'r, 't> Iterator for Matches<'r, 't> {
+    type Item = Match<'t>;
+
+    fn next(&mut self) -> Option<Match<'t>> {
+        while self.last_end <= self.text.len() {
+            let m = self.re.find_at(self.text, self.last_end)?;
+            if advance(self.text, &mut self.last_end, &mut self.last_match, m.start, m.end) {
+                return Some(m);
+            }
+        }
+        None
+    }
+}
+
+impl<'r, 't> Iterator for CaptureMatches<'r, 't> {
+    type Item = Captures<'t>;
+
+    fn next(&mut self) -> Option<Captures<'t>> {
+        while self.last_end <= self.text.len() {
+            let caps = self.re.captures_at(self.text, self.last_end)?;
+            let (start, end) = caps.locs[0].unwrap();
+            if advance(self.text, &mut self.last_end, &mut self.last_match, start, end) {
+                return Some(caps);
+            }
+        }
+        None
+    }
+}
+
+impl<'r> Iterator for CaptureNames<'r> {
+    type Item = Option<&'r str>;
+
+    fn next(&mut self) -> Option<Option<&'r str>> {
+        self.it.next().map(|name| name.as_ref().map(|name| name.as_str()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.size_hint()
+    }
+}
+
+impl<'c, 't> Iterator for SubCaptureMatches<'c, 't> {
+    type Item = Option<Match<'t>>;
+
+    fn next(&mut self) -> Option<Option<Match<'t>>> {
+        if self.i < self.caps.len() {
+            let m = self.caps.get(self.i);
+            self.i += 1;
+            Some(m)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Replacing the matches of a regular expression, with replacement strings which refer to
+//! the capture groups of each match.
+//!
+//! # Example
+//! ```
+//! use pcre::Pcre;
+//! use pcre::replace::replace_stream;
+//!
+//! let re = Pcre::compile(r"(?<key>\w+)=(?<value>\w+)").unwrap();
+//! let mut output = Vec::new();
+//! let summary = replace_stream("a=1 b=2".as_bytes(), &mut output, &re, "$value=$key").unwrap();
+//!
+//! assert_eq!(output, b"1=a 2=b");
+//! assert_eq!(summary.replacements, 2);
+//! assert_eq!(summary.bytes_written, 7);
+//! ```
+
+use chunked::{ChunkedMatcher, Step};
+use std::io::{self, Read, Write};
+use std::string::{String};
+
+use {Match, Pcre};
+
+/// The result of [replace_stream()](fn.replace_stream.html).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplaceSummary {
+
+    /// The number of matches which were replaced.
+    pub replacements: u64,
+
+    /// The number of bytes written to the output.
+    pub bytes_written: u64
+
+}
+
+/// A reference to a capture group in a replacement string.
+enum GroupRef<'r> {
+    Number(usize),
+    Name(&'r str)
+}
+
+/// Parses the group reference after a `$` at the start of `rest`, returning it and the
+/// length of the text it was parsed from.
+fn parse_group_ref(rest: &str) -> Option<(GroupRef<'_>, usize)> {
+    let (name, len) = if rest.starts_with('{') {
+        let end = rest.find('}')?;
+        (&rest[1..end], end + 1)
+    } else {
+        let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        (&rest[..end], end)
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some(match name.parse() {
+        Ok(n) => (GroupRef::Number(n), len),
+        Err(_) => (GroupRef::Name(name), len)
+    })
+}
+
+impl<'a> Match<'a> {
+    /// Appends `replacement` to `dst`, with references to capture groups replaced by the
+    /// text that they captured.
+    ///
+    /// `$n` and `${n}` refer to group `n`, where group 0 is the whole match, and `$name`
+    /// and `${name}` refer to the named group `name`. `$$` is a literal `$`. A reference
+    /// to a group which does not exist or did not participate in the match expands to
+    /// nothing, and a `$` which does not start a reference is copied as is.
+    ///
+    /// The longest possible name is used for a reference without braces, so `$1a` refers
+    /// to a group named `1a`; write `${1}a` instead.
+    ///
+    /// # Example
+    /// ```
+    /// use pcre::Pcre;
+    ///
+    /// let re = Pcre::compile(r"(?<last>\w+), (\w+)").unwrap();
+    /// let m = re.exec("Lovelace, Ada").unwrap();
+    /// let mut dst = String::new();
+    /// m.expand("$2 ${last}", &mut dst);
+    /// assert_eq!(dst, "Ada Lovelace");
+    /// ```
+    pub fn expand(&self, replacement: &str, dst: &mut String) {
+        let mut rest = replacement;
+        while let Some(i) = rest.find('$') {
+            dst.push_str(&rest[..i]);
+            rest = &rest[(i + 1)..];
+            if rest.starts_with('$') {
+                dst.push('$');
+                rest = &rest[1..];
+                continue;
+            }
+            match parse_group_ref(rest) {
+                None => dst.push('$'),
+                Some((group, len)) => {
+                    let n = match group {
+                        GroupRef::Number(n) => Some(n),
+                        GroupRef::Name(name) => self.name_table.get(name).and_then(|ns| ns.iter().cloned().find(|&n| self.is_set(n)))
+                    };
+                    if let Some(n) = n.filter(|&n| self.is_set(n)) {
+                        dst.push_str(self.group(n));
+                    }
+                    rest = &rest[len..];
+                }
+            }
+        }
+        dst.push_str(rest);
+    }
+}
+
+/// Reads all of `reader`, replacing every match of `re` with `replacement` as expanded by
+/// [Match::expand()](../struct.Match.html#method.expand), and writes the result to
+/// `writer`.
+///
+/// The input is read in chunks, and only the text which a match could still start in is
+/// kept, along with the characters that lookbehind assertions look back at (see
+/// [Pcre::max_lookbehind()](../struct.Pcre.html#method.max_lookbehind)). A match which
+/// straddles chunks is found by partial matching with
+/// [`ExecPartialHard`](../enum.ExecOption.html#variant.ExecPartialHard), so the result
+/// is the same as replacing in the whole input at once, while the memory used depends on
+/// the longest match rather than the size of the input.
+///
+/// The matches which are replaced are those that
+/// [Pcre::matches()](../struct.Pcre.html#method.matches) finds in the whole input.
+///
+/// # Errors
+/// Errors from reading and writing are returned, as is an error of kind
+/// `io::ErrorKind::InvalidData` if the input is not valid UTF-8. The output is incomplete
+/// in that case.
+pub fn replace_stream<R: Read, W: Write>(mut reader: R, mut writer: W, re: &Pcre, replacement: &str) -> io::Result<ReplaceSummary> {
+    let mut summary = ReplaceSummary::default();
+    let mut matcher = ChunkedMatcher::new(re);
+    let mut output = String::new();
+    loop {
+        if matcher.needs_input() {
+            let read = loop {
+                match reader.read(matcher.spare()) {
+                    Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    result => break result
+                }
+            }?;
+            matcher.filled(read)?;
+        }
+
+        let done = match matcher.next_step(re) {
+            Step::Match { before, m, .. } => {
+                output.push_str(before);
+                m.expand(replacement, &mut output);
+                summary.replacements += 1;
+                continue;
+            },
+            Step::NeedInput { before } => {
+                output.push_str(before);
+                false
+            },
+            Step::Done { before } => {
+                output.push_str(before);
+                true
+            }
+        };
+
+        writer.write_all(output.as_bytes())?;
+        summary.bytes_written += output.len() as u64;
+        output.clear();
+        if done {
+            break;
+        }
+    }
+
+    writer.flush()?;
+    Ok(summary)
+}
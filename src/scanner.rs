@@ -0,0 +1,162 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use enum_set::{EnumSet};
+
+use {ExecOption, Match, Pcre};
+
+/// A cursor over a subject string which matches regular expressions at its current
+/// position, in the style of Ruby's `StringScanner`.
+///
+/// Patterns are matched against the whole subject starting at the current position, so
+/// lookbehind assertions see the text before the position, `^` only matches at the start
+/// of the subject (or, with `Multiline`, of a line), and `\G` matches at the position.
+///
+/// # Example
+/// ```
+/// use pcre::{Pcre, Scanner};
+///
+/// let word = Pcre::compile(r"\w+").unwrap();
+/// let space = Pcre::compile(r"\s+").unwrap();
+///
+/// let mut scanner = Scanner::new("hello world");
+/// assert_eq!(scanner.scan(&word), Some("hello"));
+/// assert_eq!(scanner.scan(&word), None);
+/// assert_eq!(scanner.skip(&space), Some(1));
+/// assert_eq!(scanner.scan(&word), Some("world"));
+/// assert!(scanner.eos());
+/// ```
+pub struct Scanner<'a> {
+
+    subject: &'a str,
+
+    pos: usize,
+
+    not_bol: bool,
+
+    last_match: Option<Match<'a>>
+
+}
+
+impl<'a> Scanner<'a> {
+    /// Creates a scanner at the start of `subject`.
+    pub fn new(subject: &'a str) -> Scanner<'a> {
+        Scanner {
+            subject,
+            pos: 0,
+            not_bol: false,
+            last_match: None
+        }
+    }
+
+    /// Sets whether the start of the subject is not the start of a line, for example
+    /// because the subject is a piece of a larger text. If so, `^` does not match there
+    /// (`ExecNotBol`).
+    pub fn set_not_bol(&mut self, not_bol: bool) {
+        self.not_bol = not_bol;
+    }
+
+    /// Returns the subject string.
+    pub fn subject(&self) -> &'a str {
+        self.subject
+    }
+
+    /// Returns the current position, a byte offset into the subject.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves to byte offset `pos` and forgets the last match.
+    ///
+    /// # Panics
+    /// If `pos` is greater than the length of the subject or is not on a UTF-8 character
+    /// boundary.
+    pub fn set_pos(&mut self, pos: usize) {
+        assert!(self.subject.is_char_boundary(pos), "position {} is not a character boundary", pos);
+        self.pos = pos;
+        self.last_match = None;
+    }
+
+    /// Returns the rest of the subject, from the current position.
+    pub fn rest(&self) -> &'a str {
+        &self.subject[self.pos..]
+    }
+
+    /// Returns `true` if the current position is at the end of the subject.
+    pub fn eos(&self) -> bool {
+        self.pos == self.subject.len()
+    }
+
+    /// Returns the last match, or `None` if the last attempt did not match.
+    pub fn last_match(&self) -> Option<&Match<'a>> {
+        self.last_match.as_ref()
+    }
+
+    /// Matches `re` at or, if not `anchored`, after the current position, and stores the
+    /// result as the last match. Returns the start and end offsets of the match.
+    fn exec(&mut self, re: &Pcre, anchored: bool) -> Option<(usize, usize)> {
+        let mut options = EnumSet::new();
+        if anchored {
+            options.insert(ExecOption::ExecAnchored);
+        }
+        if self.not_bol {
+            options.insert(ExecOption::ExecNotBol);
+        }
+        self.last_match = re.exec_from_with_options(self.subject, self.pos, &options);
+        self.last_match.as_ref().map(|m| (m.group_start(0), m.group_end(0)))
+    }
+
+    /// Matches `re` at the current position and, if it matches, advances past the match.
+    ///
+    /// # Return value
+    /// The matched text, or `None` if `re` does not match at the current position.
+    pub fn scan(&mut self, re: &Pcre) -> Option<&'a str> {
+        let (start, end) = self.exec(re, true)?;
+        self.pos = end;
+        Some(&self.subject[start..end])
+    }
+
+    /// Like [scan()](#method.scan), but returns the length of the match instead of the
+    /// matched text.
+    pub fn skip(&mut self, re: &Pcre) -> Option<usize> {
+        let (start, end) = self.exec(re, true)?;
+        self.pos = end;
+        Some(end - start)
+    }
+
+    /// Matches `re` at the current position without advancing.
+    ///
+    /// # Return value
+    /// The matched text, or `None` if `re` does not match at the current position.
+    pub fn check(&mut self, re: &Pcre) -> Option<&'a str> {
+        let (start, end) = self.exec(re, true)?;
+        Some(&self.subject[start..end])
+    }
+
+    /// Searches for `re` from the current position and, if it is found, advances past the
+    /// match.
+    ///
+    /// # Return value
+    /// The text from the current position to the end of the match, or `None` if `re` does
+    /// not match in the rest of the subject.
+    pub fn scan_until(&mut self, re: &Pcre) -> Option<&'a str> {
+        let start = self.pos;
+        let (_, end) = self.exec(re, false)?;
+        self.pos = end;
+        Some(&self.subject[start..end])
+    }
+
+    /// Like [scan_until()](#method.scan_until), but returns the number of bytes advanced
+    /// instead of the text.
+    pub fn skip_until(&mut self, re: &Pcre) -> Option<usize> {
+        let start = self.pos;
+        let (_, end) = self.exec(re, false)?;
+        self.pos = end;
+        Some(end - start)
+    }
+}
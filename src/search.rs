@@ -0,0 +1,324 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Searching the lines of a `BufRead` for a regular expression, like `grep`.
+//!
+//! # Example
+//! ```
+//! use pcre::Pcre;
+//! use pcre::search::Searcher;
+//!
+//! let re = Pcre::compile(r"\d+").unwrap();
+//! let input = "one\ntwo 2\nthree\nfour 4 44\n";
+//!
+//! let mut lines = Vec::new();
+//! let summary = Searcher::new().before_context(1).search(&re, input.as_bytes(), |line| {
+//!     lines.push((line.number, line.is_match(), line.text.to_string(), line.spans.to_vec()));
+//!     true
+//! }).unwrap();
+//!
+//! assert_eq!(summary.matched_lines, 2);
+//! assert_eq!(lines, vec![
+//!     (1, false, "one".to_string(), vec![]),
+//!     (2, true, "two 2".to_string(), vec![4..5]),
+//!     (3, false, "three".to_string(), vec![]),
+//!     (4, true, "four 4 44".to_string(), vec![5..6, 7..9])
+//! ]);
+//! ```
+
+use std::borrow::{Cow};
+use std::cell::{RefCell};
+use std::collections::{VecDeque};
+use std::io::{self, BufRead};
+use std::ops::{Range};
+use std::str;
+use std::string::{String};
+use std::vec::{Vec};
+
+use {ExecError, MatchData, Pcre, detail};
+
+/// Options for searching lines. See the [module documentation](index.html).
+#[derive(Clone, Debug, Default)]
+pub struct Searcher {
+
+    invert: bool,
+
+    before_context: usize,
+
+    after_context: usize,
+
+    max_count: Option<u64>,
+
+    binary_detection: bool
+
+}
+
+/// Whether a line is a match or context.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    /// The line matches (or, for an inverted search, does not match).
+    Match,
+
+    /// A line before or after a matching line.
+    Context
+}
+
+/// A line passed to the callback of [`Searcher::search()`](struct.Searcher.html#method.search).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line<'a> {
+
+    pub kind: LineKind,
+
+    /// The line number, starting at 1.
+    pub number: u64,
+
+    /// The byte offset of the start of the line in the input.
+    pub offset: u64,
+
+    /// The text of the line, without the `\n` line terminator.
+    pub text: &'a str,
+
+    /// The byte ranges of the matches within `text`. Empty for context lines and for the
+    /// lines of an inverted search.
+    pub spans: &'a [Range<usize>]
+
+}
+
+/// The result of a search.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+
+    /// The number of matching lines (or, for an inverted search, non-matching lines).
+    pub matched_lines: u64,
+
+    /// The byte offset of the line where binary data was found, which ended the search.
+    /// Always `None` if binary detection is off.
+    pub binary_offset: Option<u64>
+
+}
+
+/// Buffers which are reused by the searches on a thread.
+#[derive(Default)]
+struct Buffers {
+
+    line: Vec<u8>,
+
+    spans: Vec<Range<usize>>,
+
+    data: MatchData,
+
+    /// Unused strings for before-context lines.
+    spare: Vec<String>
+
+}
+
+thread_local! {
+    static BUFFERS: RefCell<Option<Buffers>> = const { RefCell::new(None) };
+}
+
+/// Calls `f` with the buffers of this thread, or new buffers if they are in use by an
+/// enclosing search.
+fn with_buffers<T, F: FnOnce(&mut Buffers) -> T>(f: F) -> T {
+    let mut buffers = BUFFERS.with(|cell| cell.borrow_mut().take()).unwrap_or_default();
+    let result = f(&mut buffers);
+    BUFFERS.with(|cell| *cell.borrow_mut() = Some(buffers));
+    result
+}
+
+impl Line<'_> {
+    /// Returns `true` if this is a matching line rather than a context line.
+    pub fn is_match(&self) -> bool {
+        self.kind == LineKind::Match
+    }
+}
+
+impl Searcher {
+    /// Creates a searcher which reports matching lines without context, with binary
+    /// detection off.
+    pub fn new() -> Searcher {
+        Searcher::default()
+    }
+
+    /// Reports the lines which do not match instead of the lines which do (`grep -v`).
+    pub fn invert(&mut self, yes: bool) -> &mut Searcher {
+        self.invert = yes;
+        self
+    }
+
+    /// Reports up to `n` context lines before each matching line (`grep -B`).
+    pub fn before_context(&mut self, n: usize) -> &mut Searcher {
+        self.before_context = n;
+        self
+    }
+
+    /// Reports up to `n` context lines after each matching line (`grep -A`).
+    pub fn after_context(&mut self, n: usize) -> &mut Searcher {
+        self.after_context = n;
+        self
+    }
+
+    /// Reports up to `n` context lines before and after each matching line (`grep -C`).
+    pub fn context(&mut self, n: usize) -> &mut Searcher {
+        self.before_context(n).after_context(n)
+    }
+
+    /// Stops after `max` matching lines (`grep -m`), after reporting their trailing
+    /// context. `None` means no limit.
+    pub fn max_count(&mut self, max: Option<u64>) -> &mut Searcher {
+        self.max_count = max;
+        self
+    }
+
+    /// Stops the search at the first line which contains a NUL byte or is not valid UTF-8,
+    /// and reports its offset in [`Summary::binary_offset`](struct.Summary.html#structfield.binary_offset).
+    ///
+    /// If binary detection is off, lines which are not valid UTF-8 are searched after
+    /// replacing invalid sequences with U+FFFD, so that match spans are offsets into the
+    /// replaced text.
+    pub fn binary_detection(&mut self, yes: bool) -> &mut Searcher {
+        self.binary_detection = yes;
+        self
+    }
+
+    /// Searches the lines of `reader` for `re`, calling `f` with each matching line and
+    /// context line in order. The search stops early if `f` returns `false`.
+    ///
+    /// Context lines of adjacent matches are reported once, so a gap in the line numbers
+    /// separates groups of lines.
+    ///
+    /// # Panics
+    /// If matching a line exceeds the match limit or the recursion depth limit. Use
+    /// [search_with_errors()](#method.search_with_errors) to handle that.
+    pub fn search<R: BufRead, F: FnMut(&Line) -> bool>(&self, re: &Pcre, reader: R, f: F) -> io::Result<Summary> {
+        self.search_with_errors(re, reader, f, |_, _| detail::match_limit_exceeded())
+    }
+
+    /// Searches the lines of `reader` as [search()](#method.search) does, but calls
+    /// `on_error` with the number of each line on which matching exceeded the match limit or
+    /// the recursion depth limit, and the error. Such a line is neither a matching line nor
+    /// a non-matching one for an inverted search, but it can be a context line. The search
+    /// stops early if `on_error` returns `false`.
+    pub fn search_with_errors<R, F, E>(&self, re: &Pcre, reader: R, f: F, on_error: E) -> io::Result<Summary>
+        where R: BufRead, F: FnMut(&Line) -> bool, E: FnMut(u64, ExecError) -> bool {
+        with_buffers(|buffers| self.search_with(re, reader, true, f, on_error, buffers))
+    }
+
+    /// Counts the matching lines of `reader` (`grep -c`), without computing match spans.
+    ///
+    /// # Panics
+    /// If matching a line exceeds the match limit or the recursion depth limit. Use
+    /// [count_with_errors()](#method.count_with_errors) to handle that.
+    pub fn count<R: BufRead>(&self, re: &Pcre, reader: R) -> io::Result<Summary> {
+        self.count_with_errors(re, reader, |_, _| detail::match_limit_exceeded())
+    }
+
+    /// Counts the matching lines of `reader` as [count()](#method.count) does, but reports
+    /// the lines on which matching failed to `on_error` as
+    /// [search_with_errors()](#method.search_with_errors) does.
+    pub fn count_with_errors<R: BufRead, E: FnMut(u64, ExecError) -> bool>(&self, re: &Pcre, reader: R, on_error: E) -> io::Result<Summary> {
+        let counter = Searcher {
+            before_context: 0,
+            after_context: 0,
+            ..self.clone()
+        };
+        with_buffers(|buffers| counter.search_with(re, reader, false, |_| true, on_error, buffers))
+    }
+
+    /// Returns whether `text` is a matching line, storing the match spans in `spans` if
+    /// `with_spans` is set.
+    fn match_line(&self, re: &Pcre, text: &str, with_spans: bool, spans: &mut Vec<Range<usize>>, data: &mut MatchData) -> Result<bool, ExecError> {
+        let mut matches = re.matches_into(text, data);
+        if !with_spans || self.invert {
+            return Ok(matches.try_next_match()?.is_some() != self.invert);
+        }
+        while let Some(m) = matches.try_next_match()? {
+            spans.push(m.group_start(0)..m.group_end(0));
+        }
+        Ok(!spans.is_empty())
+    }
+
+    /// Searches `reader`, computing the match spans of matching lines if `spans` is set.
+    #[allow(clippy::too_many_arguments)]
+    fn search_with<R, F, E>(&self, re: &Pcre, mut reader: R, spans: bool, mut f: F, mut on_error: E, buffers: &mut Buffers) -> io::Result<Summary>
+        where R: BufRead, F: FnMut(&Line) -> bool, E: FnMut(u64, ExecError) -> bool {
+        let mut summary = Summary::default();
+        let mut before: VecDeque<(u64, u64, String)> = VecDeque::with_capacity(self.before_context);
+        let mut after_remaining = 0;
+        let mut done = self.max_count == Some(0);
+        let mut number = 0;
+        let mut offset = 0;
+
+        while !done || after_remaining > 0 {
+            buffers.line.clear();
+            let len = reader.read_until(b'\n', &mut buffers.line)?;
+            if len == 0 {
+                break;
+            }
+            number += 1;
+            let line_offset = offset;
+            offset += len as u64;
+
+            let mut bytes = &buffers.line[..];
+            if bytes.last() == Some(&b'\n') {
+                bytes = &bytes[..bytes.len() - 1];
+            }
+            let text = match str::from_utf8(bytes) {
+                Ok(text) if !self.binary_detection || !bytes.contains(&0) => Cow::Borrowed(text),
+                Err(_) if !self.binary_detection => String::from_utf8_lossy(bytes),
+                _ => {
+                    summary.binary_offset = Some(line_offset);
+                    break;
+                }
+            };
+
+            buffers.spans.clear();
+            let is_match = !done && match self.match_line(re, &text, spans, &mut buffers.spans, &mut buffers.data) {
+                Ok(is_match) => is_match,
+                Err(err) => {
+                    if !on_error(number, err) {
+                        return Ok(summary);
+                    }
+                    false
+                }
+            };
+
+            if is_match {
+                summary.matched_lines += 1;
+                done = self.max_count == Some(summary.matched_lines);
+                after_remaining = self.after_context;
+                while let Some((number, offset, text)) = before.pop_front() {
+                    let keep_going = f(&Line { kind: LineKind::Context, number, offset, text: &text, spans: &[] });
+                    buffers.spare.push(text);
+                    if !keep_going {
+                        return Ok(summary);
+                    }
+                }
+                if !f(&Line { kind: LineKind::Match, number, offset: line_offset, text: &text, spans: &buffers.spans }) {
+                    return Ok(summary);
+                }
+            } else if after_remaining > 0 {
+                after_remaining -= 1;
+                if !f(&Line { kind: LineKind::Context, number, offset: line_offset, text: &text, spans: &[] }) {
+                    return Ok(summary);
+                }
+            } else if self.before_context > 0 {
+                let mut saved = if before.len() == self.before_context {
+                    before.pop_front().map(|(_, _, text)| text)
+                } else {
+                    buffers.spare.pop()
+                }.unwrap_or_default();
+                saved.clear();
+                saved.push_str(&text);
+                before.push_back((number, line_offset, saved));
+            }
+        }
+
+        buffers.spare.extend(before.into_iter().map(|(_, _, text)| text));
+        Ok(summary)
+    }
+}
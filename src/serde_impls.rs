@@ -0,0 +1,165 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `serde` support, enabled by the `serde` feature.
+//!
+//! A `Pcre` is serialized as its pattern and its compile options as a flag string (see the
+//! [`flags`](../flags/index.html) module), for example `{"pattern": "^a+$", "flags": "i"}`,
+//! and deserialized by compiling it again. A bare string deserializes as a pattern with no
+//! options. Character tables are not serialized.
+//!
+//! A `Match` is serialized as the span of the whole match, each capture group, and the
+//! MARK:
+//!
+//! ```text
+//! {
+//!     "start": 0,
+//!     "end": 7,
+//!     "groups": [
+//!         {"name": null, "start": 0, "end": 7, "text": "2015-06"},
+//!         {"name": "year", "start": 0, "end": 4, "text": "2015"},
+//!         null
+//!     ],
+//!     "mark": null
+//! }
+//! ```
+//!
+//! Groups which did not participate in the match are `null`.
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, SerializeStruct, Serializer};
+use std::borrow::{Cow};
+use std::fmt;
+use std::str;
+use std::string::{String};
+
+use {flags, Match, Pcre};
+
+impl Serialize for Pcre {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Pcre", 2)?;
+        state.serialize_field("pattern", self.pattern())?;
+        state.serialize_field("flags", &flags::format_flags(&self.compile_options()))?;
+        state.end()
+    }
+}
+
+struct PcreVisitor;
+
+fn compile<E: de::Error>(pattern: &str, flag_string: &str) -> Result<Pcre, E> {
+    let options = flags::parse_flags(flag_string).map_err(E::custom)?;
+    Pcre::compile_with_options(pattern, &options).map_err(E::custom)
+}
+
+impl<'de> Visitor<'de> for PcreVisitor {
+    type Value = Pcre;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a pattern, or a map with a pattern and flags")
+    }
+
+    fn visit_str<E: de::Error>(self, pattern: &str) -> Result<Pcre, E> {
+        compile(pattern, "")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Pcre, A::Error> {
+        let mut pattern: Option<String> = None;
+        let mut flag_string: Option<String> = None;
+        while let Some(key) = map.next_key::<Cow<'de, str>>()? {
+            match &*key {
+                "pattern" => {
+                    if pattern.is_some() {
+                        return Err(de::Error::duplicate_field("pattern"));
+                    }
+                    pattern = Some(map.next_value()?);
+                },
+                "flags" => {
+                    if flag_string.is_some() {
+                        return Err(de::Error::duplicate_field("flags"));
+                    }
+                    flag_string = Some(map.next_value()?);
+                },
+                key => return Err(de::Error::unknown_field(key, &["pattern", "flags"]))
+            }
+        }
+        let pattern = pattern.ok_or_else(|| de::Error::missing_field("pattern"))?;
+        compile(&pattern, flag_string.as_ref().map_or("", |f| &f[..]))
+    }
+}
+
+impl<'de> Deserialize<'de> for Pcre {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Pcre, D::Error> {
+        deserializer.deserialize_any(PcreVisitor)
+    }
+}
+
+/// A capture group of a match, for serialization.
+struct Group<'m, 'a: 'm> {
+
+    m: &'m Match<'a>,
+
+    n: usize,
+
+    name: Option<&'m str>
+
+}
+
+impl<'m, 'a> Serialize for Group<'m, 'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // A group which ends with `\C` can end within a character.
+        let bytes = self.m.group_bytes(self.n);
+        let text = str::from_utf8(bytes).map(Cow::Borrowed).unwrap_or_else(|_| String::from_utf8_lossy(bytes));
+        let mut state = serializer.serialize_struct("Group", 4)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("start", &self.m.group_start(self.n))?;
+        state.serialize_field("end", &self.m.group_end(self.n))?;
+        state.serialize_field("text", &text)?;
+        state.end()
+    }
+}
+
+/// The capture groups of a match, for serialization.
+struct Groups<'m, 'a: 'm> {
+
+    m: &'m Match<'a>
+
+}
+
+impl<'m, 'a> Serialize for Groups<'m, 'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let m = self.m;
+        let group_count = m.partial_ovector.len() / 2;
+        let mut names: Vec<Option<&str>> = vec![None; group_count];
+        for (name, ns) in m.name_table.iter() {
+            for &n in ns.iter() {
+                names[n] = Some(name);
+            }
+        }
+
+        let mut seq = serializer.serialize_seq(Some(group_count))?;
+        for (n, name) in names.into_iter().enumerate() {
+            if m.is_set(n) {
+                seq.serialize_element(&Some(Group { m, n, name }))?;
+            } else {
+                seq.serialize_element(&None::<Group>)?;
+            }
+        }
+        seq.end()
+    }
+}
+
+impl<'a> Serialize for Match<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Match", 4)?;
+        state.serialize_field("start", &self.group_start(0))?;
+        state.serialize_field("end", &self.group_end(0))?;
+        state.serialize_field("groups", &Groups { m: self })?;
+        state.serialize_field("mark", &self.mark())?;
+        state.end()
+    }
+}
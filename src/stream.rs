@@ -0,0 +1,244 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Matching over an `AsyncRead`, enabled by the `async` feature.
+//!
+//! A [MatchStream](struct.MatchStream.html) is a `futures::Stream` of the matches of a
+//! regular expression in the input of an `AsyncRead`, which are the matches that
+//! `Pcre::matches()` would find in the whole input. The input is read in chunks, and a
+//! match which straddles chunks is found by continuing a partial match when more input
+//! has been read, so only the text which a match could still start in is kept in memory.
+//!
+//! Input is only read when the stream is polled, so a consumer which is slow to take the
+//! matches slows down the reading. Tokio's `AsyncRead` can be adapted with the `compat()`
+//! method of `tokio_util::compat::TokioAsyncReadCompatExt`.
+//!
+//! # Example
+//! ```
+//! extern crate futures;
+//! extern crate pcre;
+//!
+//! use futures::executor::block_on;
+//! use futures::stream::TryStreamExt;
+//! use pcre::Pcre;
+//! use pcre::stream::MatchStream;
+//!
+//! # fn main() {
+//! let re = Pcre::compile(r"id=(\d+)").unwrap();
+//! let input: &[u8] = b"id=1 id=22 id=333";
+//! let ids: Vec<(u64, String)> = block_on(MatchStream::new(&re, input)
+//!     .map_ok(|m| (m.group_start(1), m.group(1).to_string()))
+//!     .try_collect()).unwrap();
+//! assert_eq!(ids, vec![(3, "1".to_string()), (8, "22".to_string()), (14, "333".to_string())]);
+//! # }
+//! ```
+
+use futures::io::{AsyncRead};
+use futures::stream::{FusedStream, Stream};
+use libc::{c_int};
+use std::borrow::{Borrow};
+use std::io;
+use std::pin::{Pin};
+use std::task::{Context, Poll};
+
+use chunked::{ChunkedMatcher, Step};
+use {Match, OwnedMatch, Pcre};
+
+/// The number of chunks which are searched in one call to `poll_next()` before the stream
+/// yields to the executor, when the reader always has input ready but none of it matches.
+const CHUNKS_PER_POLL: usize = 16;
+
+/// A stream of the matches of a regular expression in the input of an `AsyncRead`.
+///
+/// `P` is the compiled pattern, which can be borrowed (`&Pcre`) or owned (`Pcre` or
+/// `Arc<Pcre>`) so that the stream can be spawned as a task.
+///
+/// The stream yields an error and ends if reading fails or the input is not valid UTF-8.
+pub struct MatchStream<P, R> {
+
+    re: P,
+
+    reader: R,
+
+    matcher: ChunkedMatcher,
+
+    done: bool
+
+}
+
+/// A match which was found by a [MatchStream](struct.MatchStream.html).
+///
+/// It owns a copy of the text of the match, so its offsets are offsets in the whole input
+/// rather than in a subject string.
+#[derive(Clone, Debug)]
+pub struct StreamMatch {
+
+    /// The offset in the input of the start of the subject of `m`.
+    offset: u64,
+
+    /// The match in the text from the start of the first capture group to the end of the
+    /// last one.
+    m: OwnedMatch
+
+}
+
+impl<P: Borrow<Pcre>, R: AsyncRead + Unpin> MatchStream<P, R> {
+    /// Creates a stream of the matches of `re` in the input of `reader`.
+    pub fn new(re: P, reader: R) -> MatchStream<P, R> {
+        let matcher = ChunkedMatcher::new(re.borrow());
+        MatchStream {
+            re,
+            reader,
+            matcher,
+            done: false
+        }
+    }
+}
+
+// The pattern is never pinned.
+impl<P, R: Unpin> Unpin for MatchStream<P, R> {}
+
+impl<P: Borrow<Pcre>, R: AsyncRead + Unpin> Stream for MatchStream<P, R> {
+    type Item = io::Result<StreamMatch>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<io::Result<StreamMatch>>> {
+        let this = self.get_mut();
+        let mut chunks = 0;
+        while !this.done {
+            if this.matcher.needs_input() {
+                if chunks == CHUNKS_PER_POLL {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                let result = match Pin::new(&mut this.reader).poll_read(cx, this.matcher.spare()) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(ref err)) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Poll::Ready(result) => result.and_then(|n| this.matcher.filled(n))
+                };
+                if let Err(err) = result {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+                chunks += 1;
+            }
+
+            match this.matcher.next_step(this.re.borrow()) {
+                Step::Match { m, offset, .. } => return Poll::Ready(Some(Ok(StreamMatch::new(&m, offset)))),
+                Step::NeedInput { .. } => {},
+                Step::Done { .. } => this.done = true
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+impl<P: Borrow<Pcre>, R: AsyncRead + Unpin> FusedStream for MatchStream<P, R> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl StreamMatch {
+    /// Copies `m`, whose subject starts at `offset` in the input.
+    fn new(m: &Match, offset: u64) -> StreamMatch {
+        let set = || m.partial_ovector.chunks(2).filter(|group| group[0] >= 0);
+        let mut start = set().map(|group| group[0] as usize).min().unwrap_or(0);
+        let mut end = set().map(|group| group[1] as usize).max().unwrap_or(0);
+        // A group can start or end inside a character if the pattern uses `\C`.
+        while !m.subject.is_char_boundary(start) {
+            start -= 1;
+        }
+        while !m.subject.is_char_boundary(end) {
+            end += 1;
+        }
+        let text = Match {
+            subject: &m.subject[start..end],
+            partial_ovector: m.partial_ovector.iter().map(|&i| if i < 0 { i } else { i - start as c_int }).collect(),
+            string_count_: m.string_count_,
+            mark_: m.mark_.clone(),
+            name_table: m.name_table.clone()
+        };
+        StreamMatch {
+            offset: offset + start as u64,
+            m: text.into_owned()
+        }
+    }
+
+    /// Returns the offset in the input of the start of capture group `n`.
+    ///
+    /// If the capture group is present in the pattern but wasn't captured then the start of
+    /// it will be `u64::MAX`.
+    pub fn group_start(&self, n: usize) -> u64 {
+        self.input_offset(self.m.group_start(n))
+    }
+
+    /// Returns the offset in the input of the end of capture group `n`.
+    ///
+    /// If the capture group is present in the pattern but wasn't captured then the end of
+    /// it will be `u64::MAX`.
+    pub fn group_end(&self, n: usize) -> u64 {
+        self.input_offset(self.m.group_end(n))
+    }
+
+    /// Converts an offset in the text of the match, or `usize::MAX`, to one in the input.
+    fn input_offset(&self, i: usize) -> u64 {
+        match i {
+            usize::MAX => u64::MAX,
+            i => self.offset + i as u64
+        }
+    }
+
+    /// Returns the length of the substring for capture group `n`.
+    pub fn group_len(&self, n: usize) -> usize {
+        self.m.group_len(n)
+    }
+
+    /// Returns the substring for capture group `n`.
+    ///
+    /// # Panics
+    /// If the capture group did not participate in the match, or if the substring does not
+    /// start and end on UTF-8 character boundaries. The latter can happen when the pattern
+    /// uses `\C` to match a single byte; use [group_bytes()](#method.group_bytes) instead.
+    pub fn group(&self, n: usize) -> &str {
+        self.m.group(n)
+    }
+
+    /// Returns the substring for capture group `n` as a byte slice.
+    ///
+    /// # Panics
+    /// If the capture group did not participate in the match.
+    pub fn group_bytes(&self, n: usize) -> &[u8] {
+        self.m.group_bytes(n)
+    }
+
+    /// Returns the substring for the capture group named `name`.
+    ///
+    /// If several groups have the name, the first one which participated in the match is
+    /// used.
+    ///
+    /// # Return value
+    /// `None` if there is no group named `name` or it did not participate in the match.
+    pub fn name(&self, name: &str) -> Option<&str> {
+        self.m.name(name)
+    }
+
+    /// Returns the number of substrings captured.
+    pub fn string_count(&self) -> usize {
+        self.m.string_count()
+    }
+
+    /// Returns the MARK name that was set by this match, if any.
+    pub fn mark(&self) -> Option<&str> {
+        self.m.mark()
+    }
+
+    /// Returns the MARK name that was set by this match, if any.
+    pub fn mark_bytes(&self) -> Option<&[u8]> {
+        self.m.mark_bytes()
+    }
+}
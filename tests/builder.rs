@@ -0,0 +1,89 @@
+extern crate pcre;
+
+use pcre::{Bsr, BuildError, ExecError, Newline, Pcre, PcreBuilder, Tables};
+use std::sync::{Arc};
+
+#[test]
+fn test_builder_options() {
+    let re = PcreBuilder::new("^a.c$").caseless(true).multiline(true).dotall(true).build().unwrap();
+    assert!(re.is_match("x\nA\nC\ny"));
+
+    let re = Pcre::builder("a b # comment").extended(true).build().unwrap();
+    assert_eq!(re.exec("xaby").unwrap().group(0), "ab");
+
+    let re = Pcre::builder("a+").ungreedy(true).build().unwrap();
+    assert_eq!(re.exec("aaa").unwrap().group(0), "a");
+
+    // Options can be turned off again.
+    let re = Pcre::builder("A").caseless(true).caseless(false).build().unwrap();
+    assert!(!re.is_match("a"));
+}
+
+#[test]
+fn test_builder_newline() {
+    let re = Pcre::builder("^b$").multiline(true).newline(Newline::Crlf).build().unwrap();
+    assert!(re.is_match("a\r\nb\r\nc"));
+    assert!(!re.is_match("a\nb\nc"));
+
+    // The last choice wins.
+    let re = Pcre::builder("^b$").multiline(true).newline(Newline::Crlf).newline(Newline::Lf).build().unwrap();
+    assert!(re.is_match("a\nb\nc"));
+
+    let re = Pcre::builder("a\\Rb").bsr(Bsr::AnyCrlf).build().unwrap();
+    assert!(re.is_match("a\rb"));
+    assert!(!re.is_match("a\u{2028}b"));
+    let re = Pcre::builder("a\\Rb").bsr(Bsr::Unicode).build().unwrap();
+    assert!(re.is_match("a\u{2028}b"));
+}
+
+#[test]
+fn test_builder_study() {
+    let mut re = Pcre::builder("abc").jit(true).build().unwrap();
+    assert!(re.extra().is_some());
+    assert!(re.is_match("xabcx"));
+
+    let mut re = Pcre::builder("abc").build().unwrap();
+    assert!(re.extra().is_none());
+}
+
+#[test]
+fn test_builder_match_limit() {
+    let subject = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaab";
+    let mut re = Pcre::builder("^(a+)+$").match_limit(1000).build().unwrap();
+    assert_eq!(re.extra().unwrap().match_limit(), Some(1000));
+    assert_eq!(re.exec_from_with_interrupt(subject, 0, &Default::default(), None, None).err(), Some(ExecError::MatchLimit));
+    assert_eq!(re.exec("aaa").unwrap().group(0), "aaa");
+
+    let mut re = Pcre::builder("^(a+)+$").match_limit_recursion(10).build().unwrap();
+    {
+        let extra = re.extra().unwrap();
+        assert_eq!(extra.match_limit(), None);
+        assert_eq!(extra.match_limit_recursion(), Some(10));
+    }
+    assert_eq!(re.exec_from_with_interrupt(subject, 0, &Default::default(), None, None).err(), Some(ExecError::MatchLimit));
+}
+
+#[test]
+#[should_panic(expected = "limit was exceeded")]
+fn test_builder_match_limit_exec() {
+    let re = Pcre::builder("^(a+)+$").match_limit(1000).build().unwrap();
+    re.exec("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaab");
+}
+
+#[test]
+fn test_builder_tables() {
+    let tables = Arc::new(Tables::from_current_locale());
+    let re1 = Pcre::builder("\\w+").tables(tables.clone()).build().unwrap();
+    assert!(Arc::ptr_eq(re1.tables().unwrap(), &tables));
+    let re2 = Pcre::builder("[a-z]+").caseless(true).tables(tables).build().unwrap();
+    assert_eq!(re1.exec("  foo ").unwrap().group(0), "foo");
+    assert_eq!(re2.exec("  FOO ").unwrap().group(0), "FOO");
+}
+
+#[test]
+fn test_builder_error() {
+    match Pcre::builder("(abc").build() {
+        Err(BuildError::Compile(err)) => assert_eq!(err.offset(), 4),
+        _ => panic!("expected a compilation error")
+    }
+}
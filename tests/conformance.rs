@@ -0,0 +1,605 @@
+// Copyright 2015 The rust-pcre authors.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Runs the test cases which ship with libpcre, in `testdata/testinput*` of the bundled
+// tarball, through the Rust API and compares the results with `testdata/testoutput*`.
+//
+// The input files are written for libpcre's `pcretest` program: a pattern with modifiers
+// such as `/(a+)b/ig`, followed by subject lines with escapes such as `\x{100}` and `\P`.
+// The output files echo the input with the results after each line. Test cases which use
+// a modifier or escape that has no equivalent in the bindings are skipped, as are the
+// cases in `SKIP` and `SKIP_OUTPUT`.
+//
+// The other files are for DFA matching with `pcre_dfa_exec()`, locales, the output of
+// JIT compilation, saved patterns, or the 16-bit and 32-bit libraries, none of which the
+// bindings expose.
+//
+// The expected output is for the bundled libpcre 8.39, so the tests are ignored by
+// default. Run them against the bundled copy with
+// `LIBPCRE_SYS_BUNDLED=1 cargo test --test conformance -- --ignored`.
+
+extern crate bzip2;
+extern crate enum_set;
+extern crate pcre;
+extern crate tar;
+
+use bzip2::read::{BzDecoder};
+use enum_set::{EnumSet};
+use pcre::{ExecOption, Match, PartialResult, Pcre, StudyOption, pcre_version};
+use pcre::flags;
+use std::collections::{BTreeMap};
+use std::fs::{File};
+use std::io::{Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::str;
+use std::string::{String};
+use std::vec::{Vec};
+use tar::{Archive};
+
+const BUNDLED_PCRE_VERSION: &str = "8.39";
+
+/// Test cases which are skipped, as the file, the line of the pattern or subject, and the
+/// reason.
+const SKIP: &[(&str, usize, &str)] = &[
+    ("testinput1", 4309, "\\C is allowed in lookbehind assertions only without UTF-8"),
+    ("testinput2", 1983, "flags::parse_flags() keeps the last of several newline flags"),
+    ("testinput5", 650, "flags::parse_flags() keeps the last of several \\R flags"),
+    ("testinput14", 335, "\\u.... is limited to 8 bits only without UTF-8"),
+    ("testinput14", 337, "\\u.... is limited to 8 bits only without UTF-8"),
+    ("testinput15", 179, "Match::group() cannot return part of a character")
+];
+
+/// Test cases which are skipped because their expected output starts with one of these
+/// prefixes, with the reason.
+const SKIP_OUTPUT: &[(&str, &str)] = &[
+    ("No match, mark = ", "the MARK of a failed match is not exposed"),
+    ("Partial match, mark=", "the MARK of a partial match is not exposed"),
+    ("Partial match at offset ", "PCRE_INFO_MAXLOOKBEHIND is not exposed"),
+    ("--->", "callouts are not exposed"),
+    ("Error -", "libpcre errors other than limits panic"),
+    ("Matched, but too many substrings", "pcretest's ovector is smaller than the bindings' one"),
+    ("Start of matched string is beyond its end", "\\K in lookaheads makes groups which end before they start")
+];
+
+/// The pcretest modifiers which are compile options, as understood by `flags::parse_flags()`.
+const FLAG_MODIFIERS: &str = "imsxAEXUNfJW";
+
+/// A compiled pattern and its pcretest modifiers.
+struct Test {
+
+    re: Pcre,
+
+    /// Whether non-printable characters are shown as UTF-8 (`/8`).
+    utf: bool,
+
+    global: bool,
+
+    show_mark: bool,
+
+    /// Whether the rest of the subject is shown after the match (`/+`) or after each group
+    /// (`/++`).
+    show_rest: usize
+
+}
+
+/// A line of a pcretest input file with the output lines which follow its echo in the
+/// output file.
+struct Line<'a> {
+
+    number: usize,
+
+    text: &'a [u8],
+
+    expected: Vec<&'a [u8]>
+
+}
+
+#[derive(Default)]
+struct Results {
+
+    passed: usize,
+
+    /// The number of skipped test cases by reason.
+    skipped: BTreeMap<String, usize>,
+
+    failures: Vec<String>
+
+}
+
+/// Reads the file `name` from the `testdata` directory of the bundled tarball.
+fn read_testdata(name: &str) -> Vec<u8> {
+    let tarball = format!("{}/libpcre-sys/ext/pcre-{}.tar.bz2", env!("CARGO_MANIFEST_DIR"), BUNDLED_PCRE_VERSION);
+    let mut archive = Archive::new(BzDecoder::new(File::open(tarball).unwrap()));
+    let path = format!("pcre-{}/testdata/{}", BUNDLED_PCRE_VERSION, name);
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        if entry.path().unwrap().to_str() == Some(&path[..]) {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            return data;
+        }
+    }
+    panic!("{} is not in the tarball", path);
+}
+
+fn trim_start(line: &[u8]) -> &[u8] {
+    let start = line.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(line.len());
+    &line[start..]
+}
+
+fn trim_end(line: &[u8]) -> &[u8] {
+    let end = line.iter().rposition(|b| !b.is_ascii_whitespace()).map_or(0, |i| i + 1);
+    &line[..end]
+}
+
+fn is_blank(line: &[u8]) -> bool {
+    trim_end(line).is_empty()
+}
+
+/// Pairs the lines of `input` with their results in `output`.
+///
+/// pcretest does not echo the subject lines of a pattern which failed to compile, so the
+/// results of a line end at the echo of any line up to the next blank line. Some results
+/// contain blank lines, so a blank line only counts as the echo if the line after it is
+/// the echo of the line after the blank input line.
+fn align<'a>(input: &'a [u8], output: &'a [u8]) -> Vec<Line<'a>> {
+    let raw: Vec<&[u8]> = input.split(|&b| b == b'\n').collect();
+    let input: Vec<&[u8]> = raw.iter().map(|&line| trim_end(line)).collect();
+    let output: Vec<&[u8]> = output.split(|&b| b == b'\n').map(trim_end).collect();
+    let mut lines = Vec::with_capacity(input.len());
+    let mut j = 0;
+    for (i, &text) in input.iter().enumerate() {
+        let mut expected = Vec::new();
+        if output.get(j) == Some(&text) {
+            j += 1;
+            let blank = (i + 1..input.len()).find(|&k| input[k].is_empty()).unwrap_or(input.len() - 1);
+            let is_echo = |j: usize| {
+                if output[j].is_empty() {
+                    input[(i + 1)..=blank].contains(&output[j]) && output.get(j + 1).is_none_or(|&o| input.get(blank + 1).is_none_or(|&next| o == next))
+                } else {
+                    input[(i + 1).min(blank)..=blank].contains(&output[j])
+                }
+            };
+            while j < output.len() && !is_echo(j) {
+                expected.push(output[j]);
+                j += 1;
+            }
+        }
+        lines.push(Line { number: i + 1, text: raw[i], expected });
+    }
+    lines
+}
+
+/// Formats `text` like pcretest, escaping characters which are not printable ASCII.
+fn escape(text: &str, utf: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if (' '..='~').contains(&c) {
+            escaped.push(c);
+        } else if utf || c as u32 > 0xff {
+            escaped.push_str(&format!("\\x{{{:02x}}}", c as u32));
+        } else {
+            escaped.push_str(&format!("\\x{:02x}", c as u32));
+        }
+    }
+    escaped
+}
+
+/// Parses the modifiers after a pattern and compiles it.
+///
+/// # Return value
+/// The test, or the output of a failed compilation, or `Err` with the reason that the
+/// pattern is not supported.
+fn compile(pattern: &str, modifiers: &str) -> Result<Result<Test, String>, String> {
+    let mut utf = false;
+    let mut global = false;
+    let mut show_mark = false;
+    let mut show_rest = 0;
+    let mut study = None;
+    let mut compile_flags = String::new();
+    let mut chars = modifiers.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '8' => utf = true,
+            'g' => global = true,
+            'K' => show_mark = true,
+            '+' => show_rest += 1,
+            'S' => {
+                let mut options = EnumSet::new();
+                if chars.peek() == Some(&'+') {
+                    chars.next();
+                    options.insert(StudyOption::StudyJitCompile);
+                    options.insert(StudyOption::StudyJitPartialSoftCompile);
+                    options.insert(StudyOption::StudyJitPartialHardCompile);
+                }
+                if let Some(&c) = chars.peek().filter(|&&c| c == '+' || c == '-') {
+                    return Err(format!("modifier /S{}", c));
+                }
+                study = Some(options);
+            },
+            '<' => {
+                compile_flags.push(c);
+                for c in chars.by_ref() {
+                    compile_flags.push(c);
+                    if c == '>' {
+                        break;
+                    }
+                }
+            },
+            c if FLAG_MODIFIERS.contains(c) => compile_flags.push(c),
+            c if c.is_whitespace() => {},
+            c => return Err(format!("modifier /{}", c))
+        }
+    }
+    let options = flags::parse_flags(&compile_flags).map_err(|err| format!("modifier: {}", err))?;
+
+    let mut re = match Pcre::compile_with_options(pattern, &options) {
+        Err(err) => return Ok(Err(format!("Failed: {} at offset {}", err.message().unwrap_or_default(), err.offset()))),
+        Ok(re) => re
+    };
+    if let Some(options) = study {
+        re.study_with_options(&options);
+    }
+    Ok(Ok(Test { re, utf, global, show_mark, show_rest }))
+}
+
+/// Parses the digits at the start of `chars` in base `radix`, consuming at most `max`.
+fn parse_digits(chars: &mut ::std::iter::Peekable<::std::str::Chars>, radix: u32, max: usize, value: u32) -> u32 {
+    let mut value = value;
+    for _ in 0..max {
+        match chars.peek().and_then(|c| c.to_digit(radix)) {
+            None => break,
+            Some(digit) => {
+                value = value * radix + digit;
+                chars.next();
+            }
+        }
+    }
+    value
+}
+
+/// A subject and how to match it.
+struct Subject {
+
+    subject: String,
+
+    start_offset: usize,
+
+    options: EnumSet<ExecOption>
+
+}
+
+/// Decodes the escapes of a subject line.
+///
+/// # Return value
+/// The subject, or `Err` with the reason that the subject is not supported.
+fn parse_subject(line: &str, utf: bool) -> Result<Subject, String> {
+    let mut subject = String::new();
+    let mut start_offset = 0;
+    let mut options = EnumSet::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            subject.push(c);
+            continue;
+        }
+        let code = match chars.next() {
+            None => return Err("trailing \\ in a subject".to_string()),
+            Some('a') => 0x07,
+            Some('e') => 0x1b,
+            Some('f') => 0x0c,
+            Some('n') => 0x0a,
+            Some('r') => 0x0d,
+            Some('t') => 0x09,
+            Some('v') => 0x0b,
+            Some(c @ '0'..='7') => parse_digits(&mut chars, 8, 2, c.to_digit(8).unwrap()),
+            Some('x') if chars.peek() == Some(&'{') => {
+                chars.next();
+                let code = parse_digits(&mut chars, 16, 8, 0);
+                if chars.next() != Some('}') {
+                    return Err("\\x{ without }".to_string());
+                }
+                code
+            },
+            Some('x') => parse_digits(&mut chars, 16, 2, 0),
+            Some('A') => {
+                options.insert(ExecOption::ExecAnchored);
+                continue;
+            },
+            Some('B') => {
+                options.insert(ExecOption::ExecNotBol);
+                continue;
+            },
+            Some('Z') => {
+                options.insert(ExecOption::ExecNotEol);
+                continue;
+            },
+            Some('Y') => {
+                options.insert(ExecOption::ExecNoStartOptimise);
+                continue;
+            },
+            Some('N') => {
+                if options.remove(&ExecOption::ExecNotEmpty) {
+                    options.insert(ExecOption::ExecNotEmptyAtStart);
+                } else {
+                    options.insert(ExecOption::ExecNotEmpty);
+                }
+                continue;
+            },
+            Some('P') => {
+                if options.remove(&ExecOption::ExecPartialSoft) {
+                    options.insert(ExecOption::ExecPartialHard);
+                } else {
+                    options.insert(ExecOption::ExecPartialSoft);
+                }
+                continue;
+            },
+            Some('>') => {
+                start_offset = parse_digits(&mut chars, 10, 10, 0) as usize;
+                continue;
+            },
+            Some('<') => {
+                let name: String = chars.by_ref().take_while(|&c| c != '>').collect();
+                options.insert(match &name[..] {
+                    "cr" => ExecOption::ExecNewlineCR,
+                    "lf" => ExecOption::ExecNewlineLF,
+                    "crlf" => ExecOption::ExecNewlineCRLF,
+                    "any" => ExecOption::ExecNewlineAny,
+                    "anycrlf" => ExecOption::ExecNewlineAnyCRLF,
+                    "bsr_anycrlf" => ExecOption::ExecBsrAnyCRLF,
+                    "bsr_unicode" => ExecOption::ExecBsrUnicode,
+                    _ => return Err(format!("escape \\<{}>", name))
+                });
+                continue;
+            },
+            Some(c) if c.is_ascii_alphabetic() => return Err(format!("escape \\{}", c)),
+            Some(c) => {
+                subject.push(c);
+                continue;
+            }
+        };
+        // Without `/8`, pcretest inserts a byte, which is a character only if it is ASCII.
+        if !utf && code > 0x7f {
+            return Err("non-ASCII byte in a non-UTF-8 subject".to_string());
+        }
+        match ::std::char::from_u32(code) {
+            None => return Err("invalid character in a subject".to_string()),
+            Some(c) => subject.push(c)
+        }
+    }
+    Ok(Subject { subject, start_offset, options })
+}
+
+fn print_match(test: &Test, subject: &str, m: &Match, out: &mut Vec<String>) {
+    for i in 0..m.string_count() {
+        if m.group_start(i) == usize::MAX {
+            out.push(format!("{:2}: <unset>", i));
+        } else {
+            out.push(format!("{:2}: {}", i, escape(m.group(i), test.utf)));
+        }
+        if (i == 0 && test.show_rest > 0) || (test.show_rest > 1 && m.group_start(i) != usize::MAX) {
+            out.push(format!("{:2}+ {}", i, escape(&subject[m.group_end(i)..], test.utf)));
+        }
+    }
+    if test.show_mark {
+        if let Some(mark) = m.mark() {
+            out.push(format!("MK: {}", escape(mark, test.utf)));
+        }
+    }
+}
+
+/// Matches `test` against a subject, returning pcretest's output.
+fn run_subject(test: &Test, subject: &Subject) -> Vec<String> {
+    let Subject { ref subject, start_offset, ref options } = *subject;
+    let mut out = Vec::new();
+    if options.contains(&ExecOption::ExecPartialSoft) || options.contains(&ExecOption::ExecPartialHard) {
+        match test.re.exec_partial(subject, start_offset, options) {
+            PartialResult::Match(m) => print_match(test, subject, &m, &mut out),
+            PartialResult::Partial { start, .. } => out.push(format!("Partial match: {}", escape(&subject[start..], test.utf))),
+            PartialResult::NoMatch => out.push("No match".to_string())
+        }
+    } else if test.global {
+        for m in test.re.matches_with_options(subject, options) {
+            print_match(test, subject, &m, &mut out);
+        }
+    } else if let Some(m) = test.re.exec_from_with_options(subject, start_offset, options) {
+        print_match(test, subject, &m, &mut out);
+    }
+    if out.is_empty() {
+        out.push("No match".to_string());
+    }
+    out
+}
+
+impl Results {
+    fn skip(&mut self, reason: &str) {
+        *self.skipped.entry(reason.to_string()).or_insert(0) += 1;
+    }
+
+    /// Compares the output of a test case with the expected output.
+    fn check(&mut self, file: &str, line: &Line, actual: &[String]) {
+        let expected: Vec<String> = line.expected.iter().map(|&e| String::from_utf8_lossy(trim_end(e)).into_owned()).collect();
+        let actual: Vec<String> = actual.iter().map(|a| a.trim_end().to_string()).collect();
+        if expected == actual {
+            self.passed += 1;
+        } else {
+            self.failures.push(format!("{}:{}: {}\n  expected: {:?}\n  actual:   {:?}", file, line.number, String::from_utf8_lossy(line.text).trim(), expected, actual));
+        }
+    }
+}
+
+fn skip_reason(file: &str, line: &Line) -> Option<&'static str> {
+    SKIP.iter().find(|&&(f, n, _)| f == file && n == line.number).map(|&(_, _, reason)| reason)
+        .or_else(|| SKIP_OUTPUT.iter().find(|&&(prefix, _)| line.expected.iter().any(|e| e.starts_with(prefix.as_bytes()))).map(|&(_, reason)| reason))
+}
+
+/// Runs the test cases of `testinputN`.
+fn run_testdata(n: usize) {
+    assert!(pcre_version().starts_with(BUNDLED_PCRE_VERSION),
+            "the expected output is for libpcre {}, not {}; build the bundled copy with LIBPCRE_SYS_BUNDLED=1",
+            BUNDLED_PCRE_VERSION, pcre_version());
+
+    let file = format!("testinput{}", n);
+    let input = read_testdata(&file);
+    let output = read_testdata(&format!("testoutput{}", n));
+    let lines = align(&input, &output);
+    let mut results = Results::default();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let text = trim_end(lines[i].text);
+        // Blank lines separate the blocks, and `<` starts a directive for pcretest.
+        if text.is_empty() || text.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'<') {
+            i += 1;
+            continue;
+        }
+
+        // Read the pattern, which can span several lines.
+        let start = i;
+        let mut source = Vec::new();
+        let delimiter = text.iter().cloned().find(|b| !b.is_ascii_whitespace()).unwrap();
+        let mut end = None;
+        while end.is_none() && i < lines.len() {
+            if i > start {
+                source.push(b'\n');
+            }
+            let from = source.len() + if i == start { 1 } else { 0 };
+            source.extend_from_slice(if i == start { trim_start(lines[i].text) } else { lines[i].text });
+            let mut j = from;
+            while j < source.len() {
+                if source[j] == b'\\' {
+                    j += 1;
+                } else if source[j] == delimiter {
+                    end = Some(j);
+                    break;
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+        let pattern_line = &lines[i - 1];
+
+        let compiled = match (end, str::from_utf8(&source)) {
+            (Some(end), Ok(source)) => {
+                let source = &source[1..];
+                let end = end - 1;
+                let pattern = &source[..end];
+                let modifiers = source[(end + 1)..].trim();
+                match skip_reason(&file, &lines[start]) {
+                    Some(reason) => Err(reason.to_string()),
+                    None => panic::catch_unwind(|| compile(pattern, modifiers)).unwrap_or_else(|_| Ok(Err("panic while compiling".to_string())))
+                }
+            },
+            (None, _) => Err("unterminated pattern".to_string()),
+            (_, Err(_)) => Err("pattern is not UTF-8".to_string())
+        };
+
+        let test = match compiled {
+            Err(reason) => {
+                results.skip(&reason);
+                None
+            },
+            Ok(Err(failure)) => {
+                results.check(&file, pattern_line, &[failure]);
+                None
+            },
+            Ok(Ok(test)) => {
+                results.check(&file, pattern_line, &[]);
+                Some(test)
+            }
+        };
+
+        while i < lines.len() && !is_blank(lines[i].text) {
+            let line = &lines[i];
+            i += 1;
+            let test = match test {
+                None => continue,
+                Some(ref test) => test
+            };
+            if let Some(reason) = skip_reason(&file, line) {
+                results.skip(reason);
+                continue;
+            }
+            let subject = match str::from_utf8(line.text).map_err(|_| "subject is not UTF-8".to_string()).and_then(|text| parse_subject(text.trim(), test.utf)) {
+                Err(reason) => {
+                    results.skip(&reason);
+                    continue;
+                },
+                Ok(ref subject) if test.global && subject.start_offset > 0 => {
+                    results.skip("a start offset with /g");
+                    continue;
+                },
+                Ok(subject) => subject
+            };
+            match panic::catch_unwind(AssertUnwindSafe(|| run_subject(test, &subject))) {
+                Ok(actual) => results.check(&file, line, &actual),
+                Err(_) => results.failures.push(format!("{}:{}: panic while matching", file, line.number))
+            }
+        }
+    }
+
+    println!("{}: {} passed, {} failed", file, results.passed, results.failures.len());
+    for (reason, count) in results.skipped.iter() {
+        println!("  skipped {} ({})", count, reason);
+    }
+    assert!(results.failures.is_empty(), "{} test cases failed:\n{}", results.failures.len(), results.failures.join("\n"));
+}
+
+#[test]
+#[ignore = "needs the bundled libpcre 8.39"]
+fn test_testinput1() {
+    run_testdata(1);
+}
+
+#[test]
+#[ignore = "needs the bundled libpcre 8.39"]
+fn test_testinput2() {
+    run_testdata(2);
+}
+
+#[test]
+#[ignore = "needs the bundled libpcre 8.39"]
+fn test_testinput4() {
+    run_testdata(4);
+}
+
+#[test]
+#[ignore = "needs the bundled libpcre 8.39"]
+fn test_testinput5() {
+    run_testdata(5);
+}
+
+#[test]
+#[ignore = "needs the bundled libpcre 8.39"]
+fn test_testinput6() {
+    run_testdata(6);
+}
+
+#[test]
+#[ignore = "needs the bundled libpcre 8.39"]
+fn test_testinput7() {
+    run_testdata(7);
+}
+
+#[test]
+#[ignore = "needs the bundled libpcre 8.39"]
+fn test_testinput14() {
+    run_testdata(14);
+}
+
+#[test]
+#[ignore = "needs the bundled libpcre 8.39"]
+fn test_testinput15() {
+    run_testdata(15);
+}
+
+#[test]
+#[ignore = "needs the bundled libpcre 8.39"]
+fn test_testinput16() {
+    run_testdata(16);
+}
@@ -0,0 +1,65 @@
+extern crate enum_set;
+extern crate pcre;
+
+use enum_set::{EnumSet};
+use pcre::{CompileOption, Pcre};
+use pcre::flags::{self, ParseError};
+
+#[test]
+fn test_flags_round_trip() {
+    let options = flags::parse_flags("xmi<CRLF>U<bsr_unicode>J").unwrap();
+    let expected: EnumSet<CompileOption> = vec![CompileOption::Caseless, CompileOption::Multiline, CompileOption::Extended, CompileOption::Ungreedy, CompileOption::DupNames, CompileOption::NewlineCRLF, CompileOption::BsrUnicode].into_iter().collect();
+    assert!(options == expected);
+    assert_eq!(flags::format_flags(&options), "imxUJ<crlf><bsr_unicode>");
+    assert!(flags::parse_flags(&flags::format_flags(&options)).unwrap() == options);
+
+    // Only the last newline convention is kept.
+    let options = flags::parse_flags("<cr><lf>").unwrap();
+    assert_eq!(flags::format_flags(&options), "<lf>");
+
+    assert!(flags::parse_flags("").unwrap().is_empty());
+    assert_eq!(flags::parse_flags("iq").err(), Some(ParseError::UnknownFlag('q')));
+    assert_eq!(flags::parse_flags("<foo>").err(), Some(ParseError::UnknownNamedFlag("foo".to_string())));
+    assert_eq!(flags::parse_flags("<cr").err(), Some(ParseError::UnknownNamedFlag("cr".to_string())));
+}
+
+#[test]
+fn test_parse_literal() {
+    let (pattern, options) = flags::parse_literal("/^foo.*bar$/imx").unwrap();
+    assert_eq!(pattern, "^foo.*bar$");
+    assert_eq!(flags::format_flags(&options), "imx");
+
+    let (pattern, options) = flags::parse_literal(r"#a\#b/c#").unwrap();
+    assert_eq!(pattern, r"a\#b/c");
+    assert!(options.is_empty());
+
+    let (pattern, _) = flags::parse_literal("{a{2}}s").unwrap();
+    assert_eq!(pattern, "a{2}");
+
+    let (pattern, _) = flags::parse_literal(r"/\\/").unwrap();
+    assert_eq!(pattern, r"\\");
+
+    assert_eq!(flags::parse_literal("").err(), Some(ParseError::Empty));
+    assert_eq!(flags::parse_literal("abc").err(), Some(ParseError::InvalidDelimiter('a')));
+    assert_eq!(flags::parse_literal(r"/abc\/").err(), Some(ParseError::Unterminated));
+    assert_eq!(flags::parse_literal("/abc/z").err(), Some(ParseError::UnknownFlag('z')));
+}
+
+#[test]
+fn test_pcre_display() {
+    let mut options: EnumSet<CompileOption> = EnumSet::new();
+    options.insert(CompileOption::Caseless);
+    let re = Pcre::compile_with_options("a/b", &options).unwrap();
+    assert_eq!(re.pattern(), "a/b");
+    assert_eq!(re.to_string(), r"/a\/b/i");
+
+    // The displayed literal compiles to an equivalent pattern, even with `/` in a quote.
+    for pattern in &[r"a\/b", r"\Qa/b\E/", r"\Qa\/b", r"\Qa\", r"\Qa\\E/"] {
+        let re = Pcre::compile(pattern).unwrap();
+        let (displayed, _) = flags::parse_literal(&re.to_string()).unwrap();
+        let re2 = Pcre::compile(&displayed).unwrap();
+        for subject in &["a/b", "a/b/", r"a\/b", r"a\", r"a\/"] {
+            assert_eq!(re.exec(subject).map(|m| m.group(0)), re2.exec(subject).map(|m| m.group(0)), "{} {}", pattern, displayed);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+extern crate enum_set;
+extern crate pcre;
+
+use enum_set::{EnumSet};
+use pcre::CompileOption;
+use pcre::lexer::{LexerBuilder, LexerError, Token, ERROR};
+
+#[test]
+fn test_lexer_tokens() {
+    let lexer = LexerBuilder::new()
+        .token("number", r"\d+(?:\.\d+)?")
+        .token("string", r#""(?:[^"\\]|\\.)*""#)
+        .token("ident", r"\w+")
+        .token("op", r"[-+*/=]")
+        .skip("space", r"\s+")
+        .skip("comment", r"//[^\n]*")
+        .priority("comment", 1)
+        .build()
+        .unwrap();
+
+    let tokens: Vec<Token> = lexer.tokens("x = 1.5 // set x\ns = \"a \\\" b\"").collect();
+    assert_eq!(tokens, vec![
+        Token { kind: "ident", span: 0..1, text: "x" },
+        Token { kind: "op", span: 2..3, text: "=" },
+        Token { kind: "number", span: 4..7, text: "1.5" },
+        Token { kind: "ident", span: 17..18, text: "s" },
+        Token { kind: "op", span: 19..20, text: "=" },
+        Token { kind: "string", span: 21..29, text: "\"a \\\" b\"" }
+    ]);
+    assert_eq!(lexer.tokens("").count(), 0);
+    assert_eq!(lexer.tokens("  // only a comment").count(), 0);
+}
+
+#[test]
+fn test_lexer_errors() {
+    let lexer = LexerBuilder::new()
+        .token("word", r"[a-z]+")
+        .token("maybe", r"x*")
+        .skip("space", " ")
+        .build()
+        .unwrap();
+
+    // Unmatched input up to the next match is one error token, and rules which can match
+    // the empty string do not stop the lexer.
+    let tokens: Vec<Token> = lexer.tokens("ab #$é cd!").collect();
+    let kinds: Vec<&str> = tokens.iter().map(|token| token.kind).collect();
+    assert_eq!(kinds, ["word", ERROR, "word", ERROR]);
+    assert_eq!(tokens[1].text, "#$é");
+    assert_eq!(tokens[1].span, 3..7);
+    assert!(tokens[3].is_error());
+    assert_eq!(tokens[3].text, "!");
+}
+
+#[test]
+fn test_lexer_priority() {
+    let mut builder = LexerBuilder::new();
+    builder.token("ident", r"[a-z]+").token("keyword", r"(?:if|else)\b");
+    let lexer = builder.build().unwrap();
+    assert!(lexer.tokens("if").all(|token| token.kind == "ident"));
+
+    let lexer = builder.priority("keyword", 1).build().unwrap();
+    let kinds: Vec<&str> = lexer.tokens("ifx").chain(lexer.tokens("if")).map(|token| token.kind).collect();
+    assert_eq!(kinds, ["ident", "keyword"]);
+
+    // A rule's own MARKs do not change its kind.
+    let lexer = LexerBuilder::new().token("a", "a(*MARK:b)").token("b", "b").build().unwrap();
+    assert_eq!(lexer.tokens("ab").map(|token| token.kind).collect::<Vec<_>>(), ["a", "b"]);
+}
+
+#[test]
+fn test_lexer_options() {
+    let mut options = EnumSet::new();
+    options.insert(CompileOption::Caseless);
+    options.insert(CompileOption::Extended);
+    let lexer = LexerBuilder::new()
+        .token("hex", r"0x [0-9a-f]+  # a hex number")
+        .skip("space", r"\ ")
+        .options(&options)
+        .build()
+        .unwrap();
+    let texts: Vec<&str> = lexer.tokens("0xFF 0X1a").map(|token| token.text).collect();
+    assert_eq!(texts, ["0xFF", "0X1a"]);
+}
+
+#[test]
+fn test_lexer_build_errors() {
+    match LexerBuilder::new().token("a)", "a").build() {
+        Err(LexerError::InvalidKind(kind)) => assert_eq!(kind, "a)"),
+        other => panic!("{:?}", other)
+    }
+    match LexerBuilder::new().token(ERROR, "a").build() {
+        Err(LexerError::InvalidKind(_)) => (),
+        other => panic!("{:?}", other)
+    }
+    match LexerBuilder::new().token("a", "a").skip("a", "b").build() {
+        Err(LexerError::DuplicateKind(kind)) => assert_eq!(kind, "a"),
+        other => panic!("{:?}", other)
+    }
+    match LexerBuilder::new().token("a", "a").priority("b", 1).build() {
+        Err(LexerError::UnknownKind(kind)) => assert_eq!(kind, "b"),
+        other => panic!("{:?}", other)
+    }
+    match LexerBuilder::new().token("a", "a").token("b", "(b").build() {
+        Err(LexerError::Compile { kind, error }) => {
+            assert_eq!(kind, "b");
+            assert_eq!(error.offset(), 2);
+        },
+        other => panic!("{:?}", other)
+    }
+    // Group numbers would refer to the groups of other rules in the combined pattern.
+    for &pattern in [r"(a)\1", r"(a)\g{1}", r"(a)\g1", r"(a(?1)?)", r"(a)?(?(1)b|c)", r"(a(?(R1)b|c))"].iter() {
+        match LexerBuilder::new().token("x", "(x)").token("a", pattern).build() {
+            Err(LexerError::NumberedReference(kind)) => assert_eq!(kind, "a"),
+            other => panic!("{}: {:?}", pattern, other)
+        }
+    }
+    // Relative and named references, octal escapes and quoted text are fine.
+    for &pattern in [r"(a)\g{-1}", r"(a(?-1)?)", r"(?<q>a)\k<q>", r"[\1]", r"\Q\1\E", r"(?#\1)a", r"\\1"].iter() {
+        assert!(LexerBuilder::new().token("x", "(x)").token("a", pattern).build().is_ok(), "{}", pattern);
+    }
+    let lexer = LexerBuilder::new().token("x", "(x)").token("pair", r"(a|b)\g{-1}").build().unwrap();
+    let kinds: Vec<&str> = lexer.tokens("xaabbab").map(|token| token.kind).collect();
+    assert_eq!(kinds, ["x", "pair", "pair", "error"]);
+
+    // Each pattern compiles, but not together.
+    match LexerBuilder::new().token("a", "(?<x>a)").token("b", "(?<x>b)").build() {
+        Err(LexerError::Compile { kind, .. }) => assert_eq!(kind, "b"),
+        other => panic!("{:?}", other)
+    }
+}
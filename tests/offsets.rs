@@ -0,0 +1,84 @@
+extern crate pcre;
+
+use pcre::{LineColumn, OffsetMap, Pcre};
+
+/// Returns the subject with `n` copies of a line of mixed-width characters.
+fn mixed(n: usize) -> String {
+    (0..n).map(|i| format!("{} aé€😀\r\n", i)).collect()
+}
+
+#[test]
+fn test_offset_map() {
+    for subject in ["".to_string(), "x".to_string(), "\n\n".to_string(), mixed(1), mixed(100), "😀".repeat(40)].iter() {
+        let map = OffsetMap::new(subject);
+        let (mut chars, mut utf16, mut line, mut column, mut column_utf16) = (0, 0, 0, 0, 0);
+        let mut boundaries: Vec<(usize, Option<char>)> = subject.char_indices().map(|(i, c)| (i, Some(c))).collect();
+        boundaries.push((subject.len(), None));
+        for &(offset, c) in boundaries.iter() {
+            assert_eq!(map.char_index(offset), chars);
+            assert_eq!(map.utf16_index(offset), utf16);
+            assert_eq!(map.line_column(offset), LineColumn { line, column });
+            assert_eq!(map.line_column_utf16(offset), LineColumn { line, column: column_utf16 });
+            assert_eq!(map.char_to_byte(chars), Some(offset));
+            assert_eq!(map.utf16_to_byte(utf16), Some(offset));
+            if let Some(c) = c {
+                if c.len_utf16() == 2 {
+                    assert_eq!(map.utf16_to_byte(utf16 + 1), None);
+                }
+                chars += 1;
+                utf16 += c.len_utf16();
+                column += 1;
+                column_utf16 += c.len_utf16();
+                if c == '\n' {
+                    line += 1;
+                    column = 0;
+                    column_utf16 = 0;
+                }
+            }
+        }
+        assert_eq!(map.char_to_byte(chars + 1), None);
+        assert_eq!(map.utf16_to_byte(utf16 + 1), None);
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_offset_map_inside_char() {
+    OffsetMap::new("é").char_index(1);
+}
+
+#[test]
+fn test_match_spans() {
+    let subject = mixed(50);
+    let map = OffsetMap::new(&subject);
+    let re = Pcre::compile(r"(\d+) a(é€)").unwrap();
+    let spans: Vec<_> = re.matches(&subject).map(|m| (m.char_span(2, &map), m.utf16_span(2, &map), m.line_column_span(0, &map))).collect();
+    assert_eq!(spans.len(), 50);
+    assert_eq!(spans[0], (3..5, 3..5, LineColumn { line: 0, column: 0 }..LineColumn { line: 0, column: 5 }));
+    // Lines 0 to 9 have 8 characters and 9 UTF-16 code units, and the rest one more.
+    assert_eq!(spans[12], (8 * 10 + 9 * 2 + 4..8 * 10 + 9 * 2 + 6, 9 * 10 + 10 * 2 + 4..9 * 10 + 10 * 2 + 6,
+                           LineColumn { line: 12, column: 0 }..LineColumn { line: 12, column: 6 }));
+    assert_eq!(LineColumn { line: 12, column: 0 }.to_string(), "13:1");
+}
+
+#[test]
+#[should_panic]
+fn test_match_spans_other_subject() {
+    let re = Pcre::compile("a").unwrap();
+    re.exec("a").unwrap().char_span(0, &OffsetMap::new("ba"));
+}
+
+#[test]
+fn test_exec_from_char() {
+    let re = Pcre::compile(r".").unwrap();
+    assert_eq!(re.exec_from_char("é€ab", 0).unwrap().group(0), "é");
+    assert_eq!(re.exec_from_char("é€ab", 1).unwrap().group(0), "€");
+    assert_eq!(re.exec_from_char("é€ab", 3).unwrap().group_start(0), 6);
+    assert!(re.exec_from_char("é€ab", 4).is_none());
+}
+
+#[test]
+#[should_panic]
+fn test_exec_from_char_out_of_bounds() {
+    Pcre::compile("a").unwrap().exec_from_char("é€ab", 5);
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "rayon")]
+
+extern crate pcre;
+extern crate rayon;
+
+use pcre::{Pcre};
+use rayon::{ThreadPoolBuilder};
+
+/// The spans of the groups and the MARK of a match.
+type Spans = (Vec<(usize, usize)>, Option<String>);
+
+/// Returns the spans of each result, for comparing matches.
+fn spans(results: &[Option<pcre::Match>]) -> Vec<Option<Spans>> {
+    results.iter().map(|result| result.as_ref().map(|m| {
+        let groups = (0..m.string_count()).map(|n| (m.group_start(n), m.group_end(n))).collect();
+        (groups, m.mark().map(|mark| mark.to_string()))
+    })).collect()
+}
+
+#[test]
+fn test_par_matches() {
+    let subjects: Vec<String> = (0..10_000).map(|i| format!("record {} key={} é", i, i * 7 % 100)).collect();
+    let subjects: Vec<&str> = subjects.iter().map(|s| &s[..]).collect();
+    let pattern = r"key=(?<key>\d*7)(?:(*MARK:seven)|x)? (é)?";
+    let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+
+    for &jit in [false, true].iter() {
+        let re = Pcre::builder(pattern).jit(jit).build().unwrap();
+        let expected: Vec<_> = subjects.iter().map(|subject| re.exec(subject)).collect();
+        let results = pool.install(|| re.par_matches(&subjects));
+        assert_eq!(spans(&results), spans(&expected));
+        assert_eq!(results.iter().filter(|m| m.is_some()).count(), 1000);
+        assert_eq!(results[1].as_ref().unwrap().name("key"), Some("7"));
+        assert_eq!(results[1].as_ref().unwrap().mark(), Some("seven"));
+
+        let is_match = pool.install(|| re.par_is_match(&subjects));
+        assert_eq!(is_match, expected.iter().map(|m| m.is_some()).collect::<Vec<_>>());
+    }
+
+    assert!(Pcre::compile("a").unwrap().par_matches(&[]).is_empty());
+}
+
+#[test]
+fn test_par_matches_jit_stack() {
+    // Deep backtracking needs more than libpcre's default 32 KiB of JIT stack, and matching
+    // uses the same per-thread JIT stack with and without rayon.
+    let re = Pcre::builder(r"(?:a|b)*c").jit(true).build().unwrap();
+    let subject = format!("{}c", "ab".repeat(10_000));
+    assert_eq!(re.exec(&subject).map(|m| m.group_len(0)), Some(20_001));
+    let results = re.par_matches(&[&subject, "abc"]);
+    assert_eq!(results.iter().map(|m| m.as_ref().map(|m| m.group_len(0))).collect::<Vec<_>>(), vec![Some(20_001), Some(3)]);
+}
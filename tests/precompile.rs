@@ -0,0 +1,83 @@
+extern crate enum_set;
+extern crate pcre;
+
+use enum_set::{EnumSet};
+use pcre::{CompileOption, StudyOption};
+use pcre::precompile::{self, LoadError, PrecompileError};
+
+fn patterns() -> Vec<(&'static str, EnumSet<CompileOption>)> {
+    let mut caseless = EnumSet::new();
+    caseless.insert(CompileOption::Caseless);
+    let mut crlf = EnumSet::new();
+    crlf.insert(CompileOption::Multiline);
+    crlf.insert(CompileOption::NewlineCRLF);
+    vec![(r"^(?<num>\d+)$", EnumSet::new()), ("^yes$", caseless), ("^b$", crlf)]
+}
+
+fn write() -> Vec<u8> {
+    let mut data = Vec::new();
+    precompile::write(&patterns(), &mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_precompile_round_trip() {
+    let mut res = unsafe { precompile::load(&write()) }.unwrap();
+    assert_eq!(res.len(), 3);
+
+    assert_eq!(res[0].pattern(), r"^(?<num>\d+)$");
+    assert_eq!(res[0].capture_count(), 1);
+    assert_eq!(res[0].name_table()["num"], vec![1]);
+    assert_eq!(res[0].exec("42").unwrap().group(1), "42");
+    assert!(!res[0].is_match("4x2"));
+
+    assert_eq!(res[1].to_string(), "/^yes$/i");
+    assert!(res[1].is_match("YES"));
+
+    assert!(res[2].is_match("a\r\nb\r\n"));
+    assert!(!res[2].is_match("a\nb\n"));
+
+    // Loaded patterns can be studied, which requires that they have a single reference.
+    let mut study_options = EnumSet::new();
+    study_options.insert(StudyOption::StudyExtraNeeded);
+    assert!(res[0].study_with_options(&study_options));
+    assert!(res[0].is_match("7"));
+}
+
+#[test]
+fn test_precompile_file() {
+    let path = std::env::temp_dir().join(format!("rust-pcre-precompile-{}.bin", std::process::id()));
+    precompile::write_file(&patterns(), &path).unwrap();
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(data, write());
+}
+
+#[test]
+fn test_precompile_errors() {
+    match precompile::write(&[("ok", EnumSet::new()), ("(bad", EnumSet::new())], &mut Vec::new()) {
+        Err(PrecompileError::Compile { index, error }) => {
+            assert_eq!(index, 1);
+            assert_eq!(error.offset(), 4);
+        },
+        _ => panic!("expected a compilation error")
+    }
+
+    let data = write();
+    assert_eq!(unsafe { precompile::load(&data[..(data.len() - 1)]) }.err(), Some(LoadError::Format));
+    assert_eq!(unsafe { precompile::load(b"not patterns") }.err(), Some(LoadError::Format));
+
+    // The libpcre version follows the 7-byte header and its 4-byte length.
+    let mut other_version = data.clone();
+    other_version[11] = b'0';
+    match unsafe { precompile::load(&other_version) } {
+        Err(LoadError::VersionMismatch { running, .. }) => assert_eq!(running, pcre::pcre_version()),
+        _ => panic!("expected a version mismatch")
+    }
+
+    // Corrupt the magic number at the start of the last compiled pattern.
+    let mut bad_code = data.clone();
+    let magic = bad_code.windows(4).rposition(|w| w == b"ERCP" || w == b"PCRE").unwrap();
+    bad_code[magic] = 0;
+    assert_eq!(unsafe { precompile::load(&bad_code) }.err(), Some(LoadError::InvalidCode(2)));
+}
@@ -0,0 +1,99 @@
+// Clippy checks `regex::Regex::new()` patterns against the `regex` crate's syntax, which
+// does not support lookaround or backreferences.
+#![allow(clippy::invalid_regex)]
+
+extern crate pcre;
+
+use pcre::regex::{Regex};
+
+#[test]
+fn test_regex_find() {
+    let re = Regex::new(r"(?<=\$)\d+").unwrap();
+    assert!(re.is_match("cost: $42"));
+    assert!(!re.is_match("cost: 42"));
+    let m = re.find("cost: $42").unwrap();
+    assert_eq!((m.start(), m.end()), (7, 9));
+    assert_eq!(m.range(), 7..9);
+    assert_eq!(m.as_str(), "42");
+    assert_eq!(re.shortest_match("cost: $42"), Some(9));
+    assert_eq!(re.as_str(), r"(?<=\$)\d+");
+}
+
+#[test]
+fn test_regex_find_iter_empty_matches() {
+    // Same results as the `regex` crate.
+    let re = Regex::new("a*").unwrap();
+    let spans: Vec<_> = re.find_iter("baaab").map(|m| m.range()).collect();
+    assert_eq!(spans, vec![0..0, 1..4, 5..5]);
+
+    let re = Regex::new("").unwrap();
+    let spans: Vec<_> = re.find_iter("é!").map(|m| m.range()).collect();
+    assert_eq!(spans, vec![0..0, 2..2, 3..3]);
+}
+
+#[test]
+fn test_regex_find_iter_keep_in_lookbehind() {
+    // The matches end at or before the offset they are found from, and each one is found
+    // once.
+    let re = Regex::new(r"(?<=\Ka)").unwrap();
+    let spans: Vec<_> = re.find_iter("aaa").map(|m| m.range()).collect();
+    assert_eq!(spans, vec![0..1, 1..2, 2..3]);
+    let spans: Vec<_> = re.captures_iter("aéa").map(|caps| caps.get(0).unwrap().range()).collect();
+    assert_eq!(spans, vec![0..1, 3..4]);
+}
+
+#[test]
+fn test_regex_captures() {
+    let re = Regex::new(r"(?<key>\w+)=(\w+)?(;)?").unwrap();
+    assert_eq!(re.captures_len(), 4);
+    let names: Vec<_> = re.capture_names().collect();
+    assert_eq!(names, vec![None, Some("key"), None, None]);
+
+    let caps = re.captures("x=1").unwrap();
+    assert_eq!(&caps[0], "x=1");
+    assert_eq!(&caps["key"], "x");
+    assert_eq!(&caps[2], "1");
+    assert!(caps.get(3).is_none());
+    assert!(caps.name("nope").is_none());
+    assert_eq!(caps.len(), 4);
+    let groups: Vec<_> = caps.iter().map(|m| m.map(|m| m.as_str())).collect();
+    assert_eq!(groups, vec![Some("x=1"), Some("x"), Some("1"), None]);
+
+    let keys: Vec<_> = re.captures_iter("a=1;b=;c=3").map(|caps| caps["key"].to_string()).collect();
+    assert_eq!(keys, vec!["a", "b", "c"]);
+}
+
+#[test]
+fn test_regex_backreference() {
+    let re = Regex::new(r"\b(\w+) \1\b").unwrap();
+    let m = re.find("this is is a test").unwrap();
+    assert_eq!(m.as_str(), "is is");
+}
+
+#[test]
+#[should_panic]
+fn test_regex_index_unset_group() {
+    let re = Regex::new("(a)|b").unwrap();
+    let caps = re.captures("b").unwrap();
+    let _ = &caps[1];
+}
+
+#[test]
+fn test_regex_match_as_bytes() {
+    let re = Regex::new(r"\C").unwrap();
+    let m = re.find("é").unwrap();
+    assert_eq!((m.range(), m.as_bytes()), (0..1, &b"\xc3"[..]));
+}
+
+#[test]
+#[should_panic(expected = "not on UTF-8 character boundaries")]
+fn test_regex_match_as_str_not_on_boundary() {
+    let re = Regex::new(r"\C").unwrap();
+    let _ = re.find("é").unwrap().as_str();
+}
+
+#[test]
+fn test_regex_new_error() {
+    assert!(Regex::new("(").is_err());
+    assert!("[a-".parse::<Regex>().is_err());
+}
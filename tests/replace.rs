@@ -0,0 +1,147 @@
+extern crate pcre;
+
+use pcre::Pcre;
+use pcre::replace::{replace_stream, ReplaceSummary};
+use std::io::{self, Read};
+
+/// Expands `replacement` for the first match of `pattern` in `subject`.
+fn expand(pattern: &str, subject: &str, replacement: &str) -> String {
+    let re = Pcre::compile(pattern).unwrap();
+    let m = re.exec(subject).unwrap();
+    let mut dst = String::new();
+    m.expand(replacement, &mut dst);
+    dst
+}
+
+#[test]
+fn test_expand() {
+    assert_eq!(expand(r"(\w+) (\w+)", "hello world", "$2 $1"), "world hello");
+    assert_eq!(expand(r"(\w+) (\w+)", "hello world", "<$0>"), "<hello world>");
+    assert_eq!(expand(r"(?<first>\w+) (?<second>\w+)", "hello world", "${second}-$first"), "world-hello");
+    assert_eq!(expand(r"(\w+)", "abc", "${1}1 $$1 $"), "abc1 $1 $");
+
+    // `$1x` refers to a group named `1x`, which does not exist.
+    assert_eq!(expand(r"(\w+)", "abc", "[$1x]"), "[]");
+
+    // Unset, missing and unterminated references.
+    assert_eq!(expand(r"(a)|(b)", "b", "[$1][$2][$3][$nope]"), "[][b][][]");
+    assert_eq!(expand(r"(a)", "a", "${1"), "${1");
+    assert_eq!(expand(r"(a)", "a", "$-${}"), "$-${}");
+
+    // With duplicate names, the group which participated in the match is used.
+    assert_eq!(expand(r"(?J)(?<n>a)|(?<n>b)", "b", "<$n>"), "<b>");
+}
+
+/// A reader which returns at most `size` bytes per read.
+struct Trickle<'a> {
+    data: &'a [u8],
+    size: usize
+}
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.size.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+/// Replaces the matches of `re` in the whole of `subject`.
+fn replace_all(re: &Pcre, subject: &str, replacement: &str) -> (String, u64) {
+    let mut result = String::new();
+    let mut end = 0;
+    let mut count = 0;
+    for m in re.matches(subject) {
+        result.push_str(&subject[end..m.group_start(0).max(end)]);
+        m.expand(replacement, &mut result);
+        end = m.group_end(0);
+        count += 1;
+    }
+    result.push_str(&subject[end..]);
+    (result, count)
+}
+
+#[test]
+fn test_replace_stream() {
+    let cases: &[(&str, &str, &str)] = &[
+        (r"a+", "caaandy aa a", "<$0>"),
+        (r"\bfoo\b", "foo food foo\nfoo", "bar"),
+        (r"(?<=ab)c", "abc xbc abcabc", "C"),
+        (r"(?m)^x", "x\nxx\n\nx", "y"),
+        (r"^a", "aaa\na", "b"),
+        (r"a$", "a\na\na", "b"),
+        (r"(?m)$", "one\ntwo\n", ";"),
+        (r"a$", "a\nb a\n", "b"),
+        (r"a\Z|a\z", "aa\na", "b"),
+        (r"a(?!b)", "ab ac a", "A"),
+        (r"a(?=b)", "ab ac a", "A"),
+        (r"a\b", "ab a", "A"),
+        (r"x*", "axxbx", "-"),
+        (r"é+", "héé hé éé", "e"),
+        (r"(\d{3})-(\d{4})", "call 555-1234 or 555-12345 or 55-1234", "$2-$1"),
+        (r"dog(sbody)?", "dog dogsbody dogsb", "[$1]"),
+        (r"\n\n+", "a\n\nb\n\n\nc\n", "\n"),
+        (r"(?s)<!--.*?-->", "a<!-- x\n -->b<!---->c<!-- unterminated", ""),
+        // The second match starts before the end of the first one.
+        (r"(?<=\K..)x", "aaxax", "<$0>")
+    ];
+    for &(pattern, subject, replacement) in cases.iter() {
+        let re = Pcre::compile(pattern).unwrap();
+        let (expected, count) = replace_all(&re, subject, replacement);
+        for &size in [1, 2, 3, 5, 64 * 1024].iter() {
+            let mut output = Vec::new();
+            let summary = replace_stream(Trickle { data: subject.as_bytes(), size }, &mut output, &re, replacement).unwrap();
+            assert_eq!((String::from_utf8(output).unwrap(), summary.replacements), (expected.clone(), count),
+                       "{:?} on {:?} read {} bytes at a time", pattern, subject, size);
+            assert_eq!(summary.bytes_written, expected.len() as u64);
+        }
+    }
+}
+
+#[test]
+fn test_replace_stream_overlapping() {
+    // With `\K` in a lookbehind, the second match starts before the end of the first.
+    let re = Pcre::compile(r"(?<=\K..)x").unwrap();
+    for &size in [1, 2, 64 * 1024].iter() {
+        let mut output = Vec::new();
+        let summary = replace_stream(Trickle { data: b"aaxax", size }, &mut output, &re, "<$0>").unwrap();
+        assert_eq!((output, summary.replacements), (b"<aax><xax>".to_vec(), 2), "read {} bytes at a time", size);
+    }
+}
+
+#[test]
+fn test_replace_stream_large() {
+    // Matches straddle the chunks in which the input is read.
+    let subject: String = (0..100_000).map(|i| format!("line {}\n", i)).collect();
+    let re = Pcre::compile(r"(?m)^line (\d+)5$").unwrap();
+    let (expected, count) = replace_all(&re, &subject, "${1}5 enil");
+    let mut output = Vec::new();
+    let summary = replace_stream(subject.as_bytes(), &mut output, &re, "${1}5 enil").unwrap();
+    assert_eq!(summary, ReplaceSummary { replacements: count, bytes_written: expected.len() as u64 });
+    assert_eq!(count, 9_999);
+    assert!(String::from_utf8(output).unwrap() == expected);
+
+    // A match longer than a chunk.
+    let subject = format!("<{}>", "x".repeat(200_000));
+    let re = Pcre::compile(r"<x+>").unwrap();
+    let mut output = Vec::new();
+    let summary = replace_stream(subject.as_bytes(), &mut output, &re, "y").unwrap();
+    assert_eq!((output, summary.replacements), (b"y".to_vec(), 1));
+}
+
+#[test]
+fn test_replace_stream_invalid_utf8() {
+    let re = Pcre::compile("a").unwrap();
+    let err = replace_stream(&b"abc\xff"[..], io::sink(), &re, "b").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = replace_stream(&b"abc\xc3"[..], io::sink(), &re, "b").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_max_lookbehind() {
+    assert_eq!(Pcre::compile("abc").unwrap().max_lookbehind(), 0);
+    assert_eq!(Pcre::compile(r"\bx").unwrap().max_lookbehind(), 1);
+    assert_eq!(Pcre::compile(r"(?<=abc|de)x(?<!é)").unwrap().max_lookbehind(), 3);
+}
@@ -0,0 +1,73 @@
+extern crate enum_set;
+extern crate pcre;
+
+use enum_set::{EnumSet};
+use pcre::{CompileOption, Pcre, Scanner};
+
+#[test]
+fn test_scanner_scan() {
+    let number = Pcre::compile(r"(\d+)(?:\.(\d+))?").unwrap();
+    let op = Pcre::compile(r"\s*([-+])\s*").unwrap();
+
+    let mut scanner = Scanner::new("3.14 + 2");
+    assert_eq!(scanner.check(&number), Some("3.14"));
+    assert_eq!(scanner.pos(), 0);
+    assert_eq!(scanner.scan(&number), Some("3.14"));
+    assert_eq!(scanner.last_match().unwrap().group(2), "14");
+    assert_eq!(scanner.pos(), 4);
+    assert_eq!(scanner.rest(), " + 2");
+
+    // A failed attempt does not advance, and forgets the last match.
+    assert_eq!(scanner.scan(&number), None);
+    assert!(scanner.last_match().is_none());
+    assert_eq!(scanner.pos(), 4);
+
+    assert_eq!(scanner.skip(&op), Some(3));
+    assert_eq!(scanner.scan(&number), Some("2"));
+    assert!(scanner.eos());
+    assert_eq!(scanner.rest(), "");
+}
+
+#[test]
+fn test_scanner_until() {
+    let comma = Pcre::compile(",").unwrap();
+    let mut scanner = Scanner::new("a,bc,d");
+    assert_eq!(scanner.scan_until(&comma), Some("a,"));
+    assert_eq!(scanner.skip_until(&comma), Some(3));
+    assert_eq!(scanner.pos(), 5);
+    assert_eq!(scanner.scan_until(&comma), None);
+    assert_eq!(scanner.pos(), 5);
+
+    scanner.set_pos(2);
+    assert_eq!(scanner.scan_until(&comma), Some("bc,"));
+}
+
+#[test]
+fn test_scanner_anchors() {
+    // The text before the position is still seen by `^` and lookbehind assertions.
+    let caret = Pcre::compile(r"^\w").unwrap();
+    let after_dash = Pcre::compile(r"(?<=-)\w+").unwrap();
+    let mut scanner = Scanner::new("a-b");
+    assert_eq!(scanner.scan(&caret), Some("a"));
+    scanner.set_pos(2);
+    assert_eq!(scanner.check(&caret), None);
+    assert_eq!(scanner.check(&after_dash), Some("b"));
+
+    let mut options = EnumSet::new();
+    options.insert(CompileOption::Multiline);
+    let line = Pcre::compile_with_options(r"^\w+", &options).unwrap();
+    let mut scanner = Scanner::new("x\ny");
+    assert_eq!(scanner.skip_until(&line), Some(1));
+    assert_eq!(scanner.scan_until(&line), Some("\ny"));
+
+    let mut scanner = Scanner::new("x\ny");
+    scanner.set_not_bol(true);
+    assert_eq!(scanner.check(&line), None);
+    assert_eq!(scanner.scan_until(&line), Some("x\ny"));
+}
+
+#[test]
+#[should_panic]
+fn test_scanner_set_pos_not_boundary() {
+    Scanner::new("é").set_pos(1);
+}
@@ -0,0 +1,128 @@
+extern crate pcre;
+
+use pcre::{ExecError, Pcre};
+use pcre::search::{Line, LineKind, Searcher, Summary};
+
+/// Searches `input`, returning the line numbers of the matching lines and context lines
+/// (negated) that were reported.
+fn numbers(searcher: &Searcher, re: &Pcre, input: &str) -> (Vec<i64>, Summary) {
+    let mut numbers = Vec::new();
+    let summary = searcher.search(re, input.as_bytes(), |line| {
+        numbers.push(if line.is_match() { line.number as i64 } else { -(line.number as i64) });
+        true
+    }).unwrap();
+    (numbers, summary)
+}
+
+#[test]
+fn test_search_lines() {
+    let re = Pcre::compile("o+").unwrap();
+    let input = "foo\r\nbar\nboo boo";
+    let mut lines = Vec::new();
+    let summary = Searcher::new().search(&re, input.as_bytes(), |line: &Line| {
+        lines.push((line.kind, line.number, line.offset, line.text.to_string(), line.spans.iter().map(|span| (span.start, span.end)).collect::<Vec<_>>()));
+        true
+    }).unwrap();
+    assert_eq!(summary, Summary { matched_lines: 2, binary_offset: None });
+    assert_eq!(lines, vec![
+        (LineKind::Match, 1, 0, "foo\r".to_string(), vec![(1, 3)]),
+        (LineKind::Match, 3, 9, "boo boo".to_string(), vec![(1, 3), (5, 7)])
+    ]);
+
+    let (numbers, summary) = numbers(Searcher::new().invert(true), &re, input);
+    assert_eq!(numbers, [2]);
+    assert_eq!(summary.matched_lines, 1);
+}
+
+#[test]
+fn test_search_context() {
+    let re = Pcre::compile("x").unwrap();
+    let input = "1\n2\nx\n4\n5\n6\n7\nx\nx\n10\n11\n";
+    assert_eq!(numbers(Searcher::new().context(1), &re, input).0, [-2, 3, -4, -7, 8, 9, -10]);
+    assert_eq!(numbers(Searcher::new().before_context(2), &re, input).0, [-1, -2, 3, -6, -7, 8, 9]);
+    assert_eq!(numbers(Searcher::new().after_context(3), &re, input).0, [3, -4, -5, -6, 8, 9, -10, -11]);
+
+    // Trailing context is still reported after the last match, and matching lines in it
+    // are reported as context.
+    let (numbers, summary) = numbers(Searcher::new().after_context(1).max_count(Some(2)), &re, input);
+    assert_eq!(numbers, [3, -4, 8, -9]);
+    assert_eq!(summary.matched_lines, 2);
+}
+
+#[test]
+fn test_search_stop() {
+    let re = Pcre::compile("a").unwrap();
+    let mut count = 0;
+    let summary = Searcher::new().search(&re, "a\na\na\n".as_bytes(), |_| {
+        count += 1;
+        count < 2
+    }).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(summary.matched_lines, 2);
+
+    // A nested search on the same thread.
+    let outer = Searcher::new().search(&re, "a\nb\na\n".as_bytes(), |line| {
+        let inner = Searcher::new().count(&re, line.text.as_bytes()).unwrap();
+        assert_eq!(inner.matched_lines, 1);
+        true
+    }).unwrap();
+    assert_eq!(outer.matched_lines, 2);
+}
+
+#[test]
+fn test_search_count_and_binary() {
+    let re = Pcre::compile("a").unwrap();
+    assert_eq!(Searcher::new().count(&re, "a\nb\naa\n".as_bytes()).unwrap().matched_lines, 2);
+    assert_eq!(Searcher::new().max_count(Some(1)).count(&re, "a\nb\naa\n".as_bytes()).unwrap().matched_lines, 1);
+    assert_eq!(Searcher::new().invert(true).count(&re, "a\nb\naa\n".as_bytes()).unwrap().matched_lines, 1);
+
+    let input: &[u8] = b"a\nb\x00a\n\xffa\n";
+    let summary = Searcher::new().binary_detection(true).count(&re, input).unwrap();
+    assert_eq!(summary, Summary { matched_lines: 1, binary_offset: Some(2) });
+
+    // Without binary detection, invalid UTF-8 is replaced.
+    let mut texts = Vec::new();
+    let summary = Searcher::new().search(&re, input, |line| {
+        texts.push(line.text.to_string());
+        true
+    }).unwrap();
+    assert_eq!(summary.matched_lines, 3);
+    assert_eq!(texts, ["a", "b\u{0}a", "\u{fffd}a"]);
+}
+
+#[test]
+fn test_search_with_errors() {
+    let re = Pcre::builder(r"^(a+)+$|ok").match_limit(1000).build().unwrap();
+    let input = "ok\naaaaaaaaaaaaaaaaaaaaaaaaab\nx\nok\n";
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    let summary = Searcher::new().context(1).search_with_errors(&re, input.as_bytes(), |line| {
+        lines.push((line.kind, line.number));
+        true
+    }, |number, err| {
+        errors.push((number, err));
+        true
+    }).unwrap();
+    assert_eq!(summary.matched_lines, 2);
+    assert_eq!(lines, [(LineKind::Match, 1), (LineKind::Context, 2), (LineKind::Context, 3), (LineKind::Match, 4)]);
+    assert_eq!(errors, [(2, ExecError::MatchLimit)]);
+
+    // The lines on which matching failed are not selected by an inverted search either.
+    let mut errors = 0;
+    let summary = Searcher::new().invert(true).count_with_errors(&re, input.as_bytes(), |_, _| {
+        errors += 1;
+        true
+    }).unwrap();
+    assert_eq!((summary.matched_lines, errors), (1, 1));
+
+    // The search stops if the callback returns `false`.
+    let summary = Searcher::new().count_with_errors(&re, input.as_bytes(), |_, _| false).unwrap();
+    assert_eq!(summary.matched_lines, 1);
+}
+
+#[test]
+#[should_panic(expected = "limit was exceeded")]
+fn test_search_match_limit() {
+    let re = Pcre::builder(r"^(a+)+$").match_limit(1000).build().unwrap();
+    let _ = Searcher::new().count(&re, "aaaaaaaaaaaaaaaaaaaaaaaaab\n".as_bytes());
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "serde")]
+
+extern crate pcre;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+use pcre::{Pcre};
+use std::collections::{BTreeMap};
+
+#[test]
+fn test_serde_pcre() {
+    let re: Pcre = serde_json::from_str(r#"{"pattern": "^a+$", "flags": "im"}"#).unwrap();
+    assert!(re.is_match("b\nAA"));
+    assert_eq!(serde_json::to_string(&re).unwrap(), r#"{"pattern":"^a+$","flags":"im"}"#);
+
+    let re: Pcre = serde_json::from_str(r#""^a+$""#).unwrap();
+    assert!(!re.is_match("AA"));
+    assert_eq!(serde_json::to_string(&re).unwrap(), r#"{"pattern":"^a+$","flags":""}"#);
+
+    let re: Vec<Pcre> = serde_json::from_str(r#"[{"pattern": "x"}, "y"]"#).unwrap();
+    assert_eq!(re.len(), 2);
+}
+
+#[test]
+fn test_serde_pcre_errors() {
+    let err = serde_json::from_str::<Pcre>(r#"{"pattern": "(abc"}"#).err().unwrap();
+    assert!(err.to_string().contains("compilation failed at offset 4"), "{}", err);
+
+    let err = serde_json::from_str::<Pcre>(r#"{"pattern": "abc", "flags": "q"}"#).err().unwrap();
+    assert!(err.to_string().contains("unknown flag 'q'"), "{}", err);
+
+    assert!(serde_json::from_str::<Pcre>(r#"{"flags": "i"}"#).is_err());
+    assert!(serde_json::from_str::<Pcre>(r#"{"pattern": "a", "other": 1}"#).is_err());
+}
+
+#[test]
+fn test_serde_match() {
+    let re = Pcre::compile(r"(?<year>\d{4})-(\d{2})(?:-(?<day>\d{2}))?(*MARK:date)").unwrap();
+    let m = re.exec("on 2015-06.").unwrap();
+    assert_eq!(m.name("year"), Some("2015"));
+    assert_eq!(m.name("day"), None);
+    assert_eq!(m.name("month"), None);
+
+    let json = serde_json::to_value(&m).unwrap();
+    assert_eq!(json, serde_json::json!({
+        "start": 3,
+        "end": 10,
+        "groups": [
+            {"name": null, "start": 3, "end": 10, "text": "2015-06"},
+            {"name": "year", "start": 3, "end": 7, "text": "2015"},
+            {"name": null, "start": 8, "end": 10, "text": "06"},
+            null
+        ],
+        "mark": "date"
+    }));
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+enum Method {
+    Get,
+    Post
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Request<'a> {
+    method: Method,
+    path: &'a str,
+    status: u16,
+    size: Option<u64>
+}
+
+#[test]
+fn test_captures_into() {
+    let re = Pcre::compile(r"^(?<method>\w+) (?<path>\S+) (?<status>\d+)(?: (?<size>\d+))?$").unwrap();
+    let request: Request = re.captures_into("GET /x 200 512").unwrap().unwrap();
+    assert_eq!(request, Request { method: Method::Get, path: "/x", status: 200, size: Some(512) });
+
+    let request: Request = re.captures_into("POST /y 404").unwrap().unwrap();
+    assert_eq!(request.size, None);
+
+    assert!(re.captures_into::<Request>("nope").unwrap().is_none());
+
+    let err = re.captures_into::<Request>("GET /x 99999 1").err().unwrap();
+    assert_eq!(err.group(), Some("status"));
+    assert!(err.to_string().starts_with("group `status`: cannot convert \"99999\""), "{}", err);
+
+    let err = re.captures_into::<Request>("PUT /x 200").err().unwrap();
+    assert_eq!(err.group(), Some("method"));
+}
+
+#[test]
+fn test_captures_into_tuple() {
+    let re = Pcre::compile(r"(\d+)x(\d+)(?:@(\d+))?").unwrap();
+    let (w, h, dpi): (u32, u32, Option<u32>) = re.captures_into("1920x1080").unwrap().unwrap();
+    assert_eq!((w, h, dpi), (1920, 1080, None));
+
+    let err = re.captures_into::<(u8, u8, Option<u8>)>("1x1000").err().unwrap();
+    assert_eq!(err.group(), Some("2"));
+
+    let map: BTreeMap<String, Option<String>> = Pcre::compile(r"(?<a>a)|(?<b>b)").unwrap().captures_into("b").unwrap().unwrap();
+    assert_eq!(map["a"], None);
+    assert_eq!(map["b"], Some("b".to_string()));
+}
+
+#[test]
+fn test_captures_into_split_character() {
+    // `\C` matches the first byte of "é".
+    let re = Pcre::compile(r"(?<first>\C)(?<rest>.*)").unwrap();
+    let err = re.captures_into::<BTreeMap<String, &str>>("é!").err().unwrap();
+    assert_eq!(err.group(), Some("first"));
+    assert_eq!(err.message(), "the group does not start and end on UTF-8 character boundaries");
+
+    let err = re.captures_into::<(Option<&str>, &str)>("é!").err().unwrap();
+    assert_eq!(err.group(), Some("1"));
+}
@@ -0,0 +1,87 @@
+// Tests for the edges where the bindings must not pass invalid offsets or lengths to libpcre.
+
+extern crate enum_set;
+extern crate pcre;
+
+use enum_set::{EnumSet};
+use pcre::{ExecOption, Pcre};
+use pcre::regex::{Regex};
+
+#[test]
+fn test_exec_from_char_boundaries() {
+    let re = Pcre::compile(".").unwrap();
+    let subject = "\u{e9}t\u{e9}";
+    assert_eq!(re.exec_from(subject, 0).unwrap().group(0), "\u{e9}");
+    assert_eq!(re.exec_from(subject, 2).unwrap().group(0), "t");
+    assert_eq!(re.exec_from(subject, 3).unwrap().group(0), "\u{e9}");
+    assert!(re.exec_from(subject, 5).is_none());
+}
+
+#[test]
+#[should_panic(expected = "not on a character boundary")]
+fn test_exec_from_inside_char() {
+    let re = Pcre::compile(".").unwrap();
+    re.exec_from("\u{e9}", 1);
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn test_exec_from_past_end() {
+    let re = Pcre::compile("").unwrap();
+    re.exec_from("abc", 4);
+}
+
+#[test]
+#[should_panic(expected = "not on a character boundary")]
+fn test_exec_from_with_options_inside_char() {
+    let re = Pcre::compile(".").unwrap();
+    let mut options: EnumSet<ExecOption> = EnumSet::new();
+    options.insert(ExecOption::ExecAnchored);
+    re.exec_from_with_options("\u{20ac}", 2, &options);
+}
+
+#[test]
+fn test_single_byte_group() {
+    let re = Pcre::compile("\\C").unwrap();
+    let m = re.exec("\u{e9}").unwrap();
+    assert_eq!((m.group_start(0), m.group_end(0)), (0, 1));
+    assert_eq!(m.group_bytes(0), b"\xc3");
+}
+
+#[test]
+#[should_panic]
+fn test_single_byte_group_str() {
+    let re = Pcre::compile("\\C").unwrap();
+    let m = re.exec("\u{e9}").unwrap();
+    m.group(0);
+}
+
+#[test]
+fn test_matches_single_byte() {
+    // Each match ends inside a character, so the next one must start at the following
+    // character boundary.
+    let re = Pcre::compile("\\C").unwrap();
+    let spans: Vec<_> = re.matches("\u{e9}\u{20ac}a").map(|m| (m.group_start(0), m.group_end(0))).collect();
+    assert_eq!(spans, vec![(0, 1), (2, 3), (5, 6)]);
+
+    let re = Regex::new("\\C").unwrap();
+    let spans: Vec<_> = re.find_iter("\u{e9}\u{20ac}a").map(|m| m.range()).collect();
+    assert_eq!(spans, vec![0..1, 2..3, 5..6]);
+}
+
+#[test]
+fn test_matches_empty_single_byte() {
+    // `\K` can make an empty match end inside a character.
+    let re = Pcre::compile("\\C\\K").unwrap();
+    let spans: Vec<_> = re.matches("\u{e9}a").map(|m| (m.group_start(0), m.group_end(0))).collect();
+    assert_eq!(spans, vec![(1, 1), (3, 3)]);
+}
+
+#[test]
+#[ignore] // Allocates 2 GiB.
+#[should_panic(expected = "exceeds the maximum")]
+fn test_exec_oversized_subject() {
+    let subject = String::from_utf8(vec![b'a'; (i32::MAX as usize) + 1]).unwrap();
+    let re = Pcre::compile("b").unwrap();
+    re.exec(&subject);
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "async")]
+
+extern crate futures;
+extern crate pcre;
+
+use futures::executor::block_on;
+use futures::io::{AsyncRead};
+use futures::stream::{Stream, StreamExt, TryStreamExt};
+use pcre::Pcre;
+use pcre::stream::{MatchStream, StreamMatch};
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// A reader which returns at most `size` bytes per read, and is not ready before every
+/// other read.
+struct Trickle<'a> {
+    data: &'a [u8],
+    size: usize,
+    ready: bool
+}
+
+impl<'a> AsyncRead for Trickle<'a> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let n = self.size.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// Returns the spans and texts of the matches that `stream` yields.
+fn collect<S: Stream<Item = io::Result<StreamMatch>>>(stream: S) -> io::Result<Vec<(u64, u64, String)>> {
+    block_on(stream.map_ok(|m| (m.group_start(0), m.group_end(0), m.group(0).to_string())).try_collect())
+}
+
+#[test]
+fn test_match_stream() {
+    let cases: &[(&str, &str)] = &[
+        (r"a+", "caaandy aa a"),
+        (r"\bfoo\b", "foo food foo\nfoo"),
+        (r"(?<=ab)c", "abc xbc abcabc"),
+        (r"(?m)^x", "x\nxx\n\nx"),
+        (r"a$", "a\na\na"),
+        (r"(?m)$", "one\ntwo\n"),
+        (r"x*", "axxbx"),
+        (r"é+", "héé hé éé"),
+        (r"(\d{3})-(\d{4})", "call 555-1234 or 555-12345 or 55-1234"),
+        (r"(?s)<!--.*?-->", "a<!-- x\n -->b<!---->c<!-- unterminated")
+    ];
+    for &(pattern, subject) in cases.iter() {
+        let re = Pcre::compile(pattern).unwrap();
+        let expected: Vec<_> = re.matches(subject).map(|m| (m.group_start(0) as u64, m.group_end(0) as u64, m.group(0).to_string())).collect();
+        for &size in [1, 2, 3, 64 * 1024].iter() {
+            let reader = Trickle { data: subject.as_bytes(), size, ready: false };
+            assert_eq!(collect(MatchStream::new(&re, reader)).unwrap(), expected,
+                       "{:?} on {:?} read {} bytes at a time", pattern, subject, size);
+        }
+    }
+}
+
+#[test]
+fn test_match_stream_groups() {
+    let re = Arc::new(Pcre::compile(r"(?<key>\w+)=(?<value>\w*)(;)?(*MARK:pair)").unwrap());
+    let input = "x=1; yy=; zzz=3";
+    let reader = Trickle { data: input.as_bytes(), size: 4, ready: false };
+    let matches: Vec<StreamMatch> = block_on(MatchStream::new(re.clone(), reader).try_collect()).unwrap();
+    assert_eq!(matches.len(), 3);
+
+    let m = &matches[1];
+    assert_eq!((m.group_start(0), m.group_end(0)), (5, 9));
+    assert_eq!((m.name("key"), m.name("value"), m.group(3)), (Some("yy"), Some(""), ";"));
+    assert_eq!((m.group_len(1), m.string_count(), m.mark()), (2, 4, Some("pair")));
+
+    let m = &matches[2];
+    assert_eq!((m.group_start(2), m.group_end(2), m.group_bytes(2)), (14, 15, &b"3"[..]));
+    assert_eq!((m.group_start(3), m.group_end(3), m.group_len(3)), (u64::MAX, u64::MAX, 0));
+}
+
+#[test]
+fn test_match_stream_large() {
+    let subject: String = (0..100_000).map(|i| format!("line {}\n", i)).collect();
+    let re = Pcre::compile(r"(?m)^line (\d+)5$").unwrap();
+    let matches = collect(MatchStream::new(&re, subject.as_bytes())).unwrap();
+    assert_eq!(matches.len(), 9_999);
+    let m = re.matches(&subject).last().unwrap();
+    assert_eq!(matches[9_998], (m.group_start(0) as u64, m.group_end(0) as u64, "line 99995".to_string()));
+
+    // A stream over input which is always ready yields to the executor while it searches
+    // many chunks without a match.
+    let subject = format!("{}x", "-".repeat(4 * 1024 * 1024));
+    let re = Pcre::compile("x").unwrap();
+    let mut stream = MatchStream::new(&re, subject.as_bytes());
+    let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+    let mut pending = 0;
+    loop {
+        match Pin::new(&mut stream).poll_next(&mut cx) {
+            Poll::Pending => pending += 1,
+            Poll::Ready(m) => {
+                assert_eq!(m.unwrap().unwrap().group_start(0), subject.len() as u64 - 1);
+                break;
+            }
+        }
+    }
+    assert!(pending >= 2, "{} polls were pending", pending);
+}
+
+#[test]
+fn test_match_stream_errors() {
+    let re = Pcre::compile("a").unwrap();
+    // The matches before the invalid input are found.
+    let mut stream = MatchStream::new(&re, Trickle { data: b"abc\xff", size: 3, ready: false });
+    assert_eq!(block_on(stream.next()).unwrap().unwrap().group_start(0), 0);
+    assert_eq!(block_on(stream.next()).unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert!(block_on(stream.next()).is_none());
+}
@@ -2,7 +2,10 @@ extern crate enum_set;
 extern crate pcre;
 
 use enum_set::{EnumSet};
-use pcre::{CompileOption, Pcre, StudyOption};
+use pcre::{CancellationToken, CompileOption, ExecError, ExecOption, MatchData, OwnedMatch, PartialResult, Pcre, StudyOption};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[test]
 #[should_panic]
@@ -31,7 +34,7 @@ fn test_compile_capture_count() {
 
 #[test]
 fn test_exec_basic() {
-    let mut re = Pcre::compile("^...$").unwrap();
+    let re = Pcre::compile("^...$").unwrap();
     assert_eq!(re.capture_count(), 0);
     let m = re.exec("abc").unwrap();
     assert_eq!(m.group(0), "abc");
@@ -39,21 +42,21 @@ fn test_exec_basic() {
 
 #[test]
 fn test_exec_no_match() {
-    let mut re = Pcre::compile("abc").unwrap();
+    let re = Pcre::compile("abc").unwrap();
     assert!(re.exec("def").is_none());
 }
 
 #[test]
 fn test_exec_nul_byte() {
     // Nul bytes *are* allowed in subject strings, however.
-    let mut re = Pcre::compile("abc\\0def").unwrap();
+    let re = Pcre::compile("abc\\0def").unwrap();
     let m = re.exec("abc\0def").unwrap();
     assert_eq!(m.group(0), "abc\0def");
 }
 
 #[test]
 fn test_exec_from_basic() {
-    let mut re = Pcre::compile("abc").unwrap();
+    let re = Pcre::compile("abc").unwrap();
     let subject = "abcabc";
     let m1 = re.exec_from(subject, 1).unwrap();
     assert_eq!(m1.group_start(0), 3);
@@ -79,7 +82,7 @@ fn test_study_basic() {
 #[test]
 fn test_matches_basic() {
     let subject = "\0abc1111abcabc___ababc+a";
-    let mut re = Pcre::compile("abc").unwrap();
+    let re = Pcre::compile("abc").unwrap();
     let mut it = re.matches(subject);
 
     let mut opt_m = it.next();
@@ -114,6 +117,7 @@ fn test_matches_basic() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_extra_mark() {
     let pattern = "X(*MARK:A)Y|X(*MARK:B)Z";
     let subject1 = "XY";
@@ -134,13 +138,11 @@ fn test_extra_mark() {
     // Double check to make sure the study worked
     assert!(study);
 
-    // Now after studying, we still should not be able to get the mark (since we still need 
-    // to set the option in the extra AND execute it)
+    // Now after studying, we still should not be able to get the mark
     assert_eq!(re.mark(), None);
 
-    // set that I am using the extra mark field
+    // set that I am using the mark of the last match
     let extra = re.enable_mark();
-    // This will fail only if I didn't study first
     assert!(extra);
 
     // We still haven't run the pcre_exec yet so get mark should be None still
@@ -155,9 +157,10 @@ fn test_extra_mark() {
     assert_eq!(m1.group(0), "XY");
 
     // and the marked value should be A
-    let mark1 = re.mark();
+    let mark1 = m1.mark();
     assert!(mark1.is_some());
     assert_eq!(mark1.unwrap(), "A");
+    assert_eq!((re.mark(), re.mark_bytes()), (Some("A".to_string()), Some(&b"A"[..])));
 
     let opt_m2 = re.exec(subject2);
     assert!(opt_m2.is_some());
@@ -166,13 +169,56 @@ fn test_extra_mark() {
     // It should match XZ
     assert_eq!(m2.group(0), "XZ");
 
-    // and the marked value should be B
+    // and the marked value should be B, without changing the mark of the first match
+    assert_eq!(m2.mark().unwrap(), "B");
+    assert_eq!(m1.mark().unwrap(), "A");
+    assert_eq!(re.mark().unwrap(), "B");
+
+    // Every way of matching records the mark, including failed matches.
+    assert!(re.is_match("XY"));
+    assert_eq!(re.mark().unwrap(), "A");
+    assert!(re.exec("XQ").is_none());
+    assert_eq!(re.mark().unwrap(), "B");
+    assert!(re.matches("XZ XY").next().is_some());
     assert_eq!(re.mark().unwrap(), "B");
+    assert!(re.exec("Q").is_none());
+    assert_eq!(re.mark(), None);
+}
+
+#[test]
+fn test_mark_without_study() {
+    let re = Pcre::compile("X(*MARK:A)Y|X(*MARK:B)Z|W").unwrap();
+    assert_eq!(re.exec("XZ").unwrap().mark_bytes(), Some(&b"B"[..]));
+    assert_eq!(re.exec("W").unwrap().mark(), None);
+
+    let marks: Vec<Option<String>> = re.matches("XY W XZ").map(|m| m.mark().map(|s| s.to_string())).collect();
+    assert_eq!(marks, vec![Some("A".to_string()), None, Some("B".to_string())]);
+
+    let mut data = re.match_data();
+    let mut marks = Vec::new();
+    {
+        let mut it = re.matches_into("XZ XY", &mut data);
+        while let Some(caps) = it.next_match() {
+            marks.push(caps.mark().unwrap().to_string());
+        }
+    }
+    assert_eq!(marks, vec!["B", "A"]);
+}
+
+#[test]
+fn test_mark_no_match() {
+    let re = Pcre::compile("X(*MARK:A)Y|X(*MARK:B)Z").unwrap();
+    let mut data = re.match_data();
+    assert!(re.exec_into("XQ", 0, &mut data).is_none());
+    assert_eq!(data.mark(), Some("B"));
+    assert!(re.exec_into("Q", 0, &mut data).is_none());
+    assert_eq!(data.mark(), None);
 }
 
 #[test]
+#[allow(clippy::legacy_numeric_constants)]
 fn test_optional_capture() {
-    let mut re = Pcre::compile("(foo)?bar").unwrap();
+    let re = Pcre::compile("(foo)?bar").unwrap();
     let subject = "bar";
     let m1 = re.exec(subject).unwrap();
     assert!(m1.group_start(0) == 0 && m1.group_end(0) == 3 && m1.group_len(0) == 3);  // bar
@@ -181,3 +227,274 @@ fn test_optional_capture() {
     assert_eq!(m1.group_start(1), usize::max_value());  // c_int -1
     assert_eq!(m1.group_end(1), usize::max_value());  // c_int -1
 }
+
+#[test]
+fn test_exec_with_deadline_timed_out() {
+    let mut compile_options: EnumSet<CompileOption> = EnumSet::new();
+    compile_options.insert(CompileOption::AutoCallout);
+    let mut re = Pcre::compile_with_options("^(a+)+$", &compile_options).unwrap();
+    re.study_with_options(&EnumSet::new());
+    re.extra().unwrap().set_match_limit(u32::MAX);
+
+    let subject = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa!";
+    let deadline = Instant::now() + Duration::from_millis(20);
+    assert_eq!(re.exec_with_deadline(subject, deadline).err(), Some(ExecError::TimedOut));
+
+    // The pattern can still be used after a match has been abandoned.
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let m = re.exec_with_deadline("aaa", deadline).unwrap().unwrap();
+    assert_eq!(m.group(1), "aaa");
+    assert_eq!(re.exec("aaaa").unwrap().group(0), "aaaa");
+}
+
+#[test]
+fn test_exec_with_cancellation() {
+    let mut compile_options: EnumSet<CompileOption> = EnumSet::new();
+    compile_options.insert(CompileOption::AutoCallout);
+    let mut re = Pcre::compile_with_options("^(a+)+$", &compile_options).unwrap();
+    re.study_with_options(&EnumSet::new());
+    re.extra().unwrap().set_match_limit(u32::MAX);
+
+    let token = CancellationToken::new();
+    assert_eq!(re.exec_with_cancellation("aa", &token).unwrap().unwrap().group(0), "aa");
+
+    let canceller = {
+        let token = token.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            token.cancel();
+        })
+    };
+    let subject = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa!";
+    assert_eq!(re.exec_with_cancellation(subject, &token).err(), Some(ExecError::Cancelled));
+    canceller.join().unwrap();
+    assert!(token.is_cancelled());
+}
+
+#[test]
+fn test_exec_with_deadline_without_auto_callout() {
+    // The deadline is checked even though the pattern was compiled without callouts.
+    let mut re = Pcre::compile("^(a+)+$").unwrap();
+    re.study_with_options(&EnumSet::new());
+    re.extra().unwrap().set_match_limit(u32::MAX);
+
+    let subject = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa!";
+    let deadline = Instant::now() + Duration::from_millis(20);
+    assert_eq!(re.exec_with_deadline(subject, deadline).err(), Some(ExecError::TimedOut));
+
+    let m = re.exec_with_deadline("aaa", Instant::now() + Duration::from_secs(60)).unwrap().unwrap();
+    assert_eq!(m.group(1), "aaa");
+    assert!(re.exec("xaaa").is_none());
+}
+
+fn match_spans(pattern: &str, compile_options: &EnumSet<CompileOption>, subject: &str) -> Vec<(usize, usize)> {
+    let re = Pcre::compile_with_options(pattern, compile_options).unwrap();
+    let spans = re.matches(subject).map(|m| (m.group_start(0), m.group_end(0))).collect();
+    spans
+}
+
+#[test]
+fn test_matches_empty() {
+    let no_options: EnumSet<CompileOption> = EnumSet::new();
+    // Same results as Perl's `/g` modifier.
+    assert_eq!(match_spans("a*", &no_options, "baaab"), vec![(0, 0), (1, 4), (4, 4), (5, 5)]);
+    assert_eq!(match_spans("a*", &no_options, ""), vec![(0, 0)]);
+    assert_eq!(match_spans("\\b", &no_options, "ab cd"), vec![(0, 0), (2, 2), (3, 3), (5, 5)]);
+    assert_eq!(match_spans("(?=x)", &no_options, "axbx"), vec![(1, 1), (3, 3)]);
+    assert_eq!(match_spans("x*", &no_options, "\u{e9}\u{20ac}"), vec![(0, 0), (2, 2), (5, 5)]);
+}
+
+#[test]
+fn test_matches_empty_crlf() {
+    let mut crlf: EnumSet<CompileOption> = EnumSet::new();
+    crlf.insert(CompileOption::NewlineCRLF);
+    assert_eq!(match_spans("", &crlf, "a\r\nb"), vec![(0, 0), (1, 1), (3, 3), (4, 4)]);
+    assert_eq!(match_spans("(*CRLF)", &EnumSet::new(), "a\r\nb"), vec![(0, 0), (1, 1), (3, 3), (4, 4)]);
+
+    let mut lf: EnumSet<CompileOption> = EnumSet::new();
+    lf.insert(CompileOption::NewlineLF);
+    assert_eq!(match_spans("", &lf, "a\r\nb"), vec![(0, 0), (1, 1), (2, 2), (3, 3), (4, 4)]);
+}
+
+#[test]
+fn test_exec_into() {
+    let re = Pcre::compile("(\\d+)-(\\d+)?").unwrap();
+    let mut data = re.match_data();
+    for (subject, first) in [("1-2", "1"), ("x 34-", "34")].iter() {
+        let caps = re.exec_into(subject, 0, &mut data).unwrap();
+        assert_eq!(caps.group(1), *first);
+        assert_eq!(caps.group_bytes(1), first.as_bytes());
+    }
+    assert_eq!(data.string_count(), 2);
+    assert_eq!(data.group_start(1), 2);
+    assert!(re.exec_into("none", 0, &mut data).is_none());
+    assert_eq!(data.string_count(), 0);
+
+    // A buffer which is too small grows as needed.
+    let mut data = MatchData::new();
+    let caps = re.exec_into("5-6", 0, &mut data).unwrap();
+    assert_eq!(caps.group(2), "6");
+    assert_eq!(caps.string_count(), 3);
+}
+
+#[test]
+fn test_exec_into_options() {
+    let mut options: EnumSet<ExecOption> = EnumSet::new();
+    options.insert(ExecOption::ExecAnchored);
+    let mut data = MatchData::with_options(&options);
+    let re = Pcre::compile("b").unwrap();
+    assert!(re.exec_into("ab", 0, &mut data).is_none());
+    assert!(re.exec_into("ab", 1, &mut data).is_some());
+    data.set_options(&EnumSet::new());
+    assert!(re.exec_into("ab", 0, &mut data).is_some());
+}
+
+#[test]
+fn test_matches_into() {
+    let re = Pcre::compile("a*").unwrap();
+    let mut data = re.match_data();
+    let mut spans = Vec::new();
+    {
+        let mut it = re.matches_into("baaab", &mut data);
+        while let Some(caps) = it.next_match() {
+            spans.push((caps.group_start(0), caps.group_end(0)));
+        }
+    }
+    assert_eq!(spans, vec![(0, 0), (1, 4), (4, 4), (5, 5)]);
+}
+
+#[test]
+fn test_matches_into_match_limit() {
+    let re = Pcre::builder(r"\d|(a+)+$").match_limit(1000).build().unwrap();
+    let mut data = re.match_data();
+    let mut it = re.matches_into("1aaaaaaaaaaaaaaaaaaaaaaaab", &mut data);
+    assert_eq!(it.try_next_match().unwrap().map(|caps| caps.group_start(0)), Some(0));
+    assert_eq!(it.try_next_match().err(), Some(ExecError::MatchLimit));
+    assert!(it.try_next_match().unwrap().is_none());
+}
+
+#[test]
+fn test_is_match() {
+    let re = Pcre::compile("(a)(b)\\2").unwrap();
+    assert!(re.is_match("xabb"));
+    assert!(!re.is_match("xab"));
+}
+
+#[test]
+fn test_match_name() {
+    let re = Pcre::compile("(?J)(?:(?<n>a+)|(?<n>b+))(?<c>c)?").unwrap();
+    let m = re.exec("bb").unwrap();
+    assert_eq!(m.name("n"), Some("bb"));
+    assert_eq!(m.name("c"), None);
+    assert_eq!(m.name("x"), None);
+    assert_eq!(re.matches("aac").map(|m| m.name("c")).collect::<Vec<_>>(), vec![Some("c")]);
+}
+
+#[test]
+fn test_exec_partial() {
+    let re = Pcre::compile(r"(?<=ab)cd|xyz").unwrap();
+    let no_options = EnumSet::new();
+    match re.exec_partial("abc", 0, &no_options) {
+        PartialResult::Partial { start, match_start } => assert_eq!((start, match_start), (0, 2)),
+        _ => panic!("expected a partial match")
+    }
+    match re.exec_partial("abcd", 0, &no_options) {
+        PartialResult::Match(m) => assert_eq!(m.group(0), "cd"),
+        _ => panic!("expected a match")
+    }
+    assert!(matches!(Pcre::compile("abc").unwrap().exec_partial("xab", 0, &no_options), PartialResult::Partial { start: 1, .. }));
+    assert!(matches!(Pcre::compile("abc").unwrap().exec_partial("xbd", 0, &no_options), PartialResult::NoMatch));
+
+    // A soft partial match loses to a later complete match; a hard one does not.
+    let re = Pcre::compile(r"dog(sbody)?").unwrap();
+    assert!(matches!(re.exec_partial("dogsb", 0, &no_options), PartialResult::Match(_)));
+    let mut hard = EnumSet::new();
+    hard.insert(ExecOption::ExecPartialHard);
+    assert!(matches!(re.exec_partial("dogsb", 0, &hard), PartialResult::Partial { .. }));
+}
+
+#[test]
+fn test_matches_keep_in_lookbehind() {
+    // Each match ends at or before the offset it was found from.
+    let re = Pcre::compile(r"(?<=\Ka)").unwrap();
+    let ends: Vec<usize> = re.matches("aaa").map(|m| m.group_end(0)).collect();
+    assert_eq!(ends, vec![1, 1, 2, 3]);
+}
+
+#[test]
+fn test_owned_match() {
+    let re = Pcre::compile(r"(?<key>\w+)=(?<value>\d+)?(*MARK:kv)").unwrap();
+
+    // The match outlives the buffer it was found in.
+    let m = {
+        let buffer = String::from("  x=42");
+        re.exec(&buffer).unwrap().into_owned()
+    };
+    assert_eq!((m.group_start(0), m.group_end(0), m.group_len(0)), (2, 6, 4));
+    assert_eq!((m.group(1), m.group_bytes(2), m.name("value")), ("x", &b"42"[..], Some("42")));
+    assert_eq!((m.string_count(), m.mark(), m.subject()), (3, Some("kv"), "  x=42"));
+    assert_eq!(m.as_match().group(0), "x=42");
+
+    // An unset group.
+    let m = re.exec_owned(Box::<str>::from("y=")).unwrap();
+    assert_eq!((m.group_start(2), m.name("value"), m.string_count()), (usize::MAX, None, 2));
+    assert_eq!(&*m.into_subject(), "y=");
+
+    // Matches which share their subject can be sent to another thread.
+    let subject: Arc<str> = Arc::from("a=1 b=2 c=");
+    let matches: Vec<OwnedMatch<Arc<str>>> = re.matches(&subject).map(|m| m.with_subject(subject.clone())).collect();
+    let keys = thread::spawn(move || matches.iter().map(|m| m.name("key").unwrap().to_string()).collect::<Vec<_>>()).join().unwrap();
+    assert_eq!(keys, vec!["a", "b", "c"]);
+}
+
+#[test]
+#[should_panic]
+fn test_owned_match_other_subject() {
+    let re = Pcre::compile("a").unwrap();
+    re.exec("a").unwrap().with_subject("b");
+}
+
+#[test]
+fn test_exec_in() {
+    // Lookbehind and `\b` see the text before the window.
+    let re = Pcre::compile(r"(?<=\$)\d+|\b[a-z]+").unwrap();
+    assert_eq!(re.exec_in("cost $42 total", 1..14).unwrap().group(0), "42");
+    assert_eq!(re.exec_in("cost $42 total", 6..14).unwrap().group(0), "42");
+    assert_eq!(re.exec_in("cost $42 total", 7..14).unwrap().group(0), "total");
+    assert!(re.exec_in("cost $42 total", 10..14).is_none());
+    assert_eq!(re.exec_in("cost $42 total", 9..13).unwrap().group(0), "tota");
+
+    // Matches are cut off at the end of the window, and the offsets are in the subject.
+    let m = Pcre::compile(r"\d+").unwrap().exec_in("12345", 1..3).unwrap();
+    assert_eq!((m.group_start(0), m.group_end(0), m.group(0)), (1, 3, "23"));
+    assert!(Pcre::compile(r"abc").unwrap().exec_in("xabcx", 1..3).is_none());
+    assert!(Pcre::compile(r"a").unwrap().exec_in("xabcx", 2..2).is_none());
+
+    // `$` matches at the end of the window only if it is the end of the subject.
+    let re = Pcre::compile(r"\w$").unwrap();
+    assert!(re.exec_in("ab", 0..1).is_none());
+    assert_eq!(re.exec_in("ab", 0..2).unwrap().group(0), "b");
+    let re = Pcre::compile(r"(?m)\w$").unwrap();
+    assert_eq!(re.exec_in("a\nb\nc", 0..4).unwrap().group(0), "a");
+
+    // `$` also matches before a `\n` at the end of the window where it would in the
+    // subject: before the final newline, or before any newline in multiline mode.
+    let re = Pcre::compile(r"a$").unwrap();
+    assert_eq!(re.exec("a\n").map(|m| m.group_end(0)), Some(1));
+    assert_eq!(re.exec_in("a\n", 0..1).map(|m| m.group_end(0)), Some(1));
+    assert!(re.exec_in("a\nb", 0..1).is_none());
+    assert!(Pcre::builder(r"a$").dollar_end_only(true).build().unwrap().exec_in("a\n", 0..1).is_none());
+    let re = Pcre::builder(r"a$").multiline(true).build().unwrap();
+    assert_eq!(re.exec_in("a\nb", 0..1).map(|m| m.group_end(0)), Some(1));
+    assert!(re.exec_in("ab\n", 0..1).is_none());
+
+    // `^` sees that the window starts in the middle of a line.
+    let re = Pcre::compile(r"(?m)^\w").unwrap();
+    assert_eq!(re.exec_in("ab\ncd", 1..5).unwrap().group(0), "c");
+}
+
+#[test]
+#[should_panic]
+fn test_exec_in_not_on_boundary() {
+    Pcre::compile("a").unwrap().exec_in("aé", 0..2);
+}